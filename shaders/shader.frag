@@ -1,14 +1,51 @@
 #version 450
 
-layout(binding = 1) uniform sampler2D texSampler;
+layout(binding = 0) uniform UniformBufferObject {
+    mat4 model;
+    mat4 view;
+    mat4 proj;
+    mat4 outlineModel;
+    vec4 cameraPos;
+    mat4 gizmo;
+    float exposure;
+    float gamma;
+} ubo;
+
+// One sampler per texture of the model, the default one first.
+layout(constant_id = 4) const int TEXTURE_COUNT = 16;
+layout(binding = 1) uniform sampler2D texSamplers[TEXTURE_COUNT];
+
+layout(binding = 2) uniform sampler2D depthSampler;
+
+layout(constant_id = 0) const bool HAS_TEX_COORDS = true;
+// Draw the model in `pcs.solidColor`, ignoring its materials and texture.
+layout(constant_id = 1) const bool SOLID_COLOR = false;
+// Draw the normals as colors, ignoring the materials and texture.
+layout(constant_id = 2) const bool NORMAL_COLOR = false;
+// The depth buffer holds 1 on the near plane and 0 on the far one.
+layout(constant_id = 3) const bool REVERSE_Z = false;
 
 layout(push_constant) uniform PushConstants {
+    vec3 wireframeColor;
     uint colorMode;
+    vec3 solidColor;
+    float pointSize;
+    uint wireframe;
+    uint outline;
+    uint gizmo;
+    uint textureIndex;
+    uint depthView;
+    float brightness;
 } pcs;
 
 layout(location = 0) in vec3 fragColor;
 layout(location = 1) in vec2 fragTexCoord;
 layout(location = 2) in flat uint index;
+layout(location = 3) in vec3 fragEmissive;
+layout(location = 4) in vec3 fragNormal;
+layout(location = 5) in vec3 fragViewDir;
+layout(location = 6) in vec3 fragSpecular;
+layout(location = 7) in float fragShininess;
 
 layout(location = 0) out vec4 outColor;
 
@@ -19,10 +56,59 @@ const vec3 colors[4] = vec3[4](
     vec3(0.2, 0.2, 0.2)
 );
 
+const vec3 LIGHT_DIRECTION = normalize(vec3(1.0, -3.0, -1.0));
+
+// Blinn-Phong specular term, none for materials without a shininess.
+vec3 specular() {
+    if (fragShininess <= 0.0) {
+        return vec3(0.0);
+    }
+    vec3 halfway = normalize(normalize(fragViewDir) - LIGHT_DIRECTION);
+    float intensity = max(dot(normalize(fragNormal), halfway), 0.0);
+    return pow(intensity, fragShininess) * fragSpecular;
+}
+
+const vec3 OUTLINE_COLOR = vec3(1.0, 0.6, 0.0);
+
+// Clipping planes of the projection, `Z_NEAR` and `Z_FAR` in camera.rs.
+const float Z_NEAR = 0.1;
+const float Z_FAR = 100.0;
+
+// Distance from the camera of the depth buffer value, scaled to [0, 1].
+float linearDepth() {
+    float depth = texelFetch(depthSampler, ivec2(gl_FragCoord.xy), 0).r;
+    if (REVERSE_Z) {
+        depth = 1.0 - depth;
+    }
+    float distance = Z_NEAR * Z_FAR / (Z_FAR - depth * (Z_FAR - Z_NEAR));
+    return (distance - Z_NEAR) / (Z_FAR - Z_NEAR);
+}
+
+// Applies the exposure and output gamma to a shaded color.
+vec3 adjust(vec3 color) {
+    return pow(color * ubo.exposure, vec3(1.0 / ubo.gamma));
+}
+
 void main() {
-    if (pcs.colorMode == 0) {
-        outColor = vec4(colors[index % 4], 1.0);
+    if (pcs.depthView != 0) {
+        outColor = vec4(vec3(linearDepth()), 1.0);
+    } else if (pcs.gizmo != 0) {
+        outColor = vec4(fragColor, 1.0);
+    } else if (pcs.outline != 0) {
+        outColor = vec4(OUTLINE_COLOR, 1.0);
+    } else if (pcs.wireframe != 0) {
+        outColor = vec4(pcs.wireframeColor, 1.0);
+    } else if (NORMAL_COLOR) {
+        outColor = vec4(normalize(fragNormal) * 0.5 + 0.5, 1.0);
+    } else if (SOLID_COLOR) {
+        outColor = vec4(pcs.solidColor, 1.0);
+    } else if (pcs.colorMode == 0) {
+        outColor = vec4(adjust(colors[index % 4] + fragEmissive), 1.0);
     } else if (pcs.colorMode == 1) {
-        outColor = texture(texSampler, fragTexCoord) * vec4(fragColor, 1.0);
+        // Emission is added after the lighting, glowing parts stay lit.
+        vec4 base = HAS_TEX_COORDS ? texture(texSamplers[pcs.textureIndex], fragTexCoord) : vec4(1.0);
+        outColor = base * vec4(fragColor, 1.0);
+        outColor.rgb = adjust(outColor.rgb + specular() + fragEmissive);
     }
+    outColor.rgb *= pcs.brightness;
 }
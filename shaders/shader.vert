@@ -4,23 +4,71 @@ layout(binding = 0) uniform UniformBufferObject {
     mat4 model;
     mat4 view;
     mat4 proj;
+    mat4 outlineModel;
+    vec4 cameraPos;
+    mat4 gizmo;
+    float exposure;
+    float gamma;
 } ubo;
 
+layout(push_constant) uniform PushConstants {
+    vec3 wireframeColor;
+    uint colorMode;
+    vec3 solidColor;
+    float pointSize;
+    uint wireframe;
+    uint outline;
+    uint gizmo;
+    uint textureIndex;
+    uint depthView;
+    float brightness;
+} pcs;
+
 layout(location = 0) in vec3 inPosition;
 layout(location = 1) in vec3 inColor;
 layout(location = 2) in vec2 inTexCoord;
+layout(location = 3) in vec3 inEmissive;
+layout(location = 4) in vec3 inSpecular;
+layout(location = 5) in float inShininess;
+layout(location = 6) in vec3 inNormal;
 
 layout(location = 0) out vec3 fragColor;
 layout(location = 1) out vec2 fragTexCoord;
 layout(location = 2) out uint fragID;
+layout(location = 3) out vec3 fragEmissive;
+layout(location = 4) out vec3 fragNormal;
+layout(location = 5) out vec3 fragViewDir;
+layout(location = 6) out vec3 fragSpecular;
+layout(location = 7) out float fragShininess;
 
 const vec3 LIGHT_DIRECTION = normalize(vec3(1.0, -3.0, -1.0));
 
 void main() {
-    gl_Position = ubo.proj * ubo.view * ubo.model * vec4(inPosition, 1.0);
-    vec3 normal = normalize(mat3(transpose(inverse(ubo.model))) * inPosition);
+    mat4 model = pcs.outline != 0 ? ubo.outlineModel : ubo.model;
+    gl_Position = ubo.proj * ubo.view * model * vec4(inPosition, 1.0);
+    gl_PointSize = pcs.pointSize;
+    // Models without normals are lit as if they were spheres.
+    vec3 objectNormal = length(inNormal) > 0.0 ? inNormal : inPosition;
+    vec3 normal = normalize(mat3(transpose(inverse(ubo.model))) * objectNormal);
     float intensity = dot(normal, -LIGHT_DIRECTION);
     fragColor = clamp(intensity, 0.2, 1.0) * inColor;
     fragTexCoord = inTexCoord;
     fragID = gl_VertexIndex;
+    fragEmissive = inEmissive;
+    fragNormal = normal;
+    fragViewDir = ubo.cameraPos.xyz - (model * vec4(inPosition, 1.0)).xyz;
+    fragSpecular = inSpecular;
+    fragShininess = inShininess;
+
+    // The axis gizmo only follows the camera rotation and is unlit.
+    if (pcs.gizmo != 0) {
+        gl_Position = ubo.gizmo * vec4(inPosition, 1.0);
+        fragColor = inColor;
+    }
+
+    // Fullscreen triangle covering the viewport for the depth view.
+    if (pcs.depthView != 0) {
+        vec2 uv = vec2((gl_VertexIndex << 1) & 2, gl_VertexIndex & 2);
+        gl_Position = vec4(uv * 2.0 - 1.0, 0.0, 1.0);
+    }
 }
@@ -0,0 +1,152 @@
+use anyhow::{anyhow, Result};
+
+use crate::math::{vec2, Vec2};
+
+/// Range the initial zoom is clamped to.
+const ZOOM_RANGE: (f32, f32) = (0.1, 10.0);
+/// Smallest window width or height accepted.
+const MIN_WINDOW_SIZE: u32 = 64;
+
+/// Command line arguments. Options may appear anywhere among the OBJ and
+/// texture paths.
+#[derive(Clone, Debug, Default)]
+pub struct Args {
+    pub obj_path: Option<String>,
+    pub texture_path: Option<String>,
+    /// `--z-up` renders models exported with Z as the up axis upright.
+    pub z_up: bool,
+    /// `--max-fps N` or `--max-fps=N` caps the frame rate, overriding
+    /// `SCOP_MAX_FPS`.
+    pub max_fps: Option<String>,
+    /// `--rot x,y` sets the initial orbit angles, in degrees.
+    pub rotation: Option<Vec2>,
+    /// `--zoom z` sets the initial zoom.
+    pub zoom: Option<f32>,
+    /// `--width w` and `--height h` set the initial window size.
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+}
+
+impl Args {
+    /// Parses the arguments following the program name.
+    pub fn parse(mut args: impl Iterator<Item = String>) -> Result<Self> {
+        let mut parsed = Args::default();
+        let mut paths = Vec::new();
+        while let Some(arg) = args.next() {
+            if arg == "--z-up" {
+                parsed.z_up = true;
+            } else if let Some(value) = arg.strip_prefix("--max-fps=") {
+                parsed.max_fps = Some(value.to_owned());
+            } else if arg == "--max-fps" {
+                let value = args
+                    .next()
+                    .ok_or_else(|| anyhow!("Missing --max-fps value"))?;
+                parsed.max_fps = Some(value);
+            } else if arg == "--rot" {
+                let value = args.next().ok_or_else(|| anyhow!("Missing --rot value"))?;
+                parsed.rotation = Some(parse_rotation(&value)?);
+            } else if arg == "--zoom" {
+                let value = args.next().ok_or_else(|| anyhow!("Missing --zoom value"))?;
+                parsed.zoom = Some(parse_zoom(&value)?);
+            } else if arg == "--width" || arg == "--height" {
+                let value = args
+                    .next()
+                    .ok_or_else(|| anyhow!("Missing {} value", arg))?;
+                let size = Some(parse_window_size(&arg, &value)?);
+                if arg == "--width" {
+                    parsed.width = size;
+                } else {
+                    parsed.height = size;
+                }
+            } else {
+                paths.push(arg);
+            }
+        }
+
+        let mut paths = paths.into_iter();
+        parsed.obj_path = paths.next();
+        // Without a texture, the material ones or a generated one are used.
+        parsed.texture_path = paths.next();
+        Ok(parsed)
+    }
+}
+
+/// Parses `x,y` orbit angles in degrees. The horizontal angle is wrapped to
+/// [0, 360) and the vertical one clamped to [0, 180].
+fn parse_rotation(value: &str) -> Result<Vec2> {
+    let invalid = || anyhow!("Invalid --rot value: {}", value);
+    let (x, y) = value.split_once(',').ok_or_else(invalid)?;
+    let x = x.trim().parse::<f32>().map_err(|_| invalid())?;
+    let y = y.trim().parse::<f32>().map_err(|_| invalid())?;
+    if !x.is_finite() || !y.is_finite() {
+        return Err(invalid());
+    }
+    Ok(vec2(x.rem_euclid(360.0), y.clamp(0.0, 180.0)))
+}
+
+/// Parses a positive zoom factor, clamped to `ZOOM_RANGE`.
+fn parse_zoom(value: &str) -> Result<f32> {
+    match value.trim().parse::<f32>() {
+        Ok(zoom) if zoom > 0.0 && zoom.is_finite() => Ok(zoom.clamp(ZOOM_RANGE.0, ZOOM_RANGE.1)),
+        _ => Err(anyhow!("Invalid --zoom value: {}", value)),
+    }
+}
+
+/// Parses the window size given to `flag`, raised to `MIN_WINDOW_SIZE`.
+fn parse_window_size(flag: &str, value: &str) -> Result<u32> {
+    let size = value
+        .trim()
+        .parse::<u32>()
+        .map_err(|_| anyhow!("Invalid {} value: {}", flag, value))?;
+    Ok(size.max(MIN_WINDOW_SIZE))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Args> {
+        Args::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn paths_and_options_in_any_order() {
+        let args = parse(&["--z-up", "model.obj", "--zoom", "2", "texture.png"]).unwrap();
+        assert_eq!(args.obj_path.as_deref(), Some("model.obj"));
+        assert_eq!(args.texture_path.as_deref(), Some("texture.png"));
+        assert!(args.z_up);
+        assert_eq!(args.zoom, Some(2.0));
+    }
+
+    #[test]
+    fn max_fps_with_or_without_equals() {
+        let joined = parse(&["--max-fps=30"]).unwrap();
+        let separate = parse(&["--max-fps", "30"]).unwrap();
+        assert_eq!(joined.max_fps.as_deref(), Some("30"));
+        assert_eq!(separate.max_fps.as_deref(), Some("30"));
+        assert!(parse(&["--max-fps"]).is_err());
+    }
+
+    #[test]
+    fn rotation_is_wrapped_and_clamped() {
+        let args = parse(&["--rot", "-90,200"]).unwrap();
+        assert_eq!(args.rotation, Some(vec2(270.0, 180.0)));
+        assert!(parse(&["--rot", "90"]).is_err());
+        assert!(parse(&["--rot", "inf,0"]).is_err());
+    }
+
+    #[test]
+    fn zoom_is_clamped() {
+        assert_eq!(parse(&["--zoom", "100"]).unwrap().zoom, Some(ZOOM_RANGE.1));
+        assert!(parse(&["--zoom", "0"]).is_err());
+        assert!(parse(&["--zoom"]).is_err());
+    }
+
+    #[test]
+    fn window_size_is_raised_to_the_minimum() {
+        let args = parse(&["--width", "10", "--height", "600"]).unwrap();
+        assert_eq!(args.width, Some(MIN_WINDOW_SIZE));
+        assert_eq!(args.height, Some(600));
+        assert!(parse(&["--width", "wide"]).is_err());
+    }
+}
@@ -0,0 +1,117 @@
+use anyhow::{Ok, Result};
+use vulkanalia::prelude::v1_2::*;
+
+use crate::math::{vec2, vec3, Vec3};
+use crate::vertex::{upload_buffer, Vertex};
+use crate::AppData;
+
+pub const BOUNDS_COLOR: Vec3 = vec3(0.0, 1.0, 1.0);
+
+/// Returns the smallest axis aligned box containing `vertices`, as its
+/// minimum and maximum corners.
+pub fn bounding_box(vertices: &[Vertex]) -> (Vec3, Vec3) {
+    let Some(first) = vertices.first() else {
+        return (Vec3::default(), Vec3::default());
+    };
+    let (mut min, mut max) = (first.pos, first.pos);
+    for vertex in vertices {
+        let pos = vertex.pos;
+        min = vec3(min.x.min(pos.x), min.y.min(pos.y), min.z.min(pos.z));
+        max = vec3(max.x.max(pos.x), max.y.max(pos.y), max.z.max(pos.z));
+    }
+    (min, max)
+}
+
+/// The 12 edges of the box from `min` to `max`, as a line list.
+pub fn box_lines(min: Vec3, max: Vec3) -> [Vertex; 24] {
+    // Corner `i` takes its X, Y and Z from `max` when bit 0, 1 and 2 are set.
+    let corner = |i: usize| {
+        vec3(
+            if i & 1 != 0 { max.x } else { min.x },
+            if i & 2 != 0 { max.y } else { min.y },
+            if i & 4 != 0 { max.z } else { min.z },
+        )
+    };
+    // Edges join the corners differing by a single bit.
+    let mut edges = [(0, 0); 12];
+    let mut e = 0;
+    for i in 0..8 {
+        for bit in [1, 2, 4] {
+            if i & bit == 0 {
+                edges[e] = (i, i | bit);
+                e += 1;
+            }
+        }
+    }
+
+    let zero = vec3(0.0, 0.0, 0.0);
+    let vertex = |pos| Vertex::new(pos, BOUNDS_COLOR, vec2(0.0, 0.0), zero, zero, 0.0, zero);
+    let mut lines = [vertex(zero); 24];
+    for (e, (a, b)) in edges.into_iter().enumerate() {
+        lines[2 * e] = vertex(corner(a));
+        lines[2 * e + 1] = vertex(corner(b));
+    }
+    lines
+}
+
+/// Uploads the lines of the bounding box of the loaded model.
+pub unsafe fn create_bounds_buffer(
+    instance: &Instance,
+    device: &Device,
+    data: &mut AppData,
+) -> Result<()> {
+    let (min, max) = bounding_box(&data.vertices);
+    let (buffer, memory) = upload_buffer(
+        instance,
+        device,
+        data,
+        &box_lines(min, max),
+        vk::BufferUsageFlags::VERTEX_BUFFER,
+    )?;
+    data.bounds_vertex_buffer = buffer;
+    data.bounds_vertex_buffer_memory = memory;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn box_has_twelve_edges_between_its_corners() {
+        let (min, max) = (vec3(-1.0, -2.0, -3.0), vec3(1.0, 2.0, 3.0));
+        let lines = box_lines(min, max);
+
+        let is_corner = |p: Vec3| {
+            [p.x, p.y, p.z]
+                .iter()
+                .zip([(min.x, max.x), (min.y, max.y), (min.z, max.z)])
+                .all(|(&c, (lo, hi))| c == lo || c == hi)
+        };
+        let mut edges = Vec::new();
+        for line in lines.chunks_exact(2) {
+            let (a, b) = (line[0].pos, line[1].pos);
+            assert!(is_corner(a) && is_corner(b));
+            // Edges run along a single axis.
+            let differing = [a.x != b.x, a.y != b.y, a.z != b.z];
+            assert_eq!(differing.iter().filter(|&&d| d).count(), 1);
+            edges.push(format!("{:?}", [a, b]));
+        }
+        edges.sort();
+        edges.dedup();
+        assert_eq!(edges.len(), 12);
+    }
+
+    #[test]
+    fn bounding_box_of_vertices() {
+        let zero = vec3(0.0, 0.0, 0.0);
+        let vertex =
+            |x, y, z| Vertex::new(vec3(x, y, z), zero, vec2(0.0, 0.0), zero, zero, 0.0, zero);
+        let vertices = [vertex(1.0, -2.0, 0.5), vertex(-1.0, 3.0, 0.0)];
+        assert_eq!(
+            bounding_box(&vertices),
+            (vec3(-1.0, -2.0, 0.0), vec3(1.0, 3.0, 0.5))
+        );
+    }
+}
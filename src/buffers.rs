@@ -1,9 +1,18 @@
-use anyhow::{Ok, Result};
+use anyhow::Result;
+
+use std::mem::size_of;
 
 use vulkanalia::prelude::v1_2::*;
 
+use crate::bounds::BOUNDS_COLOR;
+use crate::depth::{clear_depth, has_stencil_component};
 use crate::device::{get_memory_type_index, QueueFamilyIndices};
-use crate::{AppData, MAX_FRAMES_IN_FLIGHT};
+use crate::gizmo::GIZMO_VERTICES;
+use crate::pipeline::PushConstants;
+use crate::{AppData, MAX_FRAMES_IN_FLIGHT, SOLID_COLORS, WIREFRAME_COLORS};
+
+/// Brightness of the objects other than the active one, when there is one.
+const INACTIVE_BRIGHTNESS: f32 = 0.25;
 
 pub unsafe fn create_framebuffers(device: &Device, data: &mut AppData) -> Result<()> {
     data.framebuffers = data
@@ -30,11 +39,22 @@ pub unsafe fn create_command_pool(
 ) -> Result<()> {
     let indices = QueueFamilyIndices::get(instance, data, data.physical_device)?;
 
+    // Command buffers are recorded again when a setting they bake changes.
     let pool_info = vk::CommandPoolCreateInfo::builder()
-        .flags(vk::CommandPoolCreateFlags::empty())
+        .flags(vk::CommandPoolCreateFlags::RESET_COMMAND_BUFFER)
         .queue_family_index(indices.graphics());
 
     data.command_pool = device.create_command_pool(&pool_info, None)?;
+
+    data.transfer_command_pool = match indices.transfer() {
+        Some(transfer) => {
+            let pool_info = vk::CommandPoolCreateInfo::builder()
+                .flags(vk::CommandPoolCreateFlags::TRANSIENT)
+                .queue_family_index(transfer);
+            device.create_command_pool(&pool_info, None)?
+        }
+        None => vk::CommandPool::null(),
+    };
     Ok(())
 }
 
@@ -45,7 +65,29 @@ pub unsafe fn create_command_buffers(device: &Device, data: &mut AppData) -> Res
         .command_buffer_count(data.framebuffers.len() as u32);
 
     data.command_buffers = device.allocate_command_buffers(&allocate_info)?;
+    record_command_buffers(device, data)
+}
+
+/// Values the color and depth attachments are cleared to at the start of
+/// the render pass.
+fn clear_values(data: &AppData) -> [vk::ClearValue; 2] {
+    let color_clear_value = vk::ClearValue {
+        color: vk::ClearColorValue {
+            float32: [0.0, 0.0, 0.0, 1.0],
+        },
+    };
+    let depth_clear_value = vk::ClearValue {
+        depth_stencil: vk::ClearDepthStencilValue {
+            depth: clear_depth(data.reverse_z),
+            stencil: 0,
+        },
+    };
+    [color_clear_value, depth_clear_value]
+}
 
+/// Records the frame of each framebuffer in `data.command_buffers`, which
+/// are implicitly reset when already recorded.
+pub unsafe fn record_command_buffers(device: &Device, data: &AppData) -> Result<()> {
     for (i, &command_buffer) in data.command_buffers.iter().enumerate() {
         let info = vk::CommandBufferBeginInfo::builder();
 
@@ -55,24 +97,25 @@ pub unsafe fn create_command_buffers(device: &Device, data: &mut AppData) -> Res
             .offset(vk::Offset2D::default())
             .extent(data.swapchain_extent);
 
-        let color_clear_value = vk::ClearValue {
-            color: vk::ClearColorValue {
-                float32: [0.0, 0.0, 0.0, 1.0],
-            },
-        };
-        let depth_clear_value = vk::ClearValue {
-            depth_stencil: vk::ClearDepthStencilValue {
-                depth: 1.0,
-                stencil: 0,
-            },
-        };
-        let clear_values = [color_clear_value, depth_clear_value];
+        let clear_values = clear_values(data);
         let info = vk::RenderPassBeginInfo::builder()
             .render_pass(data.render_pass)
             .framebuffer(data.framebuffers[i])
             .render_area(render_area)
             .clear_values(&clear_values);
 
+        // Bracket the render pass with timestamps to measure its duration.
+        let first_query = 2 * i as u32;
+        if !data.query_pool.is_null() {
+            device.cmd_reset_query_pool(command_buffer, data.query_pool, first_query, 2);
+            device.cmd_write_timestamp(
+                command_buffer,
+                vk::PipelineStageFlags::TOP_OF_PIPE,
+                data.query_pool,
+                first_query,
+            );
+        }
+
         device.cmd_begin_render_pass(command_buffer, &info, vk::SubpassContents::INLINE);
         device.cmd_bind_pipeline(
             command_buffer,
@@ -80,13 +123,24 @@ pub unsafe fn create_command_buffers(device: &Device, data: &mut AppData) -> Res
             data.pipeline,
         );
 
-        // Bind vertex buffer
-        let vertex_buffers = [data.vertex_buffer];
-        let offsets = [0];
-        device.cmd_bind_vertex_buffers(command_buffer, 0, &vertex_buffers, &offsets);
-        device.cmd_bind_index_buffer(command_buffer, data.index_buffer, 0, vk::IndexType::UINT32);
+        // Bind vertex buffer, and the lightmap one when the model has one
+        let vertex_buffers = [data.vertex_buffer, data.lightmap_buffer];
+        let count = if data.has_lightmap_tex_coords { 2 } else { 1 };
+        device.cmd_bind_vertex_buffers(
+            command_buffer,
+            0,
+            &vertex_buffers[..count],
+            &[0, 0][..count],
+        );
 
-        device.cmd_push_constants(command_buffer, data.pipeline_layout, vk::ShaderStageFlags::FRAGMENT, 0, &[data.color_mod as u8, 0, 0, 0]);
+        let push_constants = push_constants(data);
+        device.cmd_push_constants(
+            command_buffer,
+            data.pipeline_layout,
+            vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::FRAGMENT,
+            0,
+            push_constants.as_bytes(),
+        );
 
         device.cmd_bind_descriptor_sets(
             command_buffer,
@@ -96,14 +150,247 @@ pub unsafe fn create_command_buffers(device: &Device, data: &mut AppData) -> Res
             &[data.descriptor_sets[i]],
             &[],
         );
-        device.cmd_draw_indexed(command_buffer, data.indices.len() as u32, 1, 0, 0, 0);
+        if !data.indices.is_empty() {
+            device.cmd_bind_index_buffer(command_buffer, data.index_buffer, 0, data.index_type);
+            // One draw per object, each with its own texture. The draw
+            // parameters are read from the indirect buffer, written every
+            // frame to cull the objects out of view.
+            let stride = size_of::<vk::DrawIndexedIndirectCommand>();
+            for (o, object) in data.objects.iter().enumerate() {
+                if object.index_count == 0 {
+                    continue;
+                }
+                let inactive = data.active_object.is_some_and(|active| active != o);
+                let object_constants = PushConstants {
+                    texture_index: object.texture_index,
+                    brightness: if inactive { INACTIVE_BRIGHTNESS } else { 1.0 },
+                    ..push_constants
+                };
+                device.cmd_push_constants(
+                    command_buffer,
+                    data.pipeline_layout,
+                    vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::FRAGMENT,
+                    0,
+                    object_constants.as_bytes(),
+                );
+                device.cmd_draw_indexed_indirect(
+                    command_buffer,
+                    data.indirect_buffers[i],
+                    (o * stride) as u64,
+                    1,
+                    stride as u32,
+                );
+            }
+        }
+
+        // Point elements, sharing the layout and therefore the bound descriptors.
+        // They belong to no object, the constants of the last one are reset.
+        if !data.point_indices.is_empty() {
+            device.cmd_push_constants(
+                command_buffer,
+                data.pipeline_layout,
+                vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::FRAGMENT,
+                0,
+                push_constants.as_bytes(),
+            );
+            device.cmd_bind_pipeline(
+                command_buffer,
+                vk::PipelineBindPoint::GRAPHICS,
+                data.point_pipeline,
+            );
+            device.cmd_bind_index_buffer(
+                command_buffer,
+                data.point_index_buffer,
+                0,
+                data.index_type,
+            );
+            device.cmd_draw_indexed(command_buffer, data.point_indices.len() as u32, 1, 0, 0, 0);
+        }
+
+        // Outline, drawn around the pixels the model wrote to the stencil buffer.
+        if data.outline && has_stencil_component(data.depth_format) && !data.indices.is_empty() {
+            let outline_constants = PushConstants {
+                outline: 1,
+                ..push_constants
+            };
+            device.cmd_push_constants(
+                command_buffer,
+                data.pipeline_layout,
+                vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::FRAGMENT,
+                0,
+                outline_constants.as_bytes(),
+            );
+            device.cmd_bind_pipeline(
+                command_buffer,
+                vk::PipelineBindPoint::GRAPHICS,
+                data.outline_pipeline,
+            );
+            device.cmd_bind_index_buffer(command_buffer, data.index_buffer, 0, data.index_type);
+            device.cmd_draw_indexed(command_buffer, data.indices.len() as u32, 1, 0, 0, 0);
+        }
+
+        // Bounding box, in the wireframe shading with its own color.
+        if data.show_bounds {
+            let bounds_constants = PushConstants {
+                wireframe_color: BOUNDS_COLOR,
+                wireframe: 1,
+                ..push_constants
+            };
+            device.cmd_push_constants(
+                command_buffer,
+                data.pipeline_layout,
+                vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::FRAGMENT,
+                0,
+                bounds_constants.as_bytes(),
+            );
+            device.cmd_bind_pipeline(
+                command_buffer,
+                vk::PipelineBindPoint::GRAPHICS,
+                data.bounds_pipeline,
+            );
+            device.cmd_bind_vertex_buffers(command_buffer, 0, &[data.bounds_vertex_buffer], &[0]);
+            device.cmd_draw(command_buffer, 24, 1, 0, 0);
+        }
+
+        // Crease and boundary edges, in the wireframe color.
+        if data.show_edges && data.edge_vertex_count > 0 {
+            let edges_constants = PushConstants {
+                wireframe: 1,
+                ..push_constants
+            };
+            device.cmd_push_constants(
+                command_buffer,
+                data.pipeline_layout,
+                vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::FRAGMENT,
+                0,
+                edges_constants.as_bytes(),
+            );
+            device.cmd_bind_pipeline(
+                command_buffer,
+                vk::PipelineBindPoint::GRAPHICS,
+                data.bounds_pipeline,
+            );
+            device.cmd_bind_vertex_buffers(command_buffer, 0, &[data.edges_vertex_buffer], &[0]);
+            device.cmd_draw(command_buffer, data.edge_vertex_count, 1, 0, 0);
+        }
+
+        // Axis gizmo, last so it stays on top.
+        let gizmo_constants = PushConstants {
+            gizmo: 1,
+            ..push_constants
+        };
+        device.cmd_push_constants(
+            command_buffer,
+            data.pipeline_layout,
+            vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::FRAGMENT,
+            0,
+            gizmo_constants.as_bytes(),
+        );
+        device.cmd_bind_pipeline(
+            command_buffer,
+            vk::PipelineBindPoint::GRAPHICS,
+            data.gizmo_pipeline,
+        );
+        device.cmd_bind_vertex_buffers(command_buffer, 0, &[data.gizmo_vertex_buffer], &[0]);
+        device.cmd_draw(command_buffer, GIZMO_VERTICES.len() as u32, 1, 0, 0);
+
+        // Depth view, a fullscreen triangle generated in the vertex shader.
+        // The gizmo vertex buffer stays bound but is ignored.
+        device.cmd_next_subpass(command_buffer, vk::SubpassContents::INLINE);
+        if data.depth_view {
+            let depth_constants = PushConstants {
+                depth_view: 1,
+                ..push_constants
+            };
+            device.cmd_push_constants(
+                command_buffer,
+                data.pipeline_layout,
+                vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::FRAGMENT,
+                0,
+                depth_constants.as_bytes(),
+            );
+            device.cmd_bind_pipeline(
+                command_buffer,
+                vk::PipelineBindPoint::GRAPHICS,
+                data.post_pipeline,
+            );
+            device.cmd_draw(command_buffer, 3, 1, 0, 0);
+        }
+
         device.cmd_end_render_pass(command_buffer);
 
+        if !data.query_pool.is_null() {
+            device.cmd_write_timestamp(
+                command_buffer,
+                vk::PipelineStageFlags::BOTTOM_OF_PIPE,
+                data.query_pool,
+                first_query + 1,
+            );
+        }
+
         device.end_command_buffer(command_buffer)?;
     }
     Ok(())
 }
 
+/// Push constants of the model, the other draws override some of them.
+fn push_constants(data: &AppData) -> PushConstants {
+    PushConstants {
+        wireframe_color: WIREFRAME_COLORS[data.wireframe_color],
+        color_mode: data.color_mod as u32,
+        solid_color: SOLID_COLORS[data.solid_color.unwrap_or_default()],
+        point_size: data.point_size,
+        wireframe: data.wireframe as u32,
+        outline: 0,
+        gizmo: 0,
+        texture_index: 0,
+        depth_view: 0,
+        brightness: 1.0,
+    }
+}
+
+/// Creates, for each swapchain image, a host visible buffer of one indexed
+/// indirect draw command per object.
+pub unsafe fn create_indirect_buffers(
+    instance: &Instance,
+    device: &Device,
+    data: &mut AppData,
+) -> Result<()> {
+    data.indirect_buffers.clear();
+    data.indirect_buffers_memory.clear();
+
+    // Vulkan rejects empty buffers.
+    if data.objects.is_empty() {
+        return Ok(());
+    }
+
+    let size = size_of::<vk::DrawIndexedIndirectCommand>() * data.objects.len();
+    for _ in 0..data.swapchain_images.len() {
+        let (indirect_buffer, indirect_buffer_memory) = create_buffer(
+            instance,
+            device,
+            data,
+            size as u64,
+            vk::BufferUsageFlags::INDIRECT_BUFFER,
+            vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+        )?;
+
+        data.indirect_buffers.push(indirect_buffer);
+        data.indirect_buffers_memory.push(indirect_buffer_memory);
+    }
+
+    Ok(())
+}
+
+pub unsafe fn destroy_indirect_buffers(device: &Device, data: &AppData) {
+    data.indirect_buffers
+        .iter()
+        .for_each(|b| device.destroy_buffer(*b, None));
+    data.indirect_buffers_memory
+        .iter()
+        .for_each(|m| device.free_memory(*m, None));
+}
+
 pub unsafe fn create_sync_objects(device: &Device, data: &mut AppData) -> Result<()> {
     let semaphore_info = vk::SemaphoreCreateInfo::builder();
     let fence_info = vk::FenceCreateInfo::builder().flags(vk::FenceCreateFlags::SIGNALED);
@@ -130,15 +417,28 @@ pub unsafe fn create_sync_objects(device: &Device, data: &mut AppData) -> Result
 pub unsafe fn create_buffer(
     instance: &Instance,
     device: &Device,
-    data: &mut AppData,
+    data: &AppData,
     size: vk::DeviceSize,
     usage: vk::BufferUsageFlags,
     properties: vk::MemoryPropertyFlags,
 ) -> Result<(vk::Buffer, vk::DeviceMemory)> {
+    // Buffers filled on the transfer queue are then read on the graphics one.
+    let families = match data.transfer_family {
+        Some(transfer) if usage.contains(vk::BufferUsageFlags::TRANSFER_DST) => {
+            vec![data.graphics_family, transfer]
+        }
+        _ => vec![],
+    };
+    let sharing_mode = if families.is_empty() {
+        vk::SharingMode::EXCLUSIVE
+    } else {
+        vk::SharingMode::CONCURRENT
+    };
     let buffer_info = vk::BufferCreateInfo::builder()
         .size(size)
         .usage(usage)
-        .sharing_mode(vk::SharingMode::EXCLUSIVE);
+        .sharing_mode(sharing_mode)
+        .queue_family_indices(&families);
 
     let buffer = device.create_buffer(&buffer_info, None)?;
 
@@ -164,7 +464,7 @@ pub unsafe fn copy_buffer(
     destination: vk::Buffer,
     size: vk::DeviceSize,
 ) -> Result<()> {
-    let command_buffer = begin_single_time_commands(device, data)?;
+    let command_buffer = begin_transfer_commands(device, data)?;
 
     let copy_region = vk::BufferCopy::builder()
         .src_offset(0)
@@ -172,18 +472,69 @@ pub unsafe fn copy_buffer(
         .size(size);
     device.cmd_copy_buffer(command_buffer, source, destination, &[copy_region]);
 
-    end_single_time_commands(device, data, command_buffer)?;
+    end_transfer_commands(device, data, command_buffer)?;
 
     Ok(())
 }
 
+/// Queue and command pool of the transfers: the dedicated transfer ones when
+/// there are, the graphics ones otherwise.
+fn transfer_queue(data: &AppData) -> (vk::Queue, vk::CommandPool) {
+    if data.transfer_family.is_some() {
+        (data.transfer_queue, data.transfer_command_pool)
+    } else {
+        (data.graphics_queue, data.command_pool)
+    }
+}
+
+/// Allocates and begins a command buffer of the transfer queue, submitted
+/// and waited for by `end_transfer_commands`. Only copies and barriers
+/// limited to the transfer stage may be recorded in it.
+pub unsafe fn begin_transfer_commands(
+    device: &Device,
+    data: &AppData,
+) -> Result<vk::CommandBuffer> {
+    begin_commands(device, transfer_queue(data).1)
+}
+
+pub unsafe fn end_transfer_commands(
+    device: &Device,
+    data: &AppData,
+    command_buffer: vk::CommandBuffer,
+) -> Result<()> {
+    let (queue, pool) = transfer_queue(data);
+    end_commands(device, queue, pool, command_buffer)
+}
+
+/// Allocates and begins a command buffer of the graphics queue, submitted
+/// and waited for by `end_single_time_commands`.
+///
+/// Used for the work a transfer queue can't do: blits, barriers waited for
+/// by the shader stages and copies out of the swapchain images.
 pub unsafe fn begin_single_time_commands(
     device: &Device,
     data: &AppData,
 ) -> Result<vk::CommandBuffer> {
+    begin_commands(device, data.command_pool)
+}
+
+pub unsafe fn end_single_time_commands(
+    device: &Device,
+    data: &AppData,
+    command_buffer: vk::CommandBuffer,
+) -> Result<()> {
+    end_commands(
+        device,
+        data.graphics_queue,
+        data.command_pool,
+        command_buffer,
+    )
+}
+
+unsafe fn begin_commands(device: &Device, pool: vk::CommandPool) -> Result<vk::CommandBuffer> {
     let allocate_info = vk::CommandBufferAllocateInfo::builder()
         .level(vk::CommandBufferLevel::PRIMARY)
-        .command_pool(data.command_pool)
+        .command_pool(pool)
         .command_buffer_count(1);
 
     let command_buffer = device.allocate_command_buffers(&allocate_info)?[0];
@@ -196,9 +547,10 @@ pub unsafe fn begin_single_time_commands(
     Ok(command_buffer)
 }
 
-pub unsafe fn end_single_time_commands(
+unsafe fn end_commands(
     device: &Device,
-    data: &AppData,
+    queue: vk::Queue,
+    pool: vk::CommandPool,
     command_buffer: vk::CommandBuffer,
 ) -> Result<()> {
     device.end_command_buffer(command_buffer)?;
@@ -206,10 +558,36 @@ pub unsafe fn end_single_time_commands(
     let command_buffers = [command_buffer];
     let submit_info = vk::SubmitInfo::builder().command_buffers(&command_buffers);
 
-    device.queue_submit(data.graphics_queue, &[submit_info], vk::Fence::null())?;
-    device.queue_wait_idle(data.graphics_queue)?;
+    device.queue_submit(queue, &[submit_info], vk::Fence::null())?;
+    device.queue_wait_idle(queue)?;
 
-    device.free_command_buffers(data.command_pool, &command_buffers);
+    device.free_command_buffers(pool, &command_buffers);
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn solid_color_is_pushed() {
+        let mut data = AppData::default();
+        for (index, color) in SOLID_COLORS.iter().enumerate() {
+            data.solid_color = Some(index);
+            assert_eq!(push_constants(&data).solid_color, *color);
+        }
+    }
+
+    #[test]
+    fn color_and_depth_are_cleared() {
+        let mut data = AppData::default();
+        let [color, depth] = clear_values(&data);
+        assert_eq!(unsafe { color.color.float32 }, [0.0, 0.0, 0.0, 1.0]);
+        assert_eq!(unsafe { depth.depth_stencil.depth }, 1.0);
+
+        data.reverse_z = true;
+        let [_, depth] = clear_values(&data);
+        assert_eq!(unsafe { depth.depth_stencil.depth }, 0.0);
+    }
+}
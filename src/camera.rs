@@ -0,0 +1,112 @@
+use crate::descriptor::Mat4;
+use crate::math::{fit_distance, vec3, vulkan_perspective, vulkan_reverse_perspective, Deg, Vec3};
+use crate::Controls;
+
+/// Vertical field of view of the camera.
+pub const FOVY: Deg = Deg(45.0);
+
+/// Clipping planes of the projection.
+pub const Z_NEAR: f32 = 0.1;
+pub const Z_FAR: f32 = 100.0;
+
+/// A perspective camera looking at `target` from `position`.
+#[derive(Copy, Clone, Debug)]
+pub struct Camera {
+    pub position: Vec3,
+    pub target: Vec3,
+    pub up: Vec3,
+    pub fov: Deg,
+    pub near: f32,
+    pub far: f32,
+}
+
+impl Camera {
+    /// Camera orbiting around `target`, following the rotation and zoom of
+    /// the user `controls`.
+    pub fn orbit(controls: &Controls, target: Vec3) -> Camera {
+        let theta_x = controls.rotation.x * (std::f32::consts::PI / 180.0);
+        let theta_y = controls.rotation.y * (std::f32::consts::PI / 180.0);
+        let radius = controls.fit_distance * controls.zoom;
+
+        // The small offset keeps the camera off the up axis.
+        let position = vec3(
+            radius * theta_x.cos() * theta_y.sin() + 0.1,
+            radius * theta_y.cos() + 0.1,
+            radius * theta_x.sin() * theta_y.sin() + 0.1,
+        );
+
+        Camera {
+            position,
+            target,
+            up: vec3(0.0, 1.0, 0.0),
+            fov: FOVY,
+            near: Z_NEAR,
+            far: Z_FAR,
+        }
+    }
+
+    /// Distance from the center of a model of `radius` at which it fits in a
+    /// viewport of the given aspect ratio. It is shortened to keep the far
+    /// side of the model within `Z_FAR`, models larger than the depth range
+    /// are still partly clipped.
+    pub fn fit_distance(radius: f32, aspect: f32) -> f32 {
+        fit_distance(radius, FOVY, aspect)
+            .min(Z_FAR - radius)
+            .max(Z_NEAR)
+    }
+
+    /// Returns the view and projection matrices for a viewport of the given
+    /// aspect ratio, with the depth reversed when `reverse_z` is set.
+    pub fn view_proj(&self, aspect: f32, reverse_z: bool) -> (Mat4, Mat4) {
+        let view = Mat4::look_at_rh(self.position, self.target, self.up);
+        let proj = if reverse_z {
+            vulkan_reverse_perspective(self.fov, aspect, self.near, self.far)
+        } else {
+            vulkan_perspective(self.fov, aspect, self.near, self.far)
+        };
+        (view, proj)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::vec4;
+
+    #[test]
+    fn fit_distance_follows_the_aspect_ratio() {
+        let square = Camera::fit_distance(1.0, 1.0);
+        assert_eq!(Camera::fit_distance(1.0, 2.0), square);
+        // Narrow windows move the camera back to fit the model horizontally.
+        assert!(Camera::fit_distance(1.0, 0.5) > square);
+    }
+
+    #[test]
+    fn fit_distance_keeps_the_model_within_the_far_plane() {
+        for radius in [10.0, 40.0, 60.0] {
+            let distance = Camera::fit_distance(radius, 1.0);
+            assert!(distance + radius <= Z_FAR, "{}", radius);
+        }
+        assert_eq!(Camera::fit_distance(500.0, 1.0), Z_NEAR);
+    }
+
+    #[test]
+    fn view_looks_at_the_target() {
+        let camera = Camera {
+            position: vec3(3.0, 4.0, 5.0),
+            target: vec3(1.0, 2.0, 3.0),
+            up: vec3(0.0, 1.0, 0.0),
+            fov: FOVY,
+            near: Z_NEAR,
+            far: Z_FAR,
+        };
+        let (view, _) = camera.view_proj(1.0, false);
+        let distance = (camera.target - camera.position).magnitude();
+
+        // The target is straight ahead, down -Z in view space.
+        let target = (view * vec4(1.0, 2.0, 3.0, 1.0)).truncate();
+        assert!((target - vec3(0.0, 0.0, -distance)).magnitude() < 1e-5);
+        let eye = (view * vec4(3.0, 4.0, 5.0, 1.0)).truncate();
+        assert!(eye.magnitude() < 1e-5);
+    }
+}
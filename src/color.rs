@@ -0,0 +1,75 @@
+use anyhow::{anyhow, Result};
+
+/// Swaps the red and blue channels of a BGR pixel.
+pub fn bgr_to_rgb([b, g, r]: [u8; 3]) -> [u8; 3] {
+    [r, g, b]
+}
+
+/// Converts a BGRA pixel, the layout of most swapchain images, to RGBA.
+pub fn bgra_to_rgba([b, g, r, a]: [u8; 4]) -> [u8; 4] {
+    let [r, g, b] = bgr_to_rgb([b, g, r]);
+    [r, g, b, a]
+}
+
+/// Expands a pixel of 1 to 4 channels, gray, gray and alpha, RGB or RGBA,
+/// to RGBA. Missing alpha is opaque. Other channel counts give `None`.
+pub fn expand_to_rgba(pixel: &[u8]) -> Option<[u8; 4]> {
+    match *pixel {
+        [gray] => Some([gray, gray, gray, 255]),
+        [gray, alpha] => Some([gray, gray, gray, alpha]),
+        [r, g, b] => Some([r, g, b, 255]),
+        [r, g, b, a] => Some([r, g, b, a]),
+        _ => None,
+    }
+}
+
+/// Expands tightly packed pixels of `channels` channels to RGBA. Fails
+/// unless there are 1 to 4 channels.
+pub fn expand_pixels_to_rgba(pixels: &[u8], channels: usize) -> Result<Vec<u8>> {
+    match channels {
+        1..=3 => Ok(pixels
+            .chunks_exact(channels)
+            .filter_map(expand_to_rgba)
+            .flatten()
+            .collect()),
+        4 => Ok(pixels.to_vec()),
+        _ => Err(anyhow!("Pixels have 1 to 4 channels, not {}", channels)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn swaps_red_and_blue() {
+        assert_eq!(bgr_to_rgb([1, 2, 3]), [3, 2, 1]);
+        assert_eq!(bgra_to_rgba([0, 128, 255, 64]), [255, 128, 0, 64]);
+    }
+
+    #[test]
+    fn expands_each_layout() {
+        assert_eq!(expand_to_rgba(&[7]), Some([7, 7, 7, 255]));
+        assert_eq!(expand_to_rgba(&[7, 9]), Some([7, 7, 7, 9]));
+        assert_eq!(expand_to_rgba(&[1, 2, 3]), Some([1, 2, 3, 255]));
+        assert_eq!(expand_to_rgba(&[1, 2, 3, 4]), Some([1, 2, 3, 4]));
+        assert_eq!(expand_to_rgba(&[]), None);
+        assert_eq!(expand_to_rgba(&[1, 2, 3, 4, 5]), None);
+    }
+
+    #[test]
+    fn expands_packed_pixels() {
+        let pixels = expand_pixels_to_rgba(&[10, 20, 30, 40, 50, 60], 3).unwrap();
+        assert_eq!(pixels, [10, 20, 30, 255, 40, 50, 60, 255]);
+        let pixels = expand_pixels_to_rgba(&[10, 20, 30, 40], 2).unwrap();
+        assert_eq!(pixels, [10, 10, 10, 20, 30, 30, 30, 40]);
+        let pixels = expand_pixels_to_rgba(&[1, 2, 3, 4], 4).unwrap();
+        assert_eq!(pixels, [1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn rejects_unsupported_channel_counts() {
+        assert!(expand_pixels_to_rgba(&[1, 2, 3], 0).is_err());
+        assert!(expand_pixels_to_rgba(&[1, 2, 3, 4, 5], 5).is_err());
+    }
+}
@@ -0,0 +1,88 @@
+use crate::math::{vec2, Vec2};
+use crate::obj::Winding;
+use crate::{depth, model, swapchain, timing, validation_from_env, vertex, VALIDATION_ENABLED};
+
+/// Options `App::create` starts from, set with the chained setters. The
+/// `SCOP_*` environment variables give the defaults of the other ones.
+#[derive(Clone, Debug)]
+pub struct AppConfig {
+    pub obj_path: String,
+    /// Texture of the objects, instead of their material ones.
+    pub texture_path: Option<String>,
+    pub validation: bool,
+    pub z_up: bool,
+    /// Initial orbit angles, in degrees.
+    pub rotation: Vec2,
+    pub zoom: f32,
+    pub dedup_tolerance: f32,
+    pub max_triangles: Option<usize>,
+    pub decimate: bool,
+    pub drop_degenerate: bool,
+    pub keep_normals: bool,
+    pub hard_edge_angle: Option<f32>,
+    pub decimal_comma: bool,
+    pub winding: Winding,
+    pub gpu_timings: bool,
+    pub swapchain_image_count: Option<u32>,
+    pub force_staging: bool,
+    pub reverse_z: bool,
+}
+
+impl Default for AppConfig {
+    fn default() -> Self {
+        AppConfig {
+            obj_path: model::DEFAULT_MODEL_PATH.to_owned(),
+            texture_path: None,
+            validation: validation_from_env(VALIDATION_ENABLED),
+            z_up: false,
+            rotation: vec2(0.0, 45.0),
+            zoom: 1.0,
+            dedup_tolerance: model::dedup_tolerance_from_env(),
+            max_triangles: model::max_triangles_from_env(),
+            decimate: model::decimate_from_env(),
+            drop_degenerate: model::drop_degenerate_from_env(),
+            keep_normals: model::keep_normals_from_env(),
+            hard_edge_angle: model::hard_edge_angle_from_env(),
+            decimal_comma: model::decimal_comma_from_env(),
+            winding: model::winding_from_env(),
+            gpu_timings: timing::gpu_timings_from_env(),
+            swapchain_image_count: swapchain::image_count_from_env(),
+            force_staging: vertex::force_staging_from_env(),
+            reverse_z: depth::reverse_z_from_env(),
+        }
+    }
+}
+
+impl AppConfig {
+    pub fn obj_path(mut self, obj_path: String) -> Self {
+        self.obj_path = obj_path;
+        self
+    }
+
+    pub fn texture_path(mut self, texture_path: Option<String>) -> Self {
+        self.texture_path = texture_path;
+        self
+    }
+
+    // Only the tests override the validation resolved from the environment.
+    #[cfg_attr(not(test), allow(dead_code))]
+    pub fn validation(mut self, validation: bool) -> Self {
+        self.validation = validation;
+        self
+    }
+
+    pub fn z_up(mut self, z_up: bool) -> Self {
+        self.z_up = z_up;
+        self
+    }
+
+    pub fn rotation(mut self, rotation: Vec2) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    pub fn zoom(mut self, zoom: f32) -> Self {
+        self.zoom = zoom;
+        self
+    }
+}
@@ -1,4 +1,5 @@
 use anyhow::{anyhow, Result};
+use log::*;
 use vulkanalia::prelude::v1_2::*;
 
 use crate::{
@@ -11,7 +12,7 @@ pub unsafe fn create_depth_objects(
     device: &Device,
     data: &mut AppData,
 ) -> Result<()> {
-    let format = get_depth_format(instance, data)?;
+    let format = data.depth_format;
 
     let (depth_image, depth_image_memory) = create_image(
         instance,
@@ -22,15 +23,26 @@ pub unsafe fn create_depth_objects(
         1,
         format,
         vk::ImageTiling::OPTIMAL,
-        vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT,
+        depth_image_usage(data.depth_view),
         vk::MemoryPropertyFlags::DEVICE_LOCAL,
     )?;
 
     data.depth_image = depth_image;
     data.depth_image_memory = depth_image_memory;
 
-    data.depth_image_view =
-        create_image_view(device, depth_image, format, vk::ImageAspectFlags::DEPTH, 1)?;
+    let aspects = if has_stencil_component(format) {
+        vk::ImageAspectFlags::DEPTH | vk::ImageAspectFlags::STENCIL
+    } else {
+        vk::ImageAspectFlags::DEPTH
+    };
+    data.depth_image_view = create_image_view(device, depth_image, format, aspects, 1)?;
+
+    // A sampled view may only have one aspect.
+    data.depth_sample_view = if data.depth_view {
+        create_image_view(device, depth_image, format, vk::ImageAspectFlags::DEPTH, 1)?
+    } else {
+        vk::ImageView::null()
+    };
 
     transition_image_layout(
         device,
@@ -45,40 +57,156 @@ pub unsafe fn create_depth_objects(
     Ok(())
 }
 
-pub unsafe fn get_depth_format(instance: &Instance, data: &AppData) -> Result<vk::Format> {
-    let candidates = &[
-        vk::Format::D32_SFLOAT,
-        vk::Format::D32_SFLOAT_S8_UINT,
-        vk::Format::D24_UNORM_S8_UINT,
-    ];
+/// Usage of the depth image, which is also sampled when the depth view is on.
+fn depth_image_usage(depth_view: bool) -> vk::ImageUsageFlags {
+    if depth_view {
+        vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT | vk::ImageUsageFlags::SAMPLED
+    } else {
+        vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT
+    }
+}
 
-    get_supported_format(
-        instance,
-        data,
-        candidates,
-        vk::ImageTiling::OPTIMAL,
-        vk::FormatFeatureFlags::DEPTH_STENCIL_ATTACHMENT,
+/// Whether the depth range is reversed, from `SCOP_REVERSE_Z`.
+pub fn reverse_z_from_env() -> bool {
+    std::env::var("SCOP_REVERSE_Z").is_ok_and(|value| value == "1" || value == "true")
+}
+
+/// Depth test passing for fragments closer to the camera, or as close when
+/// `or_equal` is set.
+pub fn closer_compare_op(reverse_z: bool, or_equal: bool) -> vk::CompareOp {
+    match (reverse_z, or_equal) {
+        (false, false) => vk::CompareOp::LESS,
+        (false, true) => vk::CompareOp::LESS_OR_EQUAL,
+        (true, false) => vk::CompareOp::GREATER,
+        (true, true) => vk::CompareOp::GREATER_OR_EQUAL,
+    }
+}
+
+/// Depth the depth buffer is cleared to, the farthest one.
+pub fn clear_depth(reverse_z: bool) -> f32 {
+    if reverse_z {
+        0.0
+    } else {
+        1.0
+    }
+}
+
+/// Depth formats, in order of preference. Formats with a stencil component
+/// come first as the outline rendering needs one.
+const DEPTH_FORMATS: &[vk::Format] = &[
+    vk::Format::D32_SFLOAT_S8_UINT,
+    vk::Format::D24_UNORM_S8_UINT,
+    vk::Format::D32_SFLOAT,
+];
+
+/// Whether `format` has a stencil component.
+pub fn has_stencil_component(format: vk::Format) -> bool {
+    matches!(
+        format,
+        vk::Format::D32_SFLOAT_S8_UINT | vk::Format::D24_UNORM_S8_UINT
     )
 }
 
-unsafe fn get_supported_format(
+/// Picks the first depth format usable as an optimal tiling depth attachment.
+pub unsafe fn find_supported_depth_format(
     instance: &Instance,
     data: &AppData,
+) -> Result<vk::Format> {
+    let format = select_format(
+        DEPTH_FORMATS,
+        vk::ImageTiling::OPTIMAL,
+        vk::FormatFeatureFlags::DEPTH_STENCIL_ATTACHMENT,
+        |format| instance.get_physical_device_format_properties(data.physical_device, format),
+    )
+    .ok_or_else(|| anyhow!("Failed to find supported depth format"))?;
+
+    debug!("Using depth format {:?}", format);
+    if data.reverse_z && format == vk::Format::D24_UNORM_S8_UINT {
+        warn!("Reverse-Z gains little precision with a fixed point depth format");
+    }
+    Ok(format)
+}
+
+/// Returns the first candidate whose `properties` support `features` with
+/// the given tiling.
+fn select_format(
     candidates: &[vk::Format],
     tiling: vk::ImageTiling,
     features: vk::FormatFeatureFlags,
-) -> Result<vk::Format> {
-    candidates
-        .iter()
-        .cloned()
-        .find(|f| {
-            let properties =
-                instance.get_physical_device_format_properties(data.physical_device, *f);
-            match tiling {
-                vk::ImageTiling::LINEAR => properties.linear_tiling_features.contains(features),
-                vk::ImageTiling::OPTIMAL => properties.optimal_tiling_features.contains(features),
-                _ => false,
-            }
-        })
-        .ok_or_else(|| anyhow!("Failed to find supported format"))
+    properties: impl Fn(vk::Format) -> vk::FormatProperties,
+) -> Option<vk::Format> {
+    candidates.iter().cloned().find(|f| {
+        let properties = properties(*f);
+        match tiling {
+            vk::ImageTiling::LINEAR => properties.linear_tiling_features.contains(features),
+            vk::ImageTiling::OPTIMAL => properties.optimal_tiling_features.contains(features),
+            _ => false,
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn depth_image_is_sampled_only_for_the_depth_view() {
+        assert!(depth_image_usage(true).contains(vk::ImageUsageFlags::SAMPLED));
+        assert!(!depth_image_usage(false).contains(vk::ImageUsageFlags::SAMPLED));
+        for depth_view in [false, true] {
+            let usage = depth_image_usage(depth_view);
+            assert!(usage.contains(vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT));
+        }
+    }
+
+    /// Properties of a device supporting only the `supported` formats as
+    /// optimal tiling depth attachments.
+    fn properties(supported: &[vk::Format]) -> impl Fn(vk::Format) -> vk::FormatProperties + '_ {
+        move |format| vk::FormatProperties {
+            optimal_tiling_features: if supported.contains(&format) {
+                vk::FormatFeatureFlags::DEPTH_STENCIL_ATTACHMENT
+            } else {
+                vk::FormatFeatureFlags::empty()
+            },
+            ..Default::default()
+        }
+    }
+
+    fn select(supported: &[vk::Format]) -> Option<vk::Format> {
+        select_format(
+            DEPTH_FORMATS,
+            vk::ImageTiling::OPTIMAL,
+            vk::FormatFeatureFlags::DEPTH_STENCIL_ATTACHMENT,
+            properties(supported),
+        )
+    }
+
+    #[test]
+    fn depth_format_falls_back_in_order() {
+        assert_eq!(select(DEPTH_FORMATS), Some(vk::Format::D32_SFLOAT_S8_UINT));
+        assert_eq!(
+            select(&[vk::Format::D32_SFLOAT, vk::Format::D24_UNORM_S8_UINT]),
+            Some(vk::Format::D24_UNORM_S8_UINT)
+        );
+        assert_eq!(
+            select(&[vk::Format::D32_SFLOAT]),
+            Some(vk::Format::D32_SFLOAT)
+        );
+        assert_eq!(select(&[]), None);
+    }
+
+    #[test]
+    fn depth_format_ignores_linear_tiling() {
+        let linear_only = |_| vk::FormatProperties {
+            linear_tiling_features: vk::FormatFeatureFlags::DEPTH_STENCIL_ATTACHMENT,
+            ..Default::default()
+        };
+        let format = select_format(
+            DEPTH_FORMATS,
+            vk::ImageTiling::OPTIMAL,
+            vk::FormatFeatureFlags::DEPTH_STENCIL_ATTACHMENT,
+            linear_only,
+        );
+        assert_eq!(format, None);
+    }
 }
@@ -1,9 +1,9 @@
 use std::mem::size_of;
 
-use anyhow::{Ok, Result};
+use anyhow::Result;
 use vulkanalia::prelude::v1_2::*;
 
-use crate::{buffers::create_buffer, AppData};
+use crate::{buffers::create_buffer, math::Vec4, AppData};
 
 pub type Mat4 = crate::math::Matrix4;
 
@@ -13,27 +13,56 @@ pub struct UniformBufferObject {
     pub model: Mat4,
     pub view: Mat4,
     pub proj: Mat4,
+    pub outline_model: Mat4,
+    /// Camera position in world space, `w` is unused.
+    pub camera_pos: Vec4,
+    pub gizmo: Mat4,
+    pub exposure: f32,
+    pub gamma: f32,
 }
 
+/// Number of textures of the model loaded in `data`: the default one, then
+/// those of its materials. The texture array of the shaders has as many.
+pub fn texture_count(data: &AppData) -> usize {
+    data.texture_paths.len() + 1
+}
+
+/// Creates the layout of the descriptor sets, whose texture array depends on
+/// the textures of the loaded model.
 pub unsafe fn create_descriptor_set_layout(device: &Device, data: &mut AppData) -> Result<()> {
+    let bindings = layout_bindings(texture_count(data));
+    let layout_info = vk::DescriptorSetLayoutCreateInfo::builder().bindings(&bindings);
+
+    data.descriptor_set_layout = device.create_descriptor_set_layout(&layout_info, None)?;
+
+    Ok(())
+}
+
+/// Bindings of the uniform buffer, the array of `texture_count` textures
+/// and the depth sampler.
+fn layout_bindings(texture_count: usize) -> [vk::DescriptorSetLayoutBinding; 3] {
     let sampler_binding = vk::DescriptorSetLayoutBinding::builder()
         .binding(1)
         .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+        .descriptor_count(texture_count as u32)
+        .stage_flags(vk::ShaderStageFlags::FRAGMENT)
+        .build();
+
+    let depth_binding = vk::DescriptorSetLayoutBinding::builder()
+        .binding(2)
+        .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
         .descriptor_count(1)
-        .stage_flags(vk::ShaderStageFlags::FRAGMENT);
+        .stage_flags(vk::ShaderStageFlags::FRAGMENT)
+        .build();
 
     let ubo_binding = vk::DescriptorSetLayoutBinding::builder()
         .binding(0)
         .descriptor_type(vk::DescriptorType::UNIFORM_BUFFER)
         .descriptor_count(1)
-        .stage_flags(vk::ShaderStageFlags::VERTEX);
-
-    let bindings = [ubo_binding, sampler_binding];
-    let layout_info = vk::DescriptorSetLayoutCreateInfo::builder().bindings(&bindings);
-
-    data.descriptor_set_layout = device.create_descriptor_set_layout(&layout_info, None)?;
+        .stage_flags(vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::FRAGMENT)
+        .build();
 
-    Ok(())
+    [ubo_binding, sampler_binding, depth_binding]
 }
 
 pub unsafe fn create_uniform_buffers(
@@ -43,6 +72,7 @@ pub unsafe fn create_uniform_buffers(
 ) -> Result<()> {
     data.uniform_buffers.clear();
     data.uniform_buffers_memory.clear();
+    data.uniform_buffers_mapped.clear();
 
     for _ in 0..data.swapchain_images.len() {
         let (uniform_buffer, uniform_buffer_memory) = create_buffer(
@@ -54,23 +84,24 @@ pub unsafe fn create_uniform_buffers(
             vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
         )?;
 
+        // The memory is coherent, it stays mapped until destroyed.
+        let mapped = device.map_memory(
+            uniform_buffer_memory,
+            0,
+            size_of::<UniformBufferObject>() as u64,
+            vk::MemoryMapFlags::empty(),
+        )?;
+
         data.uniform_buffers.push(uniform_buffer);
         data.uniform_buffers_memory.push(uniform_buffer_memory);
+        data.uniform_buffers_mapped.push(mapped);
     }
 
     Ok(())
 }
 
 pub unsafe fn create_descriptor_pool(device: &Device, data: &mut AppData) -> Result<()> {
-    let ubo_size = vk::DescriptorPoolSize::builder()
-        .type_(vk::DescriptorType::UNIFORM_BUFFER)
-        .descriptor_count(data.swapchain_images.len() as u32);
-
-    let sampler_size = vk::DescriptorPoolSize::builder()
-        .type_(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
-        .descriptor_count(data.swapchain_images.len() as u32);
-
-    let pool_sizes = [ubo_size, sampler_size];
+    let pool_sizes = pool_sizes(data.swapchain_images.len(), texture_count(data));
     let pool_info = vk::DescriptorPoolCreateInfo::builder()
         .pool_sizes(&pool_sizes)
         .max_sets(data.swapchain_images.len() as u32);
@@ -79,6 +110,22 @@ pub unsafe fn create_descriptor_pool(device: &Device, data: &mut AppData) -> Res
     Ok(())
 }
 
+/// Descriptors of `set_count` sets: a uniform buffer, the array of
+/// `texture_count` textures and the depth sampler each.
+fn pool_sizes(set_count: usize, texture_count: usize) -> [vk::DescriptorPoolSize; 2] {
+    let ubo_size = vk::DescriptorPoolSize::builder()
+        .type_(vk::DescriptorType::UNIFORM_BUFFER)
+        .descriptor_count(set_count as u32)
+        .build();
+
+    let sampler_size = vk::DescriptorPoolSize::builder()
+        .type_(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+        .descriptor_count((set_count * (texture_count + 1)) as u32)
+        .build();
+
+    [ubo_size, sampler_size]
+}
+
 pub unsafe fn create_descriptor_sets(device: &Device, data: &mut AppData) -> Result<()> {
     let layouts = vec![data.descriptor_set_layout; data.swapchain_images.len()];
     let set_info = vk::DescriptorSetAllocateInfo::builder()
@@ -86,8 +133,15 @@ pub unsafe fn create_descriptor_sets(device: &Device, data: &mut AppData) -> Res
         .set_layouts(&layouts);
 
     data.descriptor_sets = device.allocate_descriptor_sets(&set_info)?;
+    write_descriptor_sets(device, data);
+    Ok(())
+}
 
-    for i in 0..data.swapchain_images.len() {
+/// Points the descriptor sets at the uniform buffers, the textures and the
+/// current texture sampler. The command buffers binding them must then be
+/// recorded again.
+pub unsafe fn write_descriptor_sets(device: &Device, data: &AppData) {
+    for i in 0..data.descriptor_sets.len() {
         let info = vk::DescriptorBufferInfo::builder()
             .buffer(data.uniform_buffers[i])
             .offset(0)
@@ -101,20 +155,122 @@ pub unsafe fn create_descriptor_sets(device: &Device, data: &mut AppData) -> Res
             .descriptor_type(vk::DescriptorType::UNIFORM_BUFFER)
             .buffer_info(&buffer_info);
 
-        let info = vk::DescriptorImageInfo::builder()
-            .image_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
-            .image_view(data.texture_image_view)
-            .sampler(data.texture_sampler);
-
-        let image_info = &[info];
+        let image_info = data
+            .textures
+            .iter()
+            .map(|texture| {
+                vk::DescriptorImageInfo::builder()
+                    .image_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+                    .image_view(texture.view)
+                    .sampler(data.texture_sampler)
+                    .build()
+            })
+            .collect::<Vec<_>>();
         let sampler_write = vk::WriteDescriptorSet::builder()
             .dst_set(data.descriptor_sets[i])
             .dst_binding(1)
             .dst_array_element(0)
             .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
-            .image_info(image_info);
+            .image_info(&image_info);
 
-        device.update_descriptor_sets(&[ubo_write, sampler_write], &[] as &[vk::CopyDescriptorSet]);
+        // The depth image is only sampleable while the depth view is on. The
+        // shader declares the binding in every pipeline, so the default
+        // texture stands in for it otherwise.
+        let (depth_view, depth_layout) = if data.depth_view {
+            (
+                data.depth_sample_view,
+                vk::ImageLayout::DEPTH_STENCIL_READ_ONLY_OPTIMAL,
+            )
+        } else {
+            (
+                data.textures[0].view,
+                vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            )
+        };
+        let depth_info = [vk::DescriptorImageInfo::builder()
+            .image_layout(depth_layout)
+            .image_view(depth_view)
+            .sampler(data.texture_sampler)
+            .build()];
+        let depth_write = vk::WriteDescriptorSet::builder()
+            .dst_set(data.descriptor_sets[i])
+            .dst_binding(2)
+            .dst_array_element(0)
+            .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+            .image_info(&depth_info);
+
+        device.update_descriptor_sets(
+            &[ubo_write, sampler_write, depth_write],
+            &[] as &[vk::CopyDescriptorSet],
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::mem::offset_of;
+
+    #[test]
+    fn uniform_buffer_matches_the_shader_block() {
+        // std140 offsets of the `UniformBufferObject` block of the shaders.
+        assert_eq!(offset_of!(UniformBufferObject, outline_model), 192);
+        assert_eq!(offset_of!(UniformBufferObject, camera_pos), 256);
+        assert_eq!(offset_of!(UniformBufferObject, gizmo), 272);
+        assert_eq!(offset_of!(UniformBufferObject, exposure), 336);
+        assert_eq!(offset_of!(UniformBufferObject, gamma), 340);
+        assert_eq!(size_of::<UniformBufferObject>(), 344);
+    }
+
+    #[test]
+    fn pool_sizes_scale_with_the_sets() {
+        let [ubo, samplers] = pool_sizes(3, 1);
+        assert_eq!(ubo.type_, vk::DescriptorType::UNIFORM_BUFFER);
+        assert_eq!(ubo.descriptor_count, 3);
+        assert_eq!(samplers.type_, vk::DescriptorType::COMBINED_IMAGE_SAMPLER);
+        // The default texture and the depth sampler.
+        assert_eq!(samplers.descriptor_count, 3 * 2);
+    }
+
+    #[test]
+    fn pool_sizes_and_layout_scale_with_the_textures() {
+        for texture_count in [1, 4] {
+            let [ubo, samplers] = pool_sizes(3, texture_count);
+            assert_eq!(ubo.descriptor_count, 3);
+            assert_eq!(samplers.descriptor_count, 3 * (texture_count as u32 + 1));
+
+            let [ubo, textures, depth] = layout_bindings(texture_count);
+            assert_eq!((ubo.binding, ubo.descriptor_count), (0, 1));
+            assert_eq!(
+                (textures.binding, textures.descriptor_count),
+                (1, texture_count as u32)
+            );
+            assert_eq!((depth.binding, depth.descriptor_count), (2, 1));
+        }
+    }
+
+    #[test]
+    fn textures_are_counted_once_each() {
+        let mut data = AppData::default();
+        assert_eq!(texture_count(&data), 1);
+        data.texture_paths = vec!["a.png".to_owned(), "b.png".to_owned()];
+        assert_eq!(texture_count(&data), 3);
+    }
+
+    #[test]
+    fn uniform_buffers_stay_mapped() {
+        unsafe {
+            let Some(mut app) = crate::offscreen::test_app() else {
+                return;
+            };
+            let mapped = app.data.uniform_buffers_mapped.clone();
+            assert_eq!(mapped.len(), app.data.uniform_buffers.len());
+            assert!(mapped.iter().all(|pointer| !pointer.is_null()));
+
+            app.controls.exposure = 2.5;
+            app.update_uniform_buffer(0).unwrap();
+            let ubo = mapped[0].cast::<UniformBufferObject>().read();
+            assert_eq!(ubo.exposure, 2.5);
+        }
     }
-    Ok(())
 }
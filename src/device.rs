@@ -2,13 +2,13 @@ use crate::swapchain;
 
 use std::collections::HashSet;
 
-use anyhow::{anyhow, Ok, Result};
+use anyhow::{anyhow, Result};
 use log::*;
 use thiserror::Error;
 
-use vulkanalia::{prelude::v1_2::*, vk::KhrSurfaceExtension};
+use vulkanalia::{prelude::v1_2::*, vk::KhrSurfaceExtension, Version};
 
-use crate::{AppData, PORTABILITY_MACOS_VERSION, VALIDATION_ENABLED, VALIDATION_LAYER};
+use crate::{AppData, MIN_API_VERSION, PORTABILITY_MACOS_VERSION, VALIDATION_LAYER};
 
 const DEVICE_EXTENSIONS: &[vk::ExtensionName] = &[vk::KHR_SWAPCHAIN_EXTENSION.name];
 
@@ -27,6 +27,7 @@ pub unsafe fn create_logical_device(
     let mut unique_indices = std::collections::HashSet::new();
     unique_indices.insert(indices.graphics);
     unique_indices.insert(indices.present);
+    unique_indices.extend(indices.transfer);
 
     let queue_priorities = &[1.0];
     let queue_infos = unique_indices
@@ -38,7 +39,7 @@ pub unsafe fn create_logical_device(
         })
         .collect::<Vec<_>>();
 
-    let layers = if VALIDATION_ENABLED {
+    let layers = if data.validation {
         vec![VALIDATION_LAYER.as_ptr()]
     } else {
         vec![]
@@ -54,9 +55,42 @@ pub unsafe fn create_logical_device(
         extensions.push(vk::KHR_PORTABILITY_SUBSET_EXTENSION.name.as_ptr());
     }
 
+    // Points larger than one pixel need the `large_points` feature.
+    let supported_features = instance.get_physical_device_features(data.physical_device);
+    let large_points = supported_features.large_points == vk::TRUE;
+    data.max_point_size = if large_points {
+        instance
+            .get_physical_device_properties(data.physical_device)
+            .limits
+            .point_size_range[1]
+    } else {
+        1.0
+    };
+    data.point_size = data.point_size.clamp(1.0, data.max_point_size);
+
+    // Same for lines wider than one pixel and `wide_lines`.
+    let wide_lines = supported_features.wide_lines == vk::TRUE;
+    data.line_width_range = if wide_lines {
+        instance
+            .get_physical_device_properties(data.physical_device)
+            .limits
+            .line_width_range
+    } else {
+        [1.0, 1.0]
+    };
+    data.line_width = clamp_line_width(data.line_width, data.line_width_range);
+
+    data.max_sampler_lod_bias = instance
+        .get_physical_device_properties(data.physical_device)
+        .limits
+        .max_sampler_lod_bias;
+
     let features = vk::PhysicalDeviceFeatures::builder()
         .fill_mode_non_solid(true)
-        .sampler_anisotropy(true);
+        .sampler_anisotropy(true)
+        .shader_sampled_image_array_dynamic_indexing(true)
+        .large_points(large_points)
+        .wide_lines(wide_lines);
 
     let device_info = vk::DeviceCreateInfo::builder()
         .queue_create_infos(&queue_infos)
@@ -67,10 +101,34 @@ pub unsafe fn create_logical_device(
     let device = instance.create_device(data.physical_device, &device_info, None)?;
     data.graphics_queue = device.get_device_queue(indices.graphics, 0);
     data.present_queue = device.get_device_queue(indices.present, 0);
+    data.graphics_family = indices.graphics;
+    data.transfer_family = indices.transfer;
+    data.transfer_queue = match indices.transfer {
+        Some(transfer) => {
+            debug!("Using queue family {} for transfers", transfer);
+            device.get_device_queue(transfer, 0)
+        }
+        None => data.graphics_queue,
+    };
+
+    // Timestamps are written on the graphics queue, 0 valid bits meaning no support.
+    data.timestamp_valid_bits = instance
+        .get_physical_device_queue_family_properties(data.physical_device)
+        [indices.graphics as usize]
+        .timestamp_valid_bits;
+    data.timestamp_period = instance
+        .get_physical_device_properties(data.physical_device)
+        .limits
+        .timestamp_period;
 
     Ok(device)
 }
 
+/// Clamps `width` to the supported `range`, keeping at least one pixel.
+pub fn clamp_line_width(width: f32, range: [f32; 2]) -> f32 {
+    width.clamp(range[0].max(1.0), range[1].max(1.0))
+}
+
 // Picks a physical device.
 pub unsafe fn pick_physical_device(instance: &Instance, data: &mut AppData) -> Result<()> {
     let mut best_score = 0;
@@ -110,6 +168,29 @@ pub unsafe fn pick_physical_device(instance: &Instance, data: &mut AppData) -> R
     }
 }
 
+/// Lists the physical devices with their name, type, score and whether they
+/// are suitable, for diagnostics.
+pub unsafe fn list_physical_devices(
+    instance: &Instance,
+    data: &AppData,
+) -> Result<Vec<(String, vk::PhysicalDeviceType, u32, bool)>> {
+    let devices = instance
+        .enumerate_physical_devices()?
+        .into_iter()
+        .map(|physical_device| {
+            let properties = instance.get_physical_device_properties(physical_device);
+            let suitable = check_physical_device(instance, data, physical_device).is_ok();
+            (
+                properties.device_name.to_string(),
+                properties.device_type,
+                calculate_physical_device_score(&properties),
+                suitable,
+            )
+        })
+        .collect();
+    Ok(devices)
+}
+
 fn calculate_physical_device_score(properties: &vk::PhysicalDeviceProperties) -> u32 {
     let mut score = 0;
 
@@ -128,21 +209,39 @@ unsafe fn check_physical_device(
     data: &AppData,
     physical_device: vk::PhysicalDevice,
 ) -> Result<()> {
+    let properties = instance.get_physical_device_properties(physical_device);
+    if !supports_api_version(properties.api_version, MIN_API_VERSION) {
+        return Err(anyhow!(SuitabilityError("Unsupported Vulkan API version.")));
+    }
     QueueFamilyIndices::get(instance, data, physical_device)?;
     check_physical_device_extensions(instance, physical_device)?;
-    let support = swapchain::SwapchainSupport::get(instance, data, physical_device)?;
-    if support.formats().is_empty() || support.present_modes().is_empty() {
-        return Err(anyhow!(SuitabilityError(
-            "Missing required swapchain support."
-        )));
+    // Offscreen rendering presents nothing.
+    if !data.surface.is_null() {
+        let support = swapchain::SwapchainSupport::get(instance, data, physical_device)?;
+        if support.formats().is_empty() || support.present_modes().is_empty() {
+            return Err(anyhow!(SuitabilityError(
+                "Missing required swapchain support."
+            )));
+        }
     }
     let features = instance.get_physical_device_features(physical_device);
     if features.sampler_anisotropy != vk::TRUE {
         return Err(anyhow!(SuitabilityError("No sampler anisotropy")));
     }
+    // The texture of each draw is picked from an array with a push constant.
+    if features.shader_sampled_image_array_dynamic_indexing != vk::TRUE {
+        return Err(anyhow!(SuitabilityError(
+            "No dynamic sampler array indexing"
+        )));
+    }
     Ok(())
 }
 
+/// Whether a device reporting `api_version` supports at least `minimum`.
+fn supports_api_version(api_version: u32, minimum: Version) -> bool {
+    Version::from(api_version) >= minimum
+}
+
 unsafe fn check_physical_device_extensions(
     instance: &Instance,
     physical_device: vk::PhysicalDevice,
@@ -165,10 +264,26 @@ unsafe fn check_physical_device_extensions(
     }
 }
 
+/// Returns the family dedicated to transfers among `properties`, if any:
+/// one supporting transfers without graphics, preferably without compute
+/// either.
+pub fn find_transfer_family(properties: &[vk::QueueFamilyProperties]) -> Option<u32> {
+    let transfer_only = |exclude: vk::QueueFlags| {
+        properties.iter().position(|p| {
+            p.queue_flags.contains(vk::QueueFlags::TRANSFER) && !p.queue_flags.intersects(exclude)
+        })
+    };
+    transfer_only(vk::QueueFlags::GRAPHICS | vk::QueueFlags::COMPUTE)
+        .or_else(|| transfer_only(vk::QueueFlags::GRAPHICS))
+        .map(|index| index as u32)
+}
+
 #[derive(Copy, Clone, Debug)]
 pub struct QueueFamilyIndices {
     graphics: u32,
     present: u32,
+    /// Family dedicated to transfers, the graphics one is used without it.
+    transfer: Option<u32>,
 }
 
 impl QueueFamilyIndices {
@@ -178,6 +293,9 @@ impl QueueFamilyIndices {
     pub fn present(&self) -> u32 {
         self.present
     }
+    pub fn transfer(&self) -> Option<u32> {
+        self.transfer
+    }
     pub unsafe fn get(
         instance: &Instance,
         data: &AppData,
@@ -192,6 +310,12 @@ impl QueueFamilyIndices {
 
         let mut present = None;
         for (index, _) in properties.iter().enumerate() {
+            // Without a surface, when rendering offscreen, the graphics
+            // family stands for the present one.
+            if data.surface.is_null() {
+                present = graphics;
+                break;
+            }
             if instance.get_physical_device_surface_support_khr(
                 physical_device,
                 index as u32,
@@ -203,7 +327,11 @@ impl QueueFamilyIndices {
         }
 
         if let (Some(graphics), Some(present)) = (graphics, present) {
-            Ok(Self { graphics, present })
+            Ok(Self {
+                graphics,
+                present,
+                transfer: find_transfer_family(&properties),
+            })
         } else {
             Err(anyhow!(SuitabilityError(
                 "Missing required queue families."
@@ -229,3 +357,141 @@ pub unsafe fn get_memory_type_index(
         })
         .ok_or_else(|| anyhow!("Failed to find suitable memory type."))
 }
+
+/// Index of a memory type allowed by `memory_type_bits` which the host can
+/// write and which belongs to the largest device local heap. Integrated GPUs
+/// share their memory with the CPU and have one, buffers there need no
+/// staging copy. The small host visible window of discrete GPUs is left out.
+pub fn unified_memory_type(
+    memory_properties: &vk::PhysicalDeviceMemoryProperties,
+    memory_type_bits: u32,
+) -> Option<u32> {
+    let heaps = &memory_properties.memory_heaps[..memory_properties.memory_heap_count as usize];
+    let device_heap = (0..heaps.len())
+        .filter(|&i| heaps[i].flags.contains(vk::MemoryHeapFlags::DEVICE_LOCAL))
+        .max_by_key(|&i| heaps[i].size)?;
+
+    let properties = vk::MemoryPropertyFlags::DEVICE_LOCAL
+        | vk::MemoryPropertyFlags::HOST_VISIBLE
+        | vk::MemoryPropertyFlags::HOST_COHERENT;
+    (0..memory_properties.memory_type_count).find(|&i| {
+        let memory_type = memory_properties.memory_types[i as usize];
+        (memory_type_bits & (1 << i)) != 0
+            && memory_type.heap_index as usize == device_heap
+            && memory_type.property_flags.contains(properties)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Memory properties with the given heaps, as `(size, device_local)`, and
+    /// memory types, as `(heap, flags)`.
+    fn memory_properties(
+        heaps: &[(u64, bool)],
+        types: &[(u32, vk::MemoryPropertyFlags)],
+    ) -> vk::PhysicalDeviceMemoryProperties {
+        let mut properties = vk::PhysicalDeviceMemoryProperties {
+            memory_heap_count: heaps.len() as u32,
+            memory_type_count: types.len() as u32,
+            ..Default::default()
+        };
+        for (i, &(size, device_local)) in heaps.iter().enumerate() {
+            properties.memory_heaps[i].size = size;
+            if device_local {
+                properties.memory_heaps[i].flags = vk::MemoryHeapFlags::DEVICE_LOCAL;
+            }
+        }
+        for (i, &(heap_index, property_flags)) in types.iter().enumerate() {
+            properties.memory_types[i] = vk::MemoryType {
+                property_flags,
+                heap_index,
+            };
+        }
+        properties
+    }
+
+    const DEVICE_LOCAL: vk::MemoryPropertyFlags = vk::MemoryPropertyFlags::DEVICE_LOCAL;
+    const HOST: vk::MemoryPropertyFlags = vk::MemoryPropertyFlags::from_bits_truncate(
+        vk::MemoryPropertyFlags::HOST_VISIBLE.bits()
+            | vk::MemoryPropertyFlags::HOST_COHERENT.bits(),
+    );
+
+    #[test]
+    fn api_version_must_reach_the_minimum() {
+        let minimum = Version::new(1, 1, 0);
+        assert!(supports_api_version(vk::make_version(1, 1, 0), minimum));
+        assert!(supports_api_version(vk::make_version(1, 3, 250), minimum));
+        assert!(!supports_api_version(vk::make_version(1, 0, 300), minimum));
+    }
+
+    #[test]
+    fn integrated_memory_is_unified() {
+        let properties = memory_properties(
+            &[(8 << 30, true)],
+            &[(0, DEVICE_LOCAL), (0, DEVICE_LOCAL | HOST)],
+        );
+        assert_eq!(unified_memory_type(&properties, !0), Some(1));
+        assert_eq!(unified_memory_type(&properties, 0b01), None);
+    }
+
+    #[test]
+    fn discrete_host_window_is_left_out() {
+        // 8 GiB of VRAM, system memory and a 256 MiB host visible window.
+        let properties = memory_properties(
+            &[(8 << 30, true), (16 << 30, false), (256 << 20, true)],
+            &[(0, DEVICE_LOCAL), (1, HOST), (2, DEVICE_LOCAL | HOST)],
+        );
+        assert_eq!(unified_memory_type(&properties, !0), None);
+    }
+
+    #[test]
+    fn line_width_is_clamped_to_the_device_range() {
+        assert_eq!(clamp_line_width(4.0, [1.0, 8.0]), 4.0);
+        assert_eq!(clamp_line_width(12.0, [1.0, 8.0]), 8.0);
+        assert_eq!(clamp_line_width(0.5, [0.5, 8.0]), 1.0);
+        // Without `wide_lines` the range is a single pixel.
+        assert_eq!(clamp_line_width(3.0, [1.0, 1.0]), 1.0);
+    }
+
+    #[test]
+    fn discrete_gpus_score_highest() {
+        let score = |device_type| {
+            calculate_physical_device_score(&vk::PhysicalDeviceProperties {
+                device_type,
+                ..Default::default()
+            })
+        };
+        let discrete = score(vk::PhysicalDeviceType::DISCRETE_GPU);
+        let integrated = score(vk::PhysicalDeviceType::INTEGRATED_GPU);
+        let cpu = score(vk::PhysicalDeviceType::CPU);
+        assert!(discrete > integrated && integrated > cpu);
+    }
+
+    fn queue_families(flags: &[vk::QueueFlags]) -> Vec<vk::QueueFamilyProperties> {
+        flags
+            .iter()
+            .map(|&queue_flags| vk::QueueFamilyProperties {
+                queue_flags,
+                queue_count: 1,
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn transfers_prefer_a_dedicated_family() {
+        type Flags = vk::QueueFlags;
+        let all = Flags::GRAPHICS | Flags::COMPUTE | Flags::TRANSFER;
+        let compute = Flags::COMPUTE | Flags::TRANSFER;
+
+        let families = queue_families(&[all, compute, Flags::TRANSFER]);
+        assert_eq!(find_transfer_family(&families), Some(2));
+        let families = queue_families(&[all, compute]);
+        assert_eq!(find_transfer_family(&families), Some(1));
+        // The graphics family is used for transfers on its own.
+        let families = queue_families(&[all, Flags::COMPUTE]);
+        assert_eq!(find_transfer_family(&families), None);
+    }
+}
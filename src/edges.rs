@@ -0,0 +1,157 @@
+use anyhow::{Ok, Result};
+use vulkanalia::prelude::v1_2::*;
+
+use std::collections::HashMap;
+
+use crate::math::Vec3;
+use crate::vertex::{upload_buffer, Vertex};
+use crate::AppData;
+
+/// Angle in degrees between the faces of an edge above which it is drawn as
+/// a crease, unless `SCOP_HARD_EDGE_ANGLE` is set.
+pub const CREASE_ANGLE: f32 = 30.0;
+
+/// Returns the edges of the triangles in `indices` used by a single
+/// triangle, or shared by triangles whose normals differ by more than
+/// `angle_threshold_deg`. Vertices at the same position are the same corner,
+/// so seams in the texture coordinates or normals don't split edges.
+pub fn feature_edges(
+    vertices: &[Vertex],
+    indices: &[u32],
+    angle_threshold_deg: f32,
+) -> Vec<[u32; 2]> {
+    let key = |index: u32| {
+        let pos = vertices[index as usize].pos;
+        [pos.x.to_bits(), pos.y.to_bits(), pos.z.to_bits()]
+    };
+
+    // Edge between two corners, with one of its vertex pairs and the normals
+    // of the triangles using it.
+    let mut edges: HashMap<_, ([u32; 2], Vec<Vec3>)> = HashMap::new();
+    for triangle in indices.chunks_exact(3) {
+        let [a, b, c] = [triangle[0], triangle[1], triangle[2]].map(|i| vertices[i as usize].pos);
+        let normal = (b - a).cross(c - a);
+        for (i, j) in [(0, 1), (1, 2), (2, 0)] {
+            let (first, second) = (triangle[i], triangle[j]);
+            let (key_first, key_second) = (key(first), key(second));
+            if key_first == key_second {
+                continue;
+            }
+            let corners = if key_first < key_second {
+                (key_first, key_second)
+            } else {
+                (key_second, key_first)
+            };
+            edges
+                .entry(corners)
+                .or_insert_with(|| ([first, second], Vec::new()))
+                .1
+                .push(normal);
+        }
+    }
+
+    let cos_threshold = angle_threshold_deg.to_radians().cos();
+    let mut feature_edges: Vec<_> = edges
+        .into_values()
+        .filter(|(_, normals)| match normals.as_slice() {
+            [_] => true,
+            [a, b] => {
+                let magnitudes = a.magnitude() * b.magnitude();
+                magnitudes > 0.0 && a.dot(*b) / magnitudes < cos_threshold
+            }
+            // Non-manifold edges are always drawn.
+            _ => true,
+        })
+        .map(|(edge, _)| edge)
+        .collect();
+    // The map iteration order is random, keep the lines stable between loads.
+    feature_edges.sort_unstable();
+    feature_edges
+}
+
+/// Uploads the crease and boundary edges of the loaded model as a line list.
+pub unsafe fn create_edges_buffer(
+    instance: &Instance,
+    device: &Device,
+    data: &mut AppData,
+) -> Result<()> {
+    let angle = data.hard_edge_angle.unwrap_or(CREASE_ANGLE);
+    let lines: Vec<Vertex> = feature_edges(&data.vertices, &data.indices, angle)
+        .into_iter()
+        .flatten()
+        .map(|index| data.vertices[index as usize])
+        .collect();
+    data.edge_vertex_count = lines.len() as u32;
+
+    // Point clouds have no edges, and Vulkan rejects empty buffers.
+    if lines.is_empty() {
+        data.edges_vertex_buffer = vk::Buffer::null();
+        data.edges_vertex_buffer_memory = vk::DeviceMemory::null();
+        return Ok(());
+    }
+
+    let (buffer, memory) = upload_buffer(
+        instance,
+        device,
+        data,
+        &lines,
+        vk::BufferUsageFlags::VERTEX_BUFFER,
+    )?;
+    data.edges_vertex_buffer = buffer;
+    data.edges_vertex_buffer_memory = memory;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::{vec2, vec3};
+
+    /// Vertices at the corners of a cube, corner `i` taking its X, Y and Z
+    /// from bits 0, 1 and 2.
+    fn cube_vertices() -> Vec<Vertex> {
+        let zero = vec3(0.0, 0.0, 0.0);
+        (0..8)
+            .map(|i| {
+                let c = |bit| if i & bit != 0 { 1.0 } else { -1.0 };
+                let pos = vec3(c(1), c(2), c(4));
+                Vertex::new(pos, zero, vec2(0.0, 0.0), zero, zero, 0.0, zero)
+            })
+            .collect()
+    }
+
+    /// The faces of the cube as outward facing triangles.
+    fn cube_indices() -> Vec<u32> {
+        let quads = [
+            [4, 6, 2, 0],
+            [1, 3, 7, 5],
+            [0, 1, 5, 4],
+            [6, 7, 3, 2],
+            [2, 3, 1, 0],
+            [4, 5, 7, 6],
+        ];
+        quads
+            .iter()
+            .flat_map(|[a, b, c, d]| [*a, *b, *c, *a, *c, *d])
+            .collect()
+    }
+
+    #[test]
+    fn cube_has_twelve_crease_edges() {
+        let edges = feature_edges(&cube_vertices(), &cube_indices(), CREASE_ANGLE);
+        assert_eq!(edges.len(), 12);
+        // The face diagonals are flat and left out.
+        let vertices = cube_vertices();
+        for [a, b] in edges {
+            let delta = vertices[a as usize].pos - vertices[b as usize].pos;
+            assert_eq!(delta.magnitude(), 2.0);
+        }
+    }
+
+    #[test]
+    fn lone_triangle_has_three_boundary_edges() {
+        let edges = feature_edges(&cube_vertices(), &[0, 1, 3], CREASE_ANGLE);
+        assert_eq!(edges.len(), 3);
+    }
+}
@@ -0,0 +1,87 @@
+use std::time::{Duration, Instant};
+
+/// Lowest frame rate cap accepted, lower ones would sleep for minutes.
+const MIN_FPS: f32 = 1.0;
+
+/// Maximum frame rate from a `--max-fps N` argument, falling back to
+/// `SCOP_MAX_FPS`. Frames are uncapped when neither is set or valid.
+pub fn max_fps(arg: Option<&str>) -> Option<f32> {
+    let value = match arg {
+        Some(value) => value.to_owned(),
+        None => std::env::var("SCOP_MAX_FPS").ok()?,
+    };
+    match value.parse::<f32>() {
+        Ok(fps) if fps.is_finite() && fps >= MIN_FPS => Some(fps),
+        _ => {
+            log::warn!("Invalid maximum frame rate: {}", value);
+            None
+        }
+    }
+}
+
+/// Time left to sleep so a frame which already took `elapsed` lasts
+/// `1 / max_fps` seconds. There is none when that duration can't be
+/// represented.
+pub fn sleep_duration(max_fps: f32, elapsed: Duration) -> Duration {
+    Duration::try_from_secs_f32(1.0 / max_fps)
+        .unwrap_or(Duration::ZERO)
+        .saturating_sub(elapsed)
+}
+
+/// Sleeps between frames to keep them under a maximum frame rate.
+#[derive(Clone, Debug)]
+pub struct FrameLimiter {
+    max_fps: Option<f32>,
+    last_frame: Instant,
+}
+
+impl FrameLimiter {
+    pub fn new(max_fps: Option<f32>) -> Self {
+        Self {
+            max_fps,
+            last_frame: Instant::now(),
+        }
+    }
+
+    /// Sleeps until the next frame is due, if the frame rate is capped.
+    pub fn wait(&mut self) {
+        if let Some(max_fps) = self.max_fps {
+            std::thread::sleep(sleep_duration(max_fps, self.last_frame.elapsed()));
+        }
+        self.last_frame = Instant::now();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn max_fps_accepts_valid_rates() {
+        assert_eq!(max_fps(Some("60")), Some(60.0));
+        assert_eq!(max_fps(Some("1")), Some(1.0));
+    }
+
+    #[test]
+    fn max_fps_rejects_invalid_rates() {
+        for value in ["0", "-30", "0.5", "1e-20", "inf", "NaN", "fast"] {
+            assert_eq!(max_fps(Some(value)), None, "{}", value);
+        }
+    }
+
+    #[test]
+    fn sleep_duration_fills_the_frame() {
+        let elapsed = Duration::from_millis(4);
+        assert_eq!(sleep_duration(100.0, elapsed), Duration::from_millis(6));
+        assert_eq!(
+            sleep_duration(100.0, Duration::from_secs(1)),
+            Duration::ZERO
+        );
+    }
+
+    #[test]
+    fn sleep_duration_does_not_panic_on_extreme_rates() {
+        assert_eq!(sleep_duration(1e-40, Duration::ZERO), Duration::ZERO);
+        assert_eq!(sleep_duration(0.0, Duration::ZERO), Duration::ZERO);
+    }
+}
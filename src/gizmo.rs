@@ -0,0 +1,87 @@
+use anyhow::{Ok, Result};
+use vulkanalia::prelude::v1_2::*;
+
+use crate::descriptor::Mat4;
+use crate::math::{vec2, vec3, vec4, Vec3};
+use crate::vertex::{upload_buffer, Vertex};
+use crate::AppData;
+
+/// Size in pixels of the square viewport, in the bottom left corner, the
+/// gizmo is drawn in.
+pub const GIZMO_SIZE: u32 = 100;
+
+const RED: Vec3 = vec3(1.0, 0.0, 0.0);
+const GREEN: Vec3 = vec3(0.0, 1.0, 0.0);
+const BLUE: Vec3 = vec3(0.0, 0.0, 1.0);
+
+/// The X, Y and Z unit axes as red, green and blue line segments.
+pub const GIZMO_VERTICES: [Vertex; 6] = [
+    axis_vertex(vec3(0.0, 0.0, 0.0), RED),
+    axis_vertex(vec3(1.0, 0.0, 0.0), RED),
+    axis_vertex(vec3(0.0, 0.0, 0.0), GREEN),
+    axis_vertex(vec3(0.0, 1.0, 0.0), GREEN),
+    axis_vertex(vec3(0.0, 0.0, 0.0), BLUE),
+    axis_vertex(vec3(0.0, 0.0, 1.0), BLUE),
+];
+
+const fn axis_vertex(pos: Vec3, color: Vec3) -> Vertex {
+    let zero = vec3(0.0, 0.0, 0.0);
+    Vertex::new(pos, color, vec2(0.0, 0.0), zero, zero, 0.0, zero)
+}
+
+pub unsafe fn create_gizmo_buffer(
+    instance: &Instance,
+    device: &Device,
+    data: &mut AppData,
+) -> Result<()> {
+    let (buffer, memory) = upload_buffer(
+        instance,
+        device,
+        data,
+        &GIZMO_VERTICES,
+        vk::BufferUsageFlags::VERTEX_BUFFER,
+    )?;
+    data.gizmo_vertex_buffer = buffer;
+    data.gizmo_vertex_buffer_memory = memory;
+
+    Ok(())
+}
+
+/// Rotates the axes like the world is seen through `view`, keeping them
+/// centered in the gizmo viewport.
+pub fn gizmo_matrix(view: Mat4) -> Mat4 {
+    let mut rotation = view;
+    rotation[3] = vec4(0.0, 0.0, 0.0, 1.0);
+
+    // Flip Y for Vulkan and map Z from [-1, 1] to [0, 1].
+    #[rustfmt::skip]
+    let projection = Mat4::new(
+        0.8,  0.0, 0.0, 0.0,
+        0.0, -0.8, 0.0, 0.0,
+        0.0,  0.0, 0.5, 0.0,
+        0.0,  0.0, 0.5, 1.0,
+    );
+
+    projection * rotation
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gizmo_segments_are_the_unit_axes() {
+        let axes = [
+            vec3(1.0, 0.0, 0.0),
+            vec3(0.0, 1.0, 0.0),
+            vec3(0.0, 0.0, 1.0),
+        ];
+        for (segment, axis) in GIZMO_VERTICES.chunks_exact(2).zip(axes) {
+            assert_eq!(segment[0].pos, vec3(0.0, 0.0, 0.0));
+            assert_eq!(segment[1].pos, axis);
+            // Each axis has the color of its component, X in red.
+            assert_eq!(segment[0].color, axis);
+            assert_eq!(segment[1].color, axis);
+        }
+    }
+}
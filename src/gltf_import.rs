@@ -0,0 +1,148 @@
+use std::path::Path;
+
+use anyhow::Result;
+use gltf::image::Source;
+use gltf::mesh::Mode;
+
+use crate::obj::{Material, Mesh, Model, Transform};
+
+/// Whether `path` names a glTF file, by its extension.
+pub fn is_gltf(path: &str) -> bool {
+    let path = path.to_lowercase();
+    path.ends_with(".gltf") || path.ends_with(".glb")
+}
+
+/// Path of the image of `texture`. Only images stored next to the file are
+/// supported, not those embedded in its buffers.
+fn texture_path(directory: &Path, texture: gltf::Texture) -> Option<String> {
+    match texture.source().source() {
+        Source::Uri { uri, .. } => Some(directory.join(uri).to_string_lossy().into_owned()),
+        Source::View { .. } => {
+            log::warn!("Embedded glTF textures are not supported");
+            None
+        }
+    }
+}
+
+/// Parses the meshes of a `.gltf` or `.glb` file into the models and
+/// materials the OBJ loader produces, one model per primitive.
+///
+/// Node transforms are ignored, the meshes are placed as stored.
+pub fn load_gltf(path: &str) -> Result<(Vec<Model>, Vec<Material>)> {
+    let (document, buffers, _) = gltf::import(path)?;
+    let directory = Path::new(path).parent().unwrap_or(Path::new(""));
+
+    let materials = document
+        .materials()
+        .map(|material| {
+            let pbr = material.pbr_metallic_roughness();
+            let [r, g, b, _] = pbr.base_color_factor();
+            Material {
+                name: material.name().unwrap_or("undefined").to_owned(),
+                diffuse: [r, g, b],
+                texture: pbr
+                    .base_color_texture()
+                    .and_then(|info| texture_path(directory, info.texture())),
+                ..Default::default()
+            }
+        })
+        .collect();
+
+    let mut models = Vec::new();
+    for mesh in document.meshes() {
+        for primitive in mesh.primitives() {
+            let reader = primitive.reader(|buffer| Some(&buffers[buffer.index()]));
+            let Some(positions) = reader.read_positions() else {
+                continue;
+            };
+
+            let mut data = Mesh {
+                positions: positions.flatten().collect(),
+                material_id: primitive.material().index(),
+                ..Default::default()
+            };
+            if let Some(normals) = reader.read_normals() {
+                data.normals = normals.flatten().collect();
+            }
+            // glTF texture coordinates start at the top, OBJ ones at the bottom.
+            if let Some(tex_coords) = reader.read_tex_coords(0) {
+                data.tex_coords = tex_coords
+                    .into_f32()
+                    .flat_map(|[u, v]| [u, 1.0 - v])
+                    .collect();
+            }
+            if let Some(tex_coords) = reader.read_tex_coords(1) {
+                data.lightmap_tex_coords = tex_coords
+                    .into_f32()
+                    .flat_map(|[u, v]| [u, 1.0 - v])
+                    .collect();
+            }
+            let indices = match reader.read_indices() {
+                Some(indices) => indices.into_u32().collect(),
+                None => (0..data.positions.len() as u32 / 3).collect(),
+            };
+            match primitive.mode() {
+                Mode::Triangles => data.indices = indices,
+                Mode::Points => data.point_indices = indices,
+                mode => {
+                    log::warn!("glTF {:?} primitives are not supported", mode);
+                    continue;
+                }
+            }
+
+            models.push(Model {
+                name: mesh.name().unwrap_or("undefined").to_owned(),
+                group: format!("primitive {}", primitive.index()),
+                mesh: data,
+                transform: Transform::default(),
+            });
+        }
+    }
+
+    Ok((models, materials))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The triangle of the glTF tutorial, its buffer embedded as a data URI.
+    const TRIANGLE: &str = r#"{
+        "scene": 0,
+        "scenes": [{ "nodes": [0] }],
+        "nodes": [{ "mesh": 0 }],
+        "meshes": [{ "primitives": [{ "attributes": { "POSITION": 1 }, "indices": 0 }] }],
+        "buffers": [{
+            "uri": "data:application/octet-stream;base64,AAABAAIAAAAAAAAAAAAAAAAAAAAAAIA/AAAAAAAAAAAAAAAAAACAPwAAAAA=",
+            "byteLength": 44
+        }],
+        "bufferViews": [
+            { "buffer": 0, "byteOffset": 0, "byteLength": 6, "target": 34963 },
+            { "buffer": 0, "byteOffset": 8, "byteLength": 36, "target": 34962 }
+        ],
+        "accessors": [
+            {
+                "bufferView": 0, "componentType": 5123, "count": 3, "type": "SCALAR",
+                "max": [2], "min": [0]
+            },
+            {
+                "bufferView": 1, "componentType": 5126, "count": 3, "type": "VEC3",
+                "max": [1.0, 1.0, 0.0], "min": [0.0, 0.0, 0.0]
+            }
+        ],
+        "asset": { "version": "2.0" }
+    }"#;
+
+    #[test]
+    fn triangle_is_loaded() {
+        let path = std::env::temp_dir().join("scop_triangle.gltf");
+        std::fs::write(&path, TRIANGLE).unwrap();
+        let (models, materials) = load_gltf(&path.to_string_lossy()).unwrap();
+        assert!(materials.is_empty());
+        assert_eq!(models.len(), 1);
+        let mesh = &models[0].mesh;
+        assert_eq!(mesh.positions.len(), 3 * 3);
+        assert_eq!(mesh.indices, [0, 1, 2]);
+        assert_eq!(mesh.positions[3..6], [1.0, 0.0, 0.0]);
+    }
+}
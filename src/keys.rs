@@ -0,0 +1,423 @@
+use log::*;
+use winit::window::Window;
+
+use crate::math::vec3;
+use crate::{device, textures, App, SOLID_COLORS, WIREFRAME_COLORS};
+
+/// What a key does when pressed.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Action {
+    MoveForward,
+    MoveBackward,
+    MoveLeft,
+    MoveRight,
+    MoveDown,
+    MoveUp,
+    AutoRotate,
+    AutoRotateAxis,
+    AutoRotateFaster,
+    AutoRotateSlower,
+    Wireframe,
+    CycleTexture,
+    InvertX,
+    InvertY,
+    ZUp,
+    Outline,
+    Bounds,
+    Edges,
+    DepthView,
+    WireframeColor,
+    PointSizeUp,
+    PointSizeDown,
+    LineWidthUp,
+    LineWidthDown,
+    SharpenTextures,
+    BlurTextures,
+    AddressMode,
+    SolidColor,
+    NormalColor,
+    ColorMode,
+    ActiveObject,
+    ExposureUp,
+    ExposureDown,
+    GammaUp,
+    GammaDown,
+    PickColor,
+    Help,
+}
+
+/// Every key binding with its description, dispatch goes through this table.
+pub const KEY_BINDINGS: &[(&str, Action, &str)] = &[
+    ("w", Action::MoveForward, "Move the model forward"),
+    ("s", Action::MoveBackward, "Move the model backward"),
+    ("a", Action::MoveLeft, "Move the model left"),
+    ("d", Action::MoveRight, "Move the model right"),
+    ("q", Action::MoveDown, "Move the model down"),
+    ("e", Action::MoveUp, "Move the model up"),
+    ("r", Action::AutoRotate, "Toggle the automatic rotation"),
+    ("v", Action::AutoRotateAxis, "Cycle the rotation axis"),
+    (">", Action::AutoRotateFaster, "Speed up the rotation"),
+    ("<", Action::AutoRotateSlower, "Slow down the rotation"),
+    ("f", Action::Wireframe, "Toggle the wireframe"),
+    ("t", Action::CycleTexture, "Cycle the generated texture"),
+    ("x", Action::InvertX, "Invert the horizontal mouse rotation"),
+    ("y", Action::InvertY, "Invert the vertical mouse rotation"),
+    ("u", Action::ZUp, "Toggle the Z-up correction"),
+    ("o", Action::Outline, "Toggle the outline"),
+    ("b", Action::Bounds, "Toggle the bounding box"),
+    ("g", Action::Edges, "Toggle the crease and boundary edges"),
+    ("z", Action::DepthView, "Toggle the depth buffer view"),
+    ("l", Action::WireframeColor, "Cycle the wireframe color"),
+    ("+", Action::PointSizeUp, "Increase the point size"),
+    ("-", Action::PointSizeDown, "Decrease the point size"),
+    ("]", Action::LineWidthUp, "Increase the line width"),
+    ("[", Action::LineWidthDown, "Decrease the line width"),
+    (",", Action::SharpenTextures, "Sharpen the textures"),
+    (".", Action::BlurTextures, "Blur the textures"),
+    ("m", Action::AddressMode, "Cycle the texture address mode"),
+    ("k", Action::SolidColor, "Cycle the solid color mode"),
+    ("n", Action::NormalColor, "Toggle the normals as colors"),
+    (
+        "c",
+        Action::ColorMode,
+        "Toggle between the face colors and the texture",
+    ),
+    ("i", Action::ActiveObject, "Cycle the highlighted object"),
+    ("0", Action::ExposureUp, "Increase the exposure"),
+    ("9", Action::ExposureDown, "Decrease the exposure"),
+    (")", Action::GammaUp, "Increase the output gamma"),
+    ("(", Action::GammaDown, "Decrease the output gamma"),
+    ("p", Action::PickColor, "Log the color under the cursor"),
+    ("h", Action::Help, "Show this help"),
+];
+
+/// Returns the action bound to `key`, if any.
+pub fn action_for(key: &str) -> Option<Action> {
+    KEY_BINDINGS
+        .iter()
+        .find(|(k, _, _)| *k == key)
+        .map(|(_, action, _)| *action)
+}
+
+/// Object highlighted after `active` among `count` objects: none, then each
+/// of the objects.
+fn next_active_object(active: Option<usize>, count: usize) -> Option<usize> {
+    match active {
+        None if count > 0 => Some(0),
+        Some(i) if i + 1 < count => Some(i + 1),
+        _ => None,
+    }
+}
+
+/// What `App::apply` rebuilds once an action changed a setting.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Rebuild {
+    /// The setting is read every frame, or the action rebuilt what it needs.
+    Nothing,
+    /// The setting is only baked in the command buffers.
+    Commands,
+    /// The setting is baked in the pipelines, such as the polygon mode of the
+    /// wireframe, the command buffers binding them are recorded again too.
+    Pipeline,
+    /// The setting is baked in the depth image or the descriptor sets, which
+    /// are rebuilt with the swapchain.
+    Swapchain,
+}
+
+impl Action {
+    /// What must be rebuilt for the setting changed by the action to show.
+    fn rebuild(self) -> Rebuild {
+        match self {
+            Action::Outline
+            | Action::Bounds
+            | Action::Edges
+            | Action::WireframeColor
+            | Action::PointSizeUp
+            | Action::PointSizeDown
+            | Action::ColorMode
+            | Action::ActiveObject => Rebuild::Commands,
+            Action::Wireframe => Rebuild::Pipeline,
+            Action::DepthView
+            | Action::LineWidthUp
+            | Action::LineWidthDown
+            | Action::SolidColor
+            | Action::NormalColor => Rebuild::Swapchain,
+            _ => Rebuild::Nothing,
+        }
+    }
+}
+
+impl App {
+    /// Performs `action`, rebuilding what it affects.
+    ///
+    /// # Safety
+    ///
+    /// Must be called between frames, from the thread owning the device: the
+    /// rebuilds wait for it to be idle and destroy the objects it used.
+    pub unsafe fn apply(&mut self, action: Action, window: &Window) {
+        let (controls, data) = (&mut self.controls, &mut self.data);
+        match action {
+            Action::MoveForward => controls.object_pos.z += 1.0,
+            Action::MoveBackward => controls.object_pos.z -= 1.0,
+            Action::MoveLeft => controls.object_pos.x -= 1.0,
+            Action::MoveRight => controls.object_pos.x += 1.0,
+            Action::MoveDown => controls.object_pos.y -= 1.0,
+            Action::MoveUp => controls.object_pos.y += 1.0,
+            Action::AutoRotate => controls.auto_rotate = !controls.auto_rotate,
+            // X, then Y, then Z.
+            Action::AutoRotateAxis => {
+                let axis = controls.auto_rotate_axis;
+                controls.auto_rotate_axis = vec3(axis.z, axis.x, axis.y);
+            }
+            Action::AutoRotateFaster => controls.auto_rotate_speed *= 1.5,
+            Action::AutoRotateSlower => controls.auto_rotate_speed /= 1.5,
+            Action::InvertX => controls.invert_x = !controls.invert_x,
+            Action::InvertY => controls.invert_y = !controls.invert_y,
+            Action::ZUp => controls.z_up = !controls.z_up,
+            Action::Outline => data.outline = !data.outline,
+            Action::Bounds => data.show_bounds = !data.show_bounds,
+            Action::Edges => data.show_edges = !data.show_edges,
+            Action::DepthView => data.depth_view = !data.depth_view,
+            Action::WireframeColor => {
+                data.wireframe_color = (data.wireframe_color + 1) % WIREFRAME_COLORS.len()
+            }
+            Action::PointSizeUp => {
+                data.point_size = (data.point_size + 1.0).min(data.max_point_size)
+            }
+            Action::PointSizeDown => data.point_size = (data.point_size - 1.0).max(1.0),
+            Action::LineWidthUp => {
+                data.line_width =
+                    device::clamp_line_width(data.line_width + 1.0, data.line_width_range)
+            }
+            Action::LineWidthDown => {
+                data.line_width =
+                    device::clamp_line_width(data.line_width - 1.0, data.line_width_range)
+            }
+            Action::SharpenTextures | Action::BlurTextures => {
+                let step = if action == Action::BlurTextures {
+                    0.5
+                } else {
+                    -0.5
+                };
+                self.data.mip_lod_bias = textures::clamp_lod_bias(
+                    self.data.mip_lod_bias + step,
+                    self.data.max_sampler_lod_bias,
+                );
+                if let Err(error) = self.recreate_texture_sampler() {
+                    error!("Failed to recreate the texture sampler: {}", error);
+                }
+            }
+            Action::AddressMode => {
+                self.data.address_mode = textures::next_address_mode(self.data.address_mode);
+                if let Err(error) = self.recreate_texture_sampler() {
+                    error!("Failed to recreate the texture sampler: {}", error);
+                }
+            }
+            // Off, then each of the solid colors.
+            Action::SolidColor => {
+                data.solid_color = match data.solid_color {
+                    None => Some(0),
+                    Some(i) if i + 1 < SOLID_COLORS.len() => Some(i + 1),
+                    Some(_) => None,
+                }
+            }
+            Action::NormalColor => data.normal_color = !data.normal_color,
+            Action::ColorMode => data.color_mod = !data.color_mod,
+            Action::ActiveObject => {
+                data.active_object = next_active_object(data.active_object, data.objects.len());
+                if let Some(object) = data.active_object.map(|i| &data.objects[i]) {
+                    info!("Active object: {}", object.name);
+                }
+            }
+            Action::ExposureUp => controls.exposure *= 1.25,
+            Action::ExposureDown => controls.exposure /= 1.25,
+            Action::GammaUp => controls.gamma += 0.1,
+            Action::GammaDown => controls.gamma = (controls.gamma - 0.1).max(0.1),
+            Action::Wireframe => data.wireframe = !data.wireframe,
+            Action::CycleTexture => {
+                self.data.generated_texture = self.data.generated_texture.next();
+                // The generated texture only shows when no texture was given.
+                let (obj_path, texture_path) = (self.obj_path.clone(), self.texture_path.clone());
+                if let Err(error) = self.load_new_model(obj_path, texture_path) {
+                    error!("Failed to generate texture: {}", error);
+                }
+            }
+            Action::PickColor => {
+                let pos = self.controls.last_mouse_pos;
+                if let Err(error) = self.request_pixel(pos.x as u32, pos.y as u32) {
+                    warn!("Failed to read pixel: {}", error);
+                }
+            }
+            Action::Help => self.log_help(),
+        }
+        match action.rebuild() {
+            Rebuild::Nothing => {}
+            Rebuild::Commands => {
+                if let Err(error) = self.rerecord_command_buffers() {
+                    error!("Failed to record the command buffers: {}", error);
+                }
+            }
+            Rebuild::Pipeline => {
+                if let Err(error) = self.recreate_pipeline() {
+                    error!("Failed to recreate the pipeline: {}", error);
+                }
+            }
+            Rebuild::Swapchain => {
+                if let Err(error) = self.recreate_swapchain(window) {
+                    error!("Failed to recreate the swapchain: {}", error);
+                }
+            }
+        }
+    }
+
+    /// Current state of the setting changed by `action`, if it has one.
+    fn state(&self, action: Action) -> Option<String> {
+        let (controls, data) = (&self.controls, &self.data);
+        let state = match action {
+            Action::AutoRotate => controls.auto_rotate.to_string(),
+            Action::AutoRotateAxis => format!("{:?}", controls.auto_rotate_axis),
+            Action::AutoRotateFaster | Action::AutoRotateSlower => {
+                controls.auto_rotate_speed.to_string()
+            }
+            Action::Wireframe => data.wireframe.to_string(),
+            Action::CycleTexture => format!("{:?}", data.generated_texture),
+            Action::InvertX => controls.invert_x.to_string(),
+            Action::InvertY => controls.invert_y.to_string(),
+            Action::ZUp => controls.z_up.to_string(),
+            Action::Outline => data.outline.to_string(),
+            Action::Bounds => data.show_bounds.to_string(),
+            Action::Edges => data.show_edges.to_string(),
+            Action::DepthView => data.depth_view.to_string(),
+            Action::WireframeColor => data.wireframe_color.to_string(),
+            Action::PointSizeUp | Action::PointSizeDown => data.point_size.to_string(),
+            Action::LineWidthUp | Action::LineWidthDown => data.line_width.to_string(),
+            Action::SharpenTextures | Action::BlurTextures => data.mip_lod_bias.to_string(),
+            Action::AddressMode => format!("{:?}", data.address_mode),
+            Action::SolidColor => format!("{:?}", data.solid_color),
+            Action::NormalColor => data.normal_color.to_string(),
+            Action::ColorMode => data.color_mod.to_string(),
+            Action::ActiveObject => match data.active_object {
+                Some(i) => data.objects[i].name.clone(),
+                None => "none".to_owned(),
+            },
+            Action::ExposureUp | Action::ExposureDown => controls.exposure.to_string(),
+            Action::GammaUp | Action::GammaDown => controls.gamma.to_string(),
+            _ => return None,
+        };
+        Some(state)
+    }
+
+    /// Logs every key binding and the state of what it changes.
+    pub fn log_help(&self) {
+        info!("Key bindings:");
+        for (key, action, description) in KEY_BINDINGS {
+            match self.state(*action) {
+                Some(state) => info!("  {:2} {} ({})", key, description, state),
+                None => info!("  {:2} {}", key, description),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_action_has_a_key() {
+        // Every action, new ones must be added here.
+        let actions = [
+            Action::MoveForward,
+            Action::MoveBackward,
+            Action::MoveLeft,
+            Action::MoveRight,
+            Action::MoveDown,
+            Action::MoveUp,
+            Action::AutoRotate,
+            Action::AutoRotateAxis,
+            Action::AutoRotateFaster,
+            Action::AutoRotateSlower,
+            Action::Wireframe,
+            Action::CycleTexture,
+            Action::InvertX,
+            Action::InvertY,
+            Action::ZUp,
+            Action::Outline,
+            Action::Bounds,
+            Action::Edges,
+            Action::DepthView,
+            Action::WireframeColor,
+            Action::PointSizeUp,
+            Action::PointSizeDown,
+            Action::LineWidthUp,
+            Action::LineWidthDown,
+            Action::SharpenTextures,
+            Action::BlurTextures,
+            Action::AddressMode,
+            Action::SolidColor,
+            Action::NormalColor,
+            Action::ColorMode,
+            Action::ActiveObject,
+            Action::ExposureUp,
+            Action::ExposureDown,
+            Action::GammaUp,
+            Action::GammaDown,
+            Action::PickColor,
+            Action::Help,
+        ];
+        for action in actions {
+            let bound = KEY_BINDINGS.iter().any(|(_, a, _)| *a == action);
+            assert!(bound, "{:?}", action);
+        }
+    }
+
+    #[test]
+    fn keys_dispatch_to_their_action() {
+        for (i, (key, action, _)) in KEY_BINDINGS.iter().enumerate() {
+            assert_eq!(action_for(key), Some(*action), "{}", key);
+            assert!(KEY_BINDINGS[i + 1..].iter().all(|(k, _, _)| k != key));
+        }
+        assert_eq!(action_for("F13"), None);
+    }
+
+    #[test]
+    fn draw_settings_skip_the_swapchain_recreation() {
+        // The polygon mode is baked in the pipeline, only it is rebuilt.
+        assert_eq!(Action::Wireframe.rebuild(), Rebuild::Pipeline);
+        for action in [Action::Outline, Action::WireframeColor, Action::ColorMode] {
+            assert_eq!(action.rebuild(), Rebuild::Commands, "{:?}", action);
+        }
+        for action in [Action::DepthView, Action::LineWidthUp, Action::NormalColor] {
+            assert_eq!(action.rebuild(), Rebuild::Swapchain, "{:?}", action);
+        }
+    }
+
+    #[test]
+    fn per_frame_settings_rebuild_nothing() {
+        // The sampler and texture actions rebuild what they need themselves.
+        for action in [
+            Action::MoveForward,
+            Action::AutoRotate,
+            Action::ExposureUp,
+            Action::GammaDown,
+            Action::SharpenTextures,
+            Action::CycleTexture,
+            Action::PickColor,
+            Action::Help,
+        ] {
+            assert_eq!(action.rebuild(), Rebuild::Nothing, "{:?}", action);
+        }
+    }
+
+    #[test]
+    fn active_object_wraps_around() {
+        let mut active = None;
+        let mut cycle = Vec::new();
+        for _ in 0..4 {
+            active = next_active_object(active, 3);
+            cycle.push(active);
+        }
+        assert_eq!(cycle, [Some(0), Some(1), Some(2), None]);
+        assert_eq!(next_active_object(None, 0), None);
+    }
+}
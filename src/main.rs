@@ -1,33 +1,55 @@
+mod args;
+mod bounds;
 mod buffers;
+mod camera;
+mod color;
+mod config;
 mod depth;
 mod descriptor;
 mod device;
+mod edges;
+mod frame_limit;
+mod gizmo;
+#[cfg(feature = "gltf")]
+mod gltf_import;
+mod keys;
 mod math;
 mod model;
 mod obj;
+mod offscreen;
 mod pipeline;
+#[cfg(feature = "ply")]
+mod ply;
+mod readback;
 mod swapchain;
 mod textures;
+mod timing;
 mod vertex;
+#[cfg(any(feature = "hot-reload", feature = "shader-reload"))]
+mod watcher;
 
 use anyhow::{anyhow, Result};
 use descriptor::{Mat4, UniformBufferObject};
 use device::{create_logical_device, pick_physical_device};
 use log::*;
-use math::{perspective, vec2, vec3, Deg, Vec2, Vec3};
+use camera::Camera;
+use config::AppConfig;
+use math::{vec3, vec4, Frustum, Vec2, Vec3};
 use std::collections::HashSet;
 use std::ffi::CStr;
 use std::mem::size_of;
 use std::os::raw::c_void;
 use std::ptr::copy_nonoverlapping as memcpy;
 use std::time::Instant;
+use readback::PixelReadback;
+use swapchain::SwapchainStatus;
 use vertex::Vertex;
-use winit::keyboard::Key;
+use winit::keyboard::{Key, NamedKey};
 
 use winit::dpi::LogicalSize;
 use winit::event::{ElementState, Event, KeyEvent, MouseButton, WindowEvent};
 use winit::event_loop::EventLoop;
-use winit::window::{Window, WindowBuilder};
+use winit::window::{Fullscreen, Window, WindowBuilder};
 
 use vulkanalia::loader::{LibloadingLoader, LIBRARY};
 use vulkanalia::prelude::v1_2::*;
@@ -37,6 +59,12 @@ use vulkanalia::Version;
 
 pub const PORTABILITY_MACOS_VERSION: Version = Version::new(1, 3, 216);
 
+/// The minimum Vulkan version a device must support. Only core 1.0
+/// functionality is used, the 1.2 prelude is imported for its wrappers.
+pub const MIN_API_VERSION: Version = Version::new(1, 0, 0);
+
+/// Validation is on in debug builds, unless turned off at runtime with
+/// `SCOP_NO_VALIDATION`.
 pub const VALIDATION_ENABLED: bool = cfg!(debug_assertions);
 
 pub const VALIDATION_LAYER: vk::ExtensionName =
@@ -44,37 +72,117 @@ pub const VALIDATION_LAYER: vk::ExtensionName =
 
 pub const MAX_FRAMES_IN_FLIGHT: usize = 2;
 
+pub const DEFAULT_POINT_SIZE: f32 = 4.0;
+
+pub const DEFAULT_LINE_WIDTH: f32 = 1.0;
+
+/// Output gamma applied by the fragment shader. The sRGB swapchain already
+/// encodes the colors, so none is added by default.
+pub const DEFAULT_GAMMA: f32 = 1.0;
+
+/// How much larger than the model its outline is drawn.
+pub const OUTLINE_SCALE: f32 = 1.05;
+
+/// Colors cycled through for the wireframe lines.
+pub const WIREFRAME_COLORS: [Vec3; 4] = [
+    vec3(0.0, 1.0, 0.0),
+    vec3(1.0, 1.0, 1.0),
+    vec3(1.0, 0.0, 1.0),
+    vec3(1.0, 1.0, 0.0),
+];
+
+/// Colors cycled through by the solid color mode.
+pub const SOLID_COLORS: [Vec3; 3] = [
+    vec3(0.8, 0.8, 0.8),
+    vec3(0.9, 0.5, 0.2),
+    vec3(0.2, 0.5, 0.9),
+];
+
 fn main() -> Result<()> {
     pretty_env_logger::init();
 
-    let obj_path = std::env::args()
-        .nth(1)
-        .unwrap_or_else(|| String::from("./resources/texture_cube.obj"));
-    let texture_path = std::env::args()
-        .nth(2)
-        .unwrap_or_else(|| String::from("./resources/orange_texture.png"));
+    let args = args::Args::parse(std::env::args().skip(1))?;
+    let max_fps = frame_limit::max_fps(args.max_fps.as_deref());
+    let mut config = AppConfig::default()
+        .texture_path(args.texture_path)
+        .z_up(args.z_up);
+    if let Some(obj_path) = args.obj_path {
+        config = config.obj_path(obj_path);
+    }
+    if let Some(rotation) = args.rotation {
+        config = config.rotation(rotation);
+    }
+    if let Some(zoom) = args.zoom {
+        config = config.zoom(zoom);
+    }
 
     // Window
 
     let event_loop = EventLoop::new()?;
     let window = WindowBuilder::new()
         .with_title("scop")
-        .with_inner_size(LogicalSize::new(1024, 768))
+        .with_inner_size(LogicalSize::new(
+            args.width.unwrap_or(1024),
+            args.height.unwrap_or(768),
+        ))
         .build(&event_loop)?;
 
     // App
 
-    let mut app = unsafe { App::create(&window, obj_path, texture_path)? };
+    let mut app = unsafe { App::create(&window, config)? };
     let mut minimized = false;
+    let mut frame_limiter = frame_limit::FrameLimiter::new(max_fps);
+    #[cfg(feature = "hot-reload")]
+    let mut watcher = watcher::FileWatcher::new(watcher::DEBOUNCE);
+    #[cfg(feature = "shader-reload")]
+    let mut shader_watcher = watcher::FileWatcher::new(watcher::DEBOUNCE);
 
     event_loop.run(move |event, elwt| {
         match event {
             // Request a redraw when all events were processed.
-            Event::AboutToWait => window.request_redraw(),
+            Event::AboutToWait => {
+                // Reload the model when its files change on disk.
+                #[cfg(feature = "hot-reload")]
+                if watcher.poll(&model_paths(&app)) {
+                    let (obj_path, texture_path) = (app.obj_path.clone(), app.texture_path.clone());
+                    if let Err(error) = unsafe { app.load_new_model(obj_path, texture_path) } {
+                        error!("Failed to reload model: {}", error);
+                    }
+                }
+                // Recompile the shaders and rebuild the pipeline when they change.
+                #[cfg(feature = "shader-reload")]
+                if shader_watcher.poll(&pipeline::SHADER_SOURCE_PATHS) {
+                    info!("Reloading shaders.");
+                    if let Err(error) = pipeline::compile_shaders()
+                        .and_then(|_| unsafe { app.recreate_pipeline() })
+                    {
+                        error!("Failed to reload shaders: {}", error);
+                    }
+                }
+                frame_limiter.wait();
+                window.request_redraw()
+            }
             Event::WindowEvent { event, .. } => match event {
                 // Render a frame if our Vulkan app is not being destroyed.
                 WindowEvent::RedrawRequested if !elwt.exiting() && !minimized => {
-                    unsafe { app.render(&window) }.unwrap()
+                    if let Err(error) = unsafe { app.render(&window) } {
+                        error!("Failed to render: {}", error);
+                        // Rebuild what went out of date, exit on anything else.
+                        let recovered = match SwapchainStatus::from_error(&error) {
+                            SwapchainStatus::Recreate => unsafe { app.recreate_swapchain(&window) },
+                            SwapchainStatus::RecreateSurface => unsafe {
+                                app.recreate_surface(&window)
+                            },
+                            status => Err(anyhow!("Unrecoverable render error: {:?}", status)),
+                        };
+                        if let Err(error) = recovered {
+                            error!("{}", error);
+                            elwt.exit();
+                            unsafe {
+                                app.destroy();
+                            }
+                        }
+                    }
                 }
                 // Destroy our Vulkan app.
                 WindowEvent::CloseRequested => {
@@ -91,18 +199,32 @@ fn main() -> Result<()> {
                         app.resized = true;
                     }
                 }
+                // Swap the model (or its texture when a PNG is dropped).
+                WindowEvent::DroppedFile(path) => {
+                    let path = path.to_string_lossy().into_owned();
+                    let (obj_path, texture_path) = if path.to_lowercase().ends_with(".png") {
+                        (app.obj_path.clone(), Some(path))
+                    } else {
+                        (path, app.texture_path.clone())
+                    };
+                    if let Err(error) = unsafe { app.load_new_model(obj_path, texture_path) } {
+                        error!("Failed to load dropped file: {}", error);
+                    }
+                }
                 // Client input
                 WindowEvent::MouseWheel { delta, .. } => match delta {
                     winit::event::MouseScrollDelta::LineDelta(_, y) => {
                         let value = y as f32 * 0.1;
                         if app.controls.zoom + value > 0.0 {
                             app.controls.zoom += value;
+                            app.controls.zoomed = true;
                         }
                     }
                     winit::event::MouseScrollDelta::PixelDelta(pos) => {
                         let value = pos.y as f32 * 0.01;
                         if app.controls.zoom + value > 0.0 {
                             app.controls.zoom += value;
+                            app.controls.zoomed = true;
                         }
                     }
                 },
@@ -115,8 +237,7 @@ fn main() -> Result<()> {
                     if app.controls.mouse_pressed {
                         let delta_x = position.x as f32 - app.controls.last_mouse_pos.x;
                         let delta_y = position.y as f32 - app.controls.last_mouse_pos.y;
-                        app.controls.rotation.x += delta_x as f32 * 0.1;
-                        app.controls.rotation.y += delta_y as f32 * -0.1;
+                        app.controls.rotate(delta_x, delta_y);
                     }
                     app.controls.last_mouse_pos.x = position.x as f32;
                     app.controls.last_mouse_pos.y = position.y as f32;
@@ -129,42 +250,24 @@ fn main() -> Result<()> {
                             ..
                         },
                     ..
-                } => match (key.as_ref(), state) {
-                    (Key::Character("w"), ElementState::Pressed) => {
-                        app.controls.object_pos.z += 1.0
-                    }
-                    (Key::Character("s"), ElementState::Pressed) => {
-                        app.controls.object_pos.z -= 1.0
-                    }
-                    (Key::Character("a"), ElementState::Pressed) => {
-                        app.controls.object_pos.x -= 1.0
-                    }
-                    (Key::Character("d"), ElementState::Pressed) => {
-                        app.controls.object_pos.x += 1.0
-                    }
-                    (Key::Character("q"), ElementState::Pressed) => {
-                        app.controls.object_pos.y -= 1.0
-                    }
-                    (Key::Character("e"), ElementState::Pressed) => {
-                        app.controls.object_pos.y += 1.0
-                    }
-                    (Key::Character("r"), ElementState::Pressed) => {
-                        app.controls.auto_rotate = !app.controls.auto_rotate
-                    }
-                    (Key::Character("f"), ElementState::Pressed) => {
-                        app.data.wireframe = !app.data.wireframe;
-                        unsafe {
-                            let _ = app.recreate_swapchain(&window);
+                } => {
+                    match (key.as_ref(), state) {
+                        (Key::Character(key), ElementState::Pressed) => {
+                            if let Some(action) = keys::action_for(key) {
+                                unsafe { app.apply(action, &window) };
+                            }
                         }
-                    }
-                    (Key::Character("c"), ElementState::Pressed) => {
-                        app.data.color_mod = !app.data.color_mod;
-                        unsafe {
-                            let _ = app.recreate_swapchain(&window);
+                        // The resize that follows recreates the swapchain.
+                        (Key::Named(NamedKey::F11), ElementState::Pressed) => {
+                            let fullscreen = match window.fullscreen() {
+                                Some(_) => None,
+                                None => Some(Fullscreen::Borderless(None)),
+                            };
+                            window.set_fullscreen(fullscreen);
                         }
+                        _ => {}
                     }
-                    _ => {}
-                },
+                }
                 _ => {}
             },
             _ => {}
@@ -178,15 +281,78 @@ fn main() -> Result<()> {
 #[derive(Clone, Debug, Default)]
 struct Controls {
     zoom: f32,
+    /// Whether the user zoomed, the framing then no longer follows resizes.
+    zoomed: bool,
+    /// Camera distance framing the whole model, scaled by `zoom`.
+    fit_distance: f32,
     rotation: Vec2,
     auto_rotate: bool,
+    /// Axis the model spins around when `auto_rotate` is on, normalized
+    /// before use.
+    auto_rotate_axis: Vec3,
+    /// Spin speed in radians per second.
+    auto_rotate_speed: f32,
     mouse_pressed: bool,
     last_mouse_pos: Vec2,
     object_pos: Vec3,
+    invert_x: bool,
+    invert_y: bool,
+    /// Whether the model uses Z rather than Y as its up axis.
+    z_up: bool,
+    /// Factor the shaded colors are multiplied by, to inspect dark models.
+    exposure: f32,
+    /// Gamma the shaded colors are raised to the inverse of.
+    gamma: f32,
+}
+
+impl Controls {
+    /// Controls of a newly opened model, at the given framing.
+    fn new(zoom: f32, rotation: Vec2, z_up: bool) -> Self {
+        Controls {
+            zoom,
+            fit_distance: 20.0,
+            rotation,
+            z_up,
+            auto_rotate: false,
+            auto_rotate_axis: vec3(0.0, 1.0, 0.0),
+            auto_rotate_speed: 1.0,
+            exposure: 1.0,
+            gamma: DEFAULT_GAMMA,
+            ..Default::default()
+        }
+    }
+
+    /// Applies a mouse drag of `delta_x`, `delta_y` pixels to the rotation.
+    fn rotate(&mut self, delta_x: f32, delta_y: f32) {
+        let sign_x = if self.invert_x { -1.0 } else { 1.0 };
+        let sign_y = if self.invert_y { 1.0 } else { -1.0 };
+        self.rotation.x += delta_x * 0.1 * sign_x;
+        self.rotation.y += delta_y * 0.1 * sign_y;
+    }
+
+    /// Rotation of the model `time` seconds after the start, a fixed angle
+    /// when it isn't spinning.
+    fn auto_rotation(&self, time: f32) -> Mat4 {
+        let angle = if self.auto_rotate {
+            time * self.auto_rotate_speed
+        } else {
+            1.0
+        };
+        Mat4::from_axis_angle(self.auto_rotate_axis.normalize(), angle)
+    }
+
+    /// Rotation bringing the up axis of the model to +Y.
+    fn up_correction(&self) -> Mat4 {
+        if self.z_up {
+            Mat4::from_angle_x(-std::f32::consts::FRAC_PI_2)
+        } else {
+            Mat4::identity()
+        }
+    }
 }
 
-/// Our Vulkan app.
-#[derive(Clone, Debug)]
+/// Our Vulkan app, destroyed when dropped.
+#[derive(Debug)]
 pub struct App {
     entry: Entry,
     instance: Instance,
@@ -194,54 +360,161 @@ pub struct App {
     device: Device,
     frame: usize,
     resized: bool,
+    obj_path: String,
+    texture_path: Option<String>,
     start: Instant,
     controls: Controls,
+    destroyed: bool,
+}
+
+/// Settings of an app configured by `config`, before any Vulkan object is
+/// created.
+fn initial_data(config: &AppConfig) -> AppData {
+    AppData {
+        dedup_tolerance: config.dedup_tolerance,
+        max_triangles: config.max_triangles,
+        decimate: config.decimate,
+        drop_degenerate: config.drop_degenerate,
+        keep_normals: config.keep_normals,
+        hard_edge_angle: config.hard_edge_angle,
+        validation: config.validation,
+        decimal_comma: config.decimal_comma,
+        winding: config.winding,
+        gpu_timings_enabled: config.gpu_timings,
+        swapchain_image_count: config.swapchain_image_count,
+        force_staging: config.force_staging,
+        reverse_z: config.reverse_z,
+        point_size: DEFAULT_POINT_SIZE,
+        line_width: DEFAULT_LINE_WIDTH,
+        ..Default::default()
+    }
+}
+
+/// Camera controls of an app configured by `config`.
+fn initial_controls(config: &AppConfig) -> Controls {
+    Controls::new(config.zoom, config.rotation, config.z_up)
+}
+
+/// What an app renders its frames to.
+#[derive(Copy, Clone)]
+enum Target<'a> {
+    /// The swapchain of a surface of the window, presented to.
+    Window(&'a Window),
+    /// Images of the given extent, only read back.
+    Offscreen(vk::Extent2D),
 }
 
 impl App {
     /// Creates our Vulkan app.
-    unsafe fn create(window: &Window, obj_path: String, texture_path: String) -> Result<Self> {
+    unsafe fn create(window: &Window, config: AppConfig) -> Result<Self> {
+        Self::create_for(Target::Window(window), config)
+    }
+
+    /// Creates our Vulkan app, rendering to `target`.
+    unsafe fn create_for(target: Target, config: AppConfig) -> Result<Self> {
+        let window = match target {
+            Target::Window(window) => Some(window),
+            Target::Offscreen(_) => None,
+        };
+        let mut data = initial_data(&config);
+        let controls = initial_controls(&config);
+        let AppConfig {
+            obj_path,
+            texture_path,
+            ..
+        } = config;
         let loader = LibloadingLoader::new(LIBRARY)?;
         let entry = Entry::new(loader).map_err(|err| anyhow!(err))?;
-        let mut data = AppData::default();
         let instance = create_instance(window, &entry, &mut data)?;
-        data.surface = vk_window::create_surface(&instance, &window, &window)?;
+        if let Some(window) = window {
+            data.surface = vk_window::create_surface(&instance, &window, &window)?;
+        }
+        for (name, device_type, score, suitable) in device::list_physical_devices(&instance, &data)? {
+            info!(
+                "Physical device `{}`: {:?}, score {}, suitable: {}",
+                name, device_type, score, suitable
+            );
+        }
         pick_physical_device(&instance, &mut data)?;
+        data.depth_format = depth::find_supported_depth_format(&instance, &data)?;
         let device = create_logical_device(&entry, &instance, &mut data)?;
-        swapchain::create_swapchain(window, &instance, &device, &mut data)?;
+        // The descriptor set layout and the pipeline depend on the model.
+        model::load_model(&mut data, obj_path.clone(), texture_path.is_some())?;
+        match target {
+            Target::Window(window) => {
+                swapchain::create_swapchain(window, &instance, &device, &mut data)?
+            }
+            Target::Offscreen(extent) => {
+                offscreen::create_offscreen_images(&instance, &device, &mut data, extent)?
+            }
+        }
         swapchain::create_swapchain_image_views(&device, &mut data)?;
-        pipeline::create_render_pass(&instance, &device, &mut data)?;
+        pipeline::create_render_pass(&device, &mut data)?;
         descriptor::create_descriptor_set_layout(&device, &mut data)?;
         pipeline::create(&device, &mut data)?;
         buffers::create_command_pool(&instance, &device, &mut data)?;
         depth::create_depth_objects(&instance, &device, &mut data)?;
         buffers::create_framebuffers(&device, &mut data)?;
-        textures::create_texture_image(&instance, &device, &mut data, texture_path)?;
-        textures::create_texture_image_view(&device, &mut data)?;
+        textures::create_texture_images(&instance, &device, &mut data, texture_path.as_deref())?;
         textures::create_texture_sampler(&device, &mut data)?;
-        model::load_model(&mut data, obj_path)?;
         vertex::create_vertex_buffer(&instance, &device, &mut data)?;
+        vertex::create_lightmap_buffer(&instance, &device, &mut data)?;
         vertex::create_index_buffer(&instance, &device, &mut data)?;
+        vertex::create_point_index_buffer(&instance, &device, &mut data)?;
+        bounds::create_bounds_buffer(&instance, &device, &mut data)?;
+        edges::create_edges_buffer(&instance, &device, &mut data)?;
+        gizmo::create_gizmo_buffer(&instance, &device, &mut data)?;
         descriptor::create_uniform_buffers(&instance, &device, &mut data)?;
+        buffers::create_indirect_buffers(&instance, &device, &mut data)?;
+        timing::create_query_pool(&device, &mut data)?;
         descriptor::create_descriptor_pool(&device, &mut data)?;
         descriptor::create_descriptor_sets(&device, &mut data)?;
         buffers::create_command_buffers(&device, &mut data)?;
         buffers::create_sync_objects(&device, &mut data)?;
-        Ok(Self {
+        let mut app = Self {
             entry,
             instance,
             data,
             device,
             frame: 0,
             resized: false,
+            obj_path,
+            texture_path,
             start: Instant::now(),
-            controls: Controls {
-                zoom: 1.0,
-                rotation: vec2(0.0, 45.0),
-                auto_rotate: false,
-                ..Default::default()
-            },
-        })
+            destroyed: false,
+            controls,
+        };
+        app.fit_camera();
+        Ok(app)
+    }
+
+    /// Centroid of the model vertices, the point the camera orbits around.
+    fn model_center(&self) -> Vec3 {
+        let mut sum = Vec3::default();
+        for vertex in &self.data.vertices {
+            sum += vertex.pos;
+        }
+        sum /= self.data.vertices.len() as f32;
+        sum
+    }
+
+    /// Moves the camera so the whole model fits in the window, unless the
+    /// user zoomed.
+    fn fit_camera(&mut self) {
+        if self.controls.zoomed {
+            return;
+        }
+        let center = self.model_center();
+        let radius = self
+            .data
+            .vertices
+            .iter()
+            .map(|vertex| (vertex.pos - center).magnitude())
+            .fold(0.0, f32::max);
+        if radius > 0.0 {
+            let aspect = swapchain::aspect_ratio(self.data.swapchain_extent);
+            self.controls.fit_distance = Camera::fit_distance(radius, aspect);
+        }
     }
 
     /// Renders a frame for our Vulkan app.
@@ -258,38 +531,33 @@ impl App {
             vk::Fence::null(),
         );
 
+        // A suboptimal acquire still renders, presenting then triggers the recreation.
         let image_index = match result {
             Ok((image_index, _)) => image_index as usize,
-            Err(vk::ErrorCode::OUT_OF_DATE_KHR) => return self.recreate_swapchain(window),
-            Err(error) => return Err(anyhow!("Failed to acquire next image: {}", error)),
+            Err(error) => {
+                return match SwapchainStatus::from_result(Err(error)) {
+                    SwapchainStatus::Fatal(error) => {
+                        Err(anyhow!("Failed to acquire next image: {}", error))
+                    }
+                    SwapchainStatus::RecreateSurface => self.recreate_surface(window),
+                    _ => self.recreate_swapchain(window),
+                }
+            }
         };
 
-        if !self.data.images_in_flight[image_index as usize].is_null() {
-            self.device.wait_for_fences(
-                &[self.data.images_in_flight[image_index as usize]],
-                true,
-                u64::MAX,
-            )?;
-        }
-
-        self.data.images_in_flight[image_index as usize] = in_flight_fence;
-
-        self.update_uniform_buffer(image_index)?;
-
         let wait_semaphores = [self.data.image_available_semaphores[self.frame]];
-        let wait_stages = [vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT];
-        let command_buffers = [self.data.command_buffers[image_index]];
         let signal_semaphores = [self.data.render_finished_semaphores[self.frame]];
-        let submit_info = vk::SubmitInfo::builder()
-            .wait_semaphores(&wait_semaphores)
-            .wait_dst_stage_mask(&wait_stages)
-            .command_buffers(&command_buffers)
-            .signal_semaphores(&signal_semaphores);
-
-        self.device.reset_fences(&[in_flight_fence])?;
-
-        self.device
-            .queue_submit(self.data.graphics_queue, &[submit_info], in_flight_fence)?;
+        let readback = self.submit_frame(image_index, &wait_semaphores, &signal_semaphores)?;
+        if let Some(readback) = readback {
+            let (x, y) = (readback.x, readback.y);
+            match self.finish_pixel_readback(readback, in_flight_fence) {
+                Ok([r, g, b, a]) => info!(
+                    "Color at {}, {}: #{:02x}{:02x}{:02x}{:02x}",
+                    x, y, r, g, b, a
+                ),
+                Err(error) => warn!("Failed to read pixel: {}", error),
+            }
+        }
 
         let swapchains = [self.data.swapchain];
         let image_indices = [image_index as u32];
@@ -301,14 +569,16 @@ impl App {
         let result = self
             .device
             .queue_present_khr(self.data.present_queue, &present_info);
-        let changed = result == Ok(vk::SuccessCode::SUBOPTIMAL_KHR)
-            || result == Err(vk::ErrorCode::OUT_OF_DATE_KHR);
+        let status = SwapchainStatus::from_result(result);
 
-        if self.resized || changed {
+        if let SwapchainStatus::Fatal(error) = status {
+            return Err(anyhow!("Failed to present queue: {}", error));
+        } else if status == SwapchainStatus::RecreateSurface {
+            self.resized = false;
+            self.recreate_surface(window)?;
+        } else if self.resized || status == SwapchainStatus::Recreate {
             self.resized = false;
             self.recreate_swapchain(window)?;
-        } else if let Err(e) = result {
-            return Err(anyhow!("Failed to present queue: {}", e));
         }
 
         self.frame = (self.frame + 1) % MAX_FRAMES_IN_FLIGHT;
@@ -316,10 +586,67 @@ impl App {
         Ok(())
     }
 
-    /// Destroys our Vulkan app.
+    /// Updates the uniforms of the acquired image `image_index` and submits
+    /// its command buffer, the per-frame work between acquiring and
+    /// presenting. Returns the copy of the requested pixel submitted along,
+    /// if any.
+    unsafe fn submit_frame(
+        &mut self,
+        image_index: usize,
+        wait_semaphores: &[vk::Semaphore],
+        signal_semaphores: &[vk::Semaphore],
+    ) -> Result<Option<PixelReadback>> {
+        let in_flight_fence = self.data.in_flight_fences[self.frame];
+
+        if !self.data.images_in_flight[image_index].is_null() {
+            self.device.wait_for_fences(
+                &[self.data.images_in_flight[image_index]],
+                true,
+                u64::MAX,
+            )?;
+        }
+
+        self.data.images_in_flight[image_index] = in_flight_fence;
+
+        timing::read_timestamps(&self.device, &mut self.data, image_index)?;
+
+        self.update_uniform_buffer(image_index)?;
+
+        let wait_stages = vec![vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT; wait_semaphores.len()];
+        // A requested pixel is copied once the frame is rendered, before the
+        // image is presented. Failing to copy it only drops the request.
+        let readback = self
+            .record_pixel_readback(image_index)
+            .unwrap_or_else(|error| {
+                warn!("Failed to read pixel: {}", error);
+                None
+            });
+        let mut command_buffers = vec![self.data.command_buffers[image_index]];
+        command_buffers.extend(readback.as_ref().map(|readback| readback.command_buffer));
+        let submit_info = vk::SubmitInfo::builder()
+            .wait_semaphores(wait_semaphores)
+            .wait_dst_stage_mask(&wait_stages)
+            .command_buffers(&command_buffers)
+            .signal_semaphores(signal_semaphores);
+
+        self.device.reset_fences(&[in_flight_fence])?;
+
+        self.device
+            .queue_submit(self.data.graphics_queue, &[submit_info], in_flight_fence)?;
+
+        Ok(readback)
+    }
+
+    /// Destroys our Vulkan app. Does nothing if it already was.
     #[rustfmt::skip]
     unsafe fn destroy(&mut self) {
-        self.device.device_wait_idle().unwrap();
+        if !first_destroy(&mut self.destroyed) {
+            return;
+        }
+
+        if let Err(error) = self.device.device_wait_idle() {
+            error!("Failed to wait for the device: {}", error);
+        }
 
         self.destroy_swapchain();
 
@@ -327,20 +654,18 @@ impl App {
         self.data.render_finished_semaphores.iter().for_each(|s| self.device.destroy_semaphore(*s, None));
         self.data.image_available_semaphores.iter().for_each(|s| self.device.destroy_semaphore(*s, None));
 
-        self.device.destroy_sampler(self.data.texture_sampler, None);
-        self.device.destroy_image_view(self.data.texture_image_view, None);
-        self.device.destroy_image(self.data.texture_image, None);
-        self.device.free_memory(self.data.texture_image_memory, None);
-        self.device.destroy_buffer(self.data.vertex_buffer, None);
-        self.device.free_memory(self.data.vertex_buffer_memory, None);
-        self.device.destroy_buffer(self.data.index_buffer, None);
-        self.device.free_memory(self.data.index_buffer_memory, None);
+        self.destroy_model(&self.data);
+        self.device.destroy_buffer(self.data.gizmo_vertex_buffer, None);
+        self.device.free_memory(self.data.gizmo_vertex_buffer_memory, None);
         self.device.destroy_command_pool(self.data.command_pool, None);
+        self.device.destroy_command_pool(self.data.transfer_command_pool, None);
         self.device.destroy_descriptor_set_layout(self.data.descriptor_set_layout, None);
         self.device.destroy_device(None);
-        self.instance.destroy_surface_khr(self.data.surface, None);
+        if !self.data.surface.is_null() {
+            self.instance.destroy_surface_khr(self.data.surface, None);
+        }
 
-        if VALIDATION_ENABLED {
+        if self.data.validation {
             self.instance
                 .destroy_debug_utils_messenger_ext(self.data.messenger, None);
         }
@@ -348,95 +673,257 @@ impl App {
         self.instance.destroy_instance(None);
     }
 
-    unsafe fn update_uniform_buffer(&mut self, image_index: usize) -> Result<()> {
-        let time = self.start.elapsed().as_secs_f32();
+    /// Destroys the texture and geometry buffers of the model loaded in
+    /// `data`.
+    #[rustfmt::skip]
+    unsafe fn destroy_model(&self, data: &AppData) {
+        self.device.destroy_sampler(data.texture_sampler, None);
+        for texture in &data.textures {
+            self.device.destroy_image_view(texture.view, None);
+            self.device.destroy_image(texture.image, None);
+            self.device.free_memory(texture.memory, None);
+        }
+        self.device.destroy_buffer(data.vertex_buffer, None);
+        self.device.free_memory(data.vertex_buffer_memory, None);
+        self.device.destroy_buffer(data.lightmap_buffer, None);
+        self.device.free_memory(data.lightmap_buffer_memory, None);
+        self.device.destroy_buffer(data.index_buffer, None);
+        self.device.free_memory(data.index_buffer_memory, None);
+        self.device.destroy_buffer(data.point_index_buffer, None);
+        self.device.free_memory(data.point_index_buffer_memory, None);
+        self.device.destroy_buffer(data.bounds_vertex_buffer, None);
+        self.device.free_memory(data.bounds_vertex_buffer_memory, None);
+        self.device.destroy_buffer(data.edges_vertex_buffer, None);
+        self.device.free_memory(data.edges_vertex_buffer_memory, None);
+    }
 
-        let num_vertices = self.data.vertices.len() as u32;
-        let mut sum = Vec3::default();
-        for vertex in &self.data.vertices {
-            sum += vertex.pos;
+    /// Replaces the loaded model and texture at runtime.
+    ///
+    /// The new model and its resources are created next to the current ones,
+    /// which are only destroyed once everything succeeded. A failure leaves
+    /// the current model untouched.
+    unsafe fn load_new_model(
+        &mut self,
+        obj_path: String,
+        texture_path: Option<String>,
+    ) -> Result<()> {
+        let previous = self.data.clone();
+        model::reload_model(&mut self.data, obj_path.clone(), texture_path.is_some())?;
+        self.data.active_object = None;
+
+        // The texture array of the layout is sized by the new model.
+        if let Err(error) = descriptor::create_descriptor_set_layout(&self.device, &mut self.data) {
+            self.data = previous;
+            return Err(error);
+        }
+        if let Err(error) = self.create_model_resources(texture_path.as_deref()) {
+            // Only the resources created for the new model are released.
+            self.destroy_model(&self.data);
+            self.device
+                .destroy_descriptor_set_layout(self.data.descriptor_set_layout, None);
+            self.device
+                .destroy_descriptor_pool(self.data.descriptor_pool, None);
+            buffers::destroy_indirect_buffers(&self.device, &self.data);
+            self.data = previous;
+            return Err(error);
         }
-        sum /= num_vertices as f32;
-
-        let model = Mat4::from_translation(-self.controls.object_pos)
-            * Mat4::from_axis_angle(
-                vec3(0.0, 1.0, 0.0),
-                if self.controls.auto_rotate { time } else { 1.0 },
-            )
-            * Mat4::from_translation(-sum);
-
-        let theta_x = self.controls.rotation.x * (std::f32::consts::PI / 180.0);
-        let theta_y = self.controls.rotation.y * (std::f32::consts::PI / 180.0);
-        let radius: f32 = 20.0 * self.controls.zoom;
-
-        let camera: Vec3 = vec3(
-            radius * theta_x.cos() * theta_y.sin() + 0.1,
-            radius * theta_y.cos() + 0.1,
-            radius * theta_x.sin() * theta_y.sin() + 0.1,
-        );
 
-        let view = Mat4::look_at_rh(camera, sum, vec3(0.0, 1.0, 0.0));
+        self.device.device_wait_idle()?;
 
-        #[rustfmt::skip]
-        let correction = Mat4::new(
-            1.0, 0.0,       0.0, 0.0,
-            0.0, 1.0,       0.0, 0.0,
-            0.0, 0.0, 1.0 / 2.0, 0.0,
-            0.0, 0.0, 1.0 / 2.0, 1.0,
-        );
+        self.destroy_model(&previous);
+        self.device
+            .destroy_descriptor_pool(previous.descriptor_pool, None);
+        buffers::destroy_indirect_buffers(&self.device, &previous);
 
-        let proj = correction
-            * perspective(
-                Deg(45.0),
-                self.data.swapchain_extent.width as f32 / self.data.swapchain_extent.height as f32,
-                0.1,
-                100.0,
-            );
+        // The pipeline depends on whether the model has texture coordinates
+        // and on its descriptor set layout, rebuilding it records the command
+        // buffers again too.
+        self.recreate_pipeline()?;
+        self.device
+            .destroy_descriptor_set_layout(previous.descriptor_set_layout, None);
+        self.fit_camera();
+
+        info!("Loaded model {} with texture {:?}", obj_path, texture_path);
+        self.obj_path = obj_path;
+        self.texture_path = texture_path;
+
+        Ok(())
+    }
 
-        let ubo = UniformBufferObject { model, view, proj };
+    /// Creates the textures, geometry buffers, descriptor sets and indirect
+    /// buffers of the model parsed into `data`, in place of the handles of
+    /// the current model.
+    unsafe fn create_model_resources(&mut self, texture_path: Option<&str>) -> Result<()> {
+        let data = &mut self.data;
+        data.texture_sampler = vk::Sampler::null();
+        data.textures.clear();
+        data.vertex_buffer = vk::Buffer::null();
+        data.vertex_buffer_memory = vk::DeviceMemory::null();
+        data.lightmap_buffer = vk::Buffer::null();
+        data.lightmap_buffer_memory = vk::DeviceMemory::null();
+        data.index_buffer = vk::Buffer::null();
+        data.index_buffer_memory = vk::DeviceMemory::null();
+        data.point_index_buffer = vk::Buffer::null();
+        data.point_index_buffer_memory = vk::DeviceMemory::null();
+        data.bounds_vertex_buffer = vk::Buffer::null();
+        data.bounds_vertex_buffer_memory = vk::DeviceMemory::null();
+        data.edges_vertex_buffer = vk::Buffer::null();
+        data.edges_vertex_buffer_memory = vk::DeviceMemory::null();
+        data.descriptor_pool = vk::DescriptorPool::null();
+        data.indirect_buffers.clear();
+        data.indirect_buffers_memory.clear();
+
+        textures::create_texture_images(&self.instance, &self.device, data, texture_path)?;
+        textures::create_texture_sampler(&self.device, data)?;
+        vertex::create_vertex_buffer(&self.instance, &self.device, data)?;
+        vertex::create_lightmap_buffer(&self.instance, &self.device, data)?;
+        vertex::create_index_buffer(&self.instance, &self.device, data)?;
+        vertex::create_point_index_buffer(&self.instance, &self.device, data)?;
+        bounds::create_bounds_buffer(&self.instance, &self.device, data)?;
+        edges::create_edges_buffer(&self.instance, &self.device, data)?;
+
+        // Descriptor sets reference the texture and command buffers the geometry buffers.
+        descriptor::create_descriptor_pool(&self.device, data)?;
+        descriptor::create_descriptor_sets(&self.device, data)?;
+        // One indirect draw command per object.
+        buffers::create_indirect_buffers(&self.instance, &self.device, data)
+    }
 
-        let memory = self.device.map_memory(
-            self.data.uniform_buffers_memory[image_index],
-            0,
-            size_of::<UniformBufferObject>() as u64,
-            vk::MemoryMapFlags::empty(),
-        )?;
+    /// Recreates the texture sampler after its settings changed, and points
+    /// the descriptor sets at it. The previous sampler is kept if the new one
+    /// fails to be created.
+    unsafe fn recreate_texture_sampler(&mut self) -> Result<()> {
+        self.device.device_wait_idle()?;
 
-        memcpy(&ubo, memory.cast(), 1);
+        let previous = self.data.texture_sampler;
+        textures::create_texture_sampler(&self.device, &mut self.data)?;
+        descriptor::write_descriptor_sets(&self.device, &self.data);
+        self.device.destroy_sampler(previous, None);
 
-        self.device
-            .unmap_memory(self.data.uniform_buffers_memory[image_index]);
+        // Updating the descriptor sets invalidated the command buffers.
+        buffers::record_command_buffers(&self.device, &self.data)
+    }
+
+    unsafe fn update_uniform_buffer(&mut self, image_index: usize) -> Result<()> {
+        let time = self.start.elapsed().as_secs_f32();
+
+        let sum = self.model_center();
+
+        let rotation = Mat4::from_translation(-self.controls.object_pos)
+            * self.controls.auto_rotation(time);
+        let up = self.controls.up_correction();
+        let model = rotation * up * Mat4::from_translation(-sum);
+        let outline_model =
+            rotation * Mat4::from_scale(OUTLINE_SCALE) * up * Mat4::from_translation(-sum);
+
+        let camera = Camera::orbit(&self.controls, sum);
+        let aspect = swapchain::aspect_ratio(self.data.swapchain_extent);
+        let (view, proj) = camera.view_proj(aspect, self.data.reverse_z);
+
+        let ubo = UniformBufferObject {
+            model,
+            view,
+            proj,
+            outline_model,
+            camera_pos: vec4(camera.position.x, camera.position.y, camera.position.z, 1.0),
+            gizmo: gizmo::gizmo_matrix(view),
+            exposure: self.controls.exposure,
+            gamma: self.controls.gamma,
+        };
+
+        memcpy(&ubo, self.data.uniform_buffers_mapped[image_index].cast(), 1);
+
+        // Skip the objects whose bounding sphere is out of view.
+        let frustum = Frustum::from_matrix(proj * view);
+        let commands = self
+            .data
+            .objects
+            .iter()
+            .map(|object| {
+                let c = object.center;
+                let center = model * vec4(c.x, c.y, c.z, 1.0);
+                let center = vec3(center.x, center.y, center.z);
+                let visible = frustum.intersects_sphere(center, object.radius);
+                vk::DrawIndexedIndirectCommand {
+                    index_count: object.index_count,
+                    instance_count: visible as u32,
+                    first_index: object.first_index,
+                    vertex_offset: 0,
+                    first_instance: 0,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        if !commands.is_empty() {
+            let memory = self.device.map_memory(
+                self.data.indirect_buffers_memory[image_index],
+                0,
+                (size_of::<vk::DrawIndexedIndirectCommand>() * commands.len()) as u64,
+                vk::MemoryMapFlags::empty(),
+            )?;
+
+            memcpy(commands.as_ptr(), memory.cast(), commands.len());
+
+            self.device
+                .unmap_memory(self.data.indirect_buffers_memory[image_index]);
+        }
 
         Ok(())
     }
 }
 
+impl Drop for App {
+    fn drop(&mut self) {
+        unsafe { self.destroy() }
+    }
+}
+
 /// The Vulkan handles and associated properties used by our Vulkan app.
 #[derive(Clone, Debug, Default)]
 pub struct AppData {
     // Debug
     messenger: vk::DebugUtilsMessengerEXT,
+    validation: bool,
     // Surface
     surface: vk::SurfaceKHR,
     // Physical Device / Logical Device
     physical_device: vk::PhysicalDevice,
     graphics_queue: vk::Queue,
     present_queue: vk::Queue,
+    graphics_family: u32,
+    /// Family of `transfer_queue` when it is dedicated to transfers, which
+    /// then go through `transfer_command_pool`.
+    transfer_family: Option<u32>,
+    transfer_queue: vk::Queue,
     // Swapchain
     swapchain_format: vk::Format,
     swapchain_extent: vk::Extent2D,
     swapchain: vk::SwapchainKHR,
+    swapchain_image_count: Option<u32>,
     swapchain_images: Vec<vk::Image>,
+    swapchain_usage: vk::ImageUsageFlags,
+    /// Memory of the images standing for the swapchain ones when rendering
+    /// offscreen, empty otherwise.
+    offscreen_images_memory: Vec<vk::DeviceMemory>,
+    /// Pixel whose color is logged once the next frame is rendered, set by
+    /// `request_pixel`.
+    pixel_request: Option<(u32, u32)>,
     swapchain_images_views: Vec<vk::ImageView>,
     // Pipeline
     render_pass: vk::RenderPass,
     descriptor_set_layout: vk::DescriptorSetLayout,
     pipeline_layout: vk::PipelineLayout,
     pipeline: vk::Pipeline,
+    point_pipeline: vk::Pipeline,
+    outline_pipeline: vk::Pipeline,
+    gizmo_pipeline: vk::Pipeline,
+    bounds_pipeline: vk::Pipeline,
+    post_pipeline: vk::Pipeline,
     // Framebuffers
     framebuffers: Vec<vk::Framebuffer>,
     // Command Pool
     command_pool: vk::CommandPool,
+    transfer_command_pool: vk::CommandPool,
     // Command Buffers
     command_buffers: Vec<vk::CommandBuffer>,
     // Semaphores for each frame in flight.
@@ -444,48 +931,120 @@ pub struct AppData {
     render_finished_semaphores: Vec<vk::Semaphore>,
     in_flight_fences: Vec<vk::Fence>,
     images_in_flight: Vec<vk::Fence>,
+    // GPU timings
+    gpu_timings_enabled: bool,
+    timestamp_period: f32,
+    timestamp_valid_bits: u32,
+    query_pool: vk::QueryPool,
+    gpu_timings: timing::GpuTimings,
     // Vertex Buffer
+    dedup_tolerance: f32,
+    max_triangles: Option<usize>,
+    decimate: bool,
+    drop_degenerate: bool,
+    keep_normals: bool,
+    hard_edge_angle: Option<f32>,
+    decimal_comma: bool,
+    winding: obj::Winding,
+    /// Uploads buffers through a staging buffer even on unified memory.
+    force_staging: bool,
     vertices: Vec<Vertex>,
     indices: Vec<u32>,
+    index_type: vk::IndexType,
+    point_indices: Vec<u32>,
+    objects: Vec<model::Object>,
+    has_tex_coords: bool,
+    /// Whether the model has lightmap texture coordinates, given to the
+    /// shaders from `lightmap_buffer`.
+    has_lightmap_tex_coords: bool,
+    /// Lightmap texture coordinates of each of `vertices`, when the model
+    /// has some.
+    lightmap_tex_coords: Vec<Vec2>,
+    lightmap_buffer: vk::Buffer,
+    lightmap_buffer_memory: vk::DeviceMemory,
     vertex_buffer: vk::Buffer,
     vertex_buffer_memory: vk::DeviceMemory,
     index_buffer: vk::Buffer,
     index_buffer_memory: vk::DeviceMemory,
+    point_index_buffer: vk::Buffer,
+    point_index_buffer_memory: vk::DeviceMemory,
+    gizmo_vertex_buffer: vk::Buffer,
+    gizmo_vertex_buffer_memory: vk::DeviceMemory,
+    bounds_vertex_buffer: vk::Buffer,
+    bounds_vertex_buffer_memory: vk::DeviceMemory,
+    edges_vertex_buffer: vk::Buffer,
+    edges_vertex_buffer_memory: vk::DeviceMemory,
+    edge_vertex_count: u32,
     uniform_buffers: Vec<vk::Buffer>,
     uniform_buffers_memory: Vec<vk::DeviceMemory>,
+    uniform_buffers_mapped: Vec<*mut c_void>,
+    indirect_buffers: Vec<vk::Buffer>,
+    indirect_buffers_memory: Vec<vk::DeviceMemory>,
     // Descriptor
     descriptor_pool: vk::DescriptorPool,
     descriptor_sets: Vec<vk::DescriptorSet>,
     // Textures
-    mip_levels: u32,
-    texture_image: vk::Image,
-    texture_image_memory: vk::DeviceMemory,
-    texture_image_view: vk::ImageView,
+    generated_texture: textures::GeneratedTexture,
+    texture_paths: Vec<String>,
+    textures: Vec<textures::Texture>,
     texture_sampler: vk::Sampler,
+    /// Added to the mip level of the textures, sharper below 0.
+    mip_lod_bias: f32,
+    /// How the textures are sampled outside of [0, 1], on every axis.
+    address_mode: vk::SamplerAddressMode,
+    max_sampler_lod_bias: f32,
     // Depth image
+    depth_format: vk::Format,
     depth_image: vk::Image,
     depth_image_memory: vk::DeviceMemory,
     depth_image_view: vk::ImageView,
+    depth_sample_view: vk::ImageView,
+    /// Near points have a depth of 1 and far ones a depth of 0.
+    reverse_z: bool,
     // Rasterization parameters
     wireframe: bool,
+    wireframe_color: usize,
     color_mod: bool,
+    /// Index in `SOLID_COLORS` of the color the whole model is drawn in.
+    solid_color: Option<usize>,
+    normal_color: bool,
+    point_size: f32,
+    max_point_size: f32,
+    line_width: f32,
+    line_width_range: [f32; 2],
+    outline: bool,
+    depth_view: bool,
+    show_bounds: bool,
+    /// Draw the crease and boundary edges over the model.
+    show_edges: bool,
+    /// Index in `objects` of the object highlighted, the others are dimmed.
+    /// Kept here rather than in `Controls` since the dimming is recorded in
+    /// the command buffers, like the outline and the edges.
+    active_object: Option<usize>,
 }
 
-/// Creates a Vulkan instance.
-unsafe fn create_instance(window: &Window, entry: &Entry, data: &mut AppData) -> Result<Instance> {
+/// Creates a Vulkan instance, with the extensions presenting to `window` if
+/// any.
+unsafe fn create_instance(
+    window: Option<&Window>,
+    entry: &Entry,
+    data: &mut AppData,
+) -> Result<Instance> {
     let app_info = vk::ApplicationInfo::builder()
         .application_name(b"scop\0")
         .application_version(vk::make_version(1, 0, 0))
         .engine_name(b"No Engine\0")
         .engine_version(vk::make_version(1, 0, 0))
-        .api_version(vk::make_version(1, 0, 0));
+        .api_version(MIN_API_VERSION.into());
 
-    let mut extensions = vk_window::get_required_instance_extensions(window)
+    let mut extensions = window
+        .map(|window| vk_window::get_required_instance_extensions(window))
+        .unwrap_or_default()
         .iter()
         .map(|ext| ext.as_ptr())
         .collect::<Vec<_>>();
 
-    if VALIDATION_ENABLED {
+    if data.validation {
         extensions.push(vk::EXT_DEBUG_UTILS_EXTENSION.name.as_ptr());
     }
 
@@ -508,11 +1067,11 @@ unsafe fn create_instance(window: &Window, entry: &Entry, data: &mut AppData) ->
         .map(|layer| layer.layer_name)
         .collect::<HashSet<_>>();
 
-    if VALIDATION_ENABLED && !available_layers.contains(&VALIDATION_LAYER) {
+    if data.validation && !available_layers.contains(&VALIDATION_LAYER) {
         return Err(anyhow!("Validation layer requested but not supported."));
     }
 
-    let enabled_layer_names = if VALIDATION_ENABLED {
+    let enabled_layer_names = if data.validation {
         vec![VALIDATION_LAYER.as_ptr()]
     } else {
         Vec::new()
@@ -525,7 +1084,7 @@ unsafe fn create_instance(window: &Window, entry: &Entry, data: &mut AppData) ->
         .enabled_layer_names(&enabled_layer_names);
 
     let mut debug_info = vk::DebugUtilsMessengerCreateInfoEXT::builder()
-        .message_severity(vk::DebugUtilsMessageSeverityFlagsEXT::all())
+        .message_severity(message_severity_from_env())
         .message_type(
             vk::DebugUtilsMessageTypeFlagsEXT::GENERAL
                 | vk::DebugUtilsMessageTypeFlagsEXT::VALIDATION
@@ -533,19 +1092,73 @@ unsafe fn create_instance(window: &Window, entry: &Entry, data: &mut AppData) ->
         ) // Can't use all() because it might include additional extensions (EXT_DEVICE_ADDRESS_BINDING_REPORT_EXTENSION)
         .user_callback(Some(debug_callback));
 
-    if VALIDATION_ENABLED {
+    if data.validation {
         instance_info = instance_info.push_next(&mut debug_info);
     }
 
     let instance = entry.create_instance(&instance_info, None)?;
 
-    if VALIDATION_ENABLED {
+    if data.validation {
         data.messenger = instance.create_debug_utils_messenger_ext(&debug_info, None)?;
     }
 
     Ok(instance)
 }
 
+/// Marks a resource as destroyed, returning whether this is the first time.
+fn first_destroy(destroyed: &mut bool) -> bool {
+    !std::mem::replace(destroyed, true)
+}
+
+/// Builds the severity flags of the messages at or above `level`, which is one
+/// of `error`, `warning`, `info` or `verbose`.
+fn parse_message_severity(level: &str) -> Option<vk::DebugUtilsMessageSeverityFlagsEXT> {
+    type Severity = vk::DebugUtilsMessageSeverityFlagsEXT;
+
+    match level.trim().to_lowercase().as_str() {
+        "error" => Some(Severity::ERROR),
+        "warning" => Some(Severity::ERROR | Severity::WARNING),
+        "info" => Some(Severity::ERROR | Severity::WARNING | Severity::INFO),
+        "verbose" => Some(Severity::all()),
+        _ => None,
+    }
+}
+
+/// The files the loaded model is read from.
+#[cfg(feature = "hot-reload")]
+fn model_paths(app: &App) -> Vec<&str> {
+    let mut paths = vec![app.obj_path.as_str()];
+    paths.extend(app.texture_path.as_deref());
+    paths
+}
+
+/// Whether validation is on, given whether the build enables it. Setting
+/// `SCOP_NO_VALIDATION` turns it off at runtime.
+fn validation_from_env(build_enabled: bool) -> bool {
+    validation_enabled(
+        build_enabled,
+        std::env::var("SCOP_NO_VALIDATION").ok().as_deref(),
+    )
+}
+
+/// Whether validation is on, given whether the build enables it and the
+/// value of `SCOP_NO_VALIDATION`, if set.
+fn validation_enabled(build_enabled: bool, no_validation: Option<&str>) -> bool {
+    build_enabled && !matches!(no_validation, Some("1") | Some("true"))
+}
+
+/// Reads the minimum validation message severity from `SCOP_VK_SEVERITY`,
+/// reporting every message when unset.
+fn message_severity_from_env() -> vk::DebugUtilsMessageSeverityFlagsEXT {
+    match std::env::var("SCOP_VK_SEVERITY") {
+        Ok(level) => parse_message_severity(&level).unwrap_or_else(|| {
+            warn!("Invalid SCOP_VK_SEVERITY: {}", level);
+            vk::DebugUtilsMessageSeverityFlagsEXT::all()
+        }),
+        Err(_) => vk::DebugUtilsMessageSeverityFlagsEXT::all(),
+    }
+}
+
 extern "system" fn debug_callback(
     severity: vk::DebugUtilsMessageSeverityFlagsEXT,
     type_: vk::DebugUtilsMessageTypeFlagsEXT,
@@ -567,3 +1180,125 @@ extern "system" fn debug_callback(
 
     vk::FALSE
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn message_severity_levels() {
+        type Severity = vk::DebugUtilsMessageSeverityFlagsEXT;
+
+        assert_eq!(parse_message_severity("error"), Some(Severity::ERROR));
+        assert_eq!(
+            parse_message_severity("Warning"),
+            Some(Severity::ERROR | Severity::WARNING)
+        );
+        assert_eq!(
+            parse_message_severity(" info "),
+            Some(Severity::ERROR | Severity::WARNING | Severity::INFO)
+        );
+        assert_eq!(parse_message_severity("verbose"), Some(Severity::all()));
+        assert_eq!(parse_message_severity("loud"), None);
+    }
+
+    #[test]
+    fn drag_rotation_follows_the_inversions() {
+        for (invert_x, invert_y, expected) in [
+            (false, false, (1.0, -2.0)),
+            (true, false, (-1.0, -2.0)),
+            (false, true, (1.0, 2.0)),
+            (true, true, (-1.0, 2.0)),
+        ] {
+            let mut controls = Controls {
+                invert_x,
+                invert_y,
+                ..Default::default()
+            };
+            controls.rotate(10.0, 20.0);
+            assert_eq!((controls.rotation.x, controls.rotation.y), expected);
+        }
+    }
+
+    #[test]
+    fn z_up_models_are_stood_upright() {
+        let mut controls = Controls {
+            z_up: true,
+            ..Default::default()
+        };
+        let up = (controls.up_correction() * vec4(0.0, 0.0, 1.0, 0.0)).truncate();
+        assert!((up - vec3(0.0, 1.0, 0.0)).magnitude() < 1e-6, "{:?}", up);
+
+        controls.z_up = false;
+        let up = (controls.up_correction() * vec4(0.0, 1.0, 0.0, 0.0)).truncate();
+        assert_eq!(up, vec3(0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn resources_are_destroyed_once() {
+        let mut destroyed = false;
+        assert!(first_destroy(&mut destroyed));
+        assert!(!first_destroy(&mut destroyed));
+        assert!(destroyed);
+    }
+
+    #[test]
+    fn validation_can_be_turned_off_at_runtime() {
+        assert!(validation_enabled(true, None));
+        assert!(!validation_enabled(false, None));
+        for (value, expected) in [("1", false), ("true", false), ("0", true)] {
+            assert_eq!(validation_enabled(true, Some(value)), expected, "{}", value);
+            assert!(!validation_enabled(false, Some(value)));
+        }
+    }
+
+    #[test]
+    fn auto_rotation_spins_around_the_normalized_axis() {
+        let controls = Controls {
+            auto_rotate: true,
+            auto_rotate_axis: vec3(0.0, 0.0, 2.0),
+            auto_rotate_speed: 0.5,
+            ..Default::default()
+        };
+        let expected = Mat4::from_axis_angle(vec3(0.0, 0.0, 1.0), 1.5);
+        assert_eq!(controls.auto_rotation(3.0), expected);
+    }
+
+    #[test]
+    fn colors_are_left_as_shaded_by_default() {
+        let controls = Controls::new(1.0, Vec2::default(), false);
+        assert_eq!(controls.exposure, 1.0);
+        // The sRGB swapchain already encodes the colors.
+        assert_eq!(controls.gamma, 1.0);
+    }
+
+    #[test]
+    fn config_reaches_the_app() {
+        let config = AppConfig {
+            max_triangles: Some(100),
+            decimate: true,
+            winding: obj::Winding::Auto,
+            swapchain_image_count: Some(3),
+            reverse_z: true,
+            ..AppConfig::default()
+        }
+        .validation(false)
+        .z_up(true)
+        .rotation(crate::math::vec2(10.0, 20.0))
+        .zoom(2.0);
+
+        let data = initial_data(&config);
+        assert!(!data.validation);
+        assert_eq!(data.max_triangles, Some(100));
+        assert!(data.decimate);
+        assert_eq!(data.winding, obj::Winding::Auto);
+        assert_eq!(data.swapchain_image_count, Some(3));
+        assert!(data.reverse_z);
+        assert!(initial_data(&config.clone().validation(true)).validation);
+
+        let controls = initial_controls(&config);
+        assert!(controls.z_up);
+        assert_eq!(controls.rotation, crate::math::vec2(10.0, 20.0));
+        assert_eq!(controls.zoom, 2.0);
+    }
+}
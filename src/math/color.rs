@@ -0,0 +1,29 @@
+/// Converts an sRGB encoded color component, in [0, 1], to linear.
+pub fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn srgb_to_linear_known_values() {
+        assert_eq!(srgb_to_linear(0.0), 0.0);
+        assert!((srgb_to_linear(1.0) - 1.0).abs() < 1e-6);
+        assert!((srgb_to_linear(0.5) - 0.2140).abs() < 1e-4);
+        // A byte of 200 is a linear 0.58.
+        assert!((srgb_to_linear(200.0 / 255.0) - 0.5776).abs() < 1e-4);
+    }
+
+    #[test]
+    fn srgb_to_linear_is_continuous() {
+        let below = srgb_to_linear(0.04045);
+        let above = srgb_to_linear(0.04045 + 1e-6);
+        assert!(above >= below && above - below < 1e-5);
+    }
+}
@@ -0,0 +1,69 @@
+use super::{Matrix4, Vector3, Vector4};
+
+/// The six planes bounding the volume seen through a view-projection matrix,
+/// normalized and with their normals pointing inside.
+#[derive(Copy, Clone, Debug)]
+pub struct Frustum {
+    planes: [Vector4; 6],
+}
+
+impl Frustum {
+    /// Extracts the planes of `view_proj`, for Vulkan's [0, 1] depth range.
+    pub fn from_matrix(view_proj: Matrix4) -> Frustum {
+        let row = |i: usize| {
+            Vector4::new(
+                view_proj[0][i],
+                view_proj[1][i],
+                view_proj[2][i],
+                view_proj[3][i],
+            )
+        };
+        let (r0, r1, r2, r3) = (row(0), row(1), row(2), row(3));
+
+        // Left, right, bottom, top, near and far.
+        let planes = [r3 + r0, r3 - r0, r3 + r1, r3 - r1, r2, r3 - r2]
+            .map(|plane| plane * (1.0 / plane.truncate().magnitude()));
+
+        Frustum { planes }
+    }
+
+    /// Whether a sphere is at least partly inside the frustum.
+    pub fn intersects_sphere(&self, center: Vector3, radius: f32) -> bool {
+        self.planes
+            .iter()
+            .all(|plane| plane.truncate().dot(center) + plane.w >= -radius)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::{vec3, vulkan_perspective, Deg};
+
+    /// Camera at the origin looking down -Z.
+    fn frustum() -> Frustum {
+        let view = Matrix4::look_at_rh(
+            vec3(0.0, 0.0, 0.0),
+            vec3(0.0, 0.0, -1.0),
+            vec3(0.0, 1.0, 0.0),
+        );
+        let proj = vulkan_perspective(Deg(90.0), 1.0, 0.1, 100.0);
+        Frustum::from_matrix(proj * view)
+    }
+
+    #[test]
+    fn spheres_in_view_are_kept() {
+        let frustum = frustum();
+        assert!(frustum.intersects_sphere(vec3(0.0, 0.0, -10.0), 1.0));
+        // Partly inside the left plane.
+        assert!(frustum.intersects_sphere(vec3(-10.5, 0.0, -10.0), 1.0));
+    }
+
+    #[test]
+    fn spheres_out_of_view_are_culled() {
+        let frustum = frustum();
+        assert!(!frustum.intersects_sphere(vec3(0.0, 0.0, 10.0), 1.0));
+        assert!(!frustum.intersects_sphere(vec3(20.0, 0.0, -10.0), 1.0));
+        assert!(!frustum.intersects_sphere(vec3(0.0, 0.0, -200.0), 1.0));
+    }
+}
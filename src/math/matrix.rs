@@ -73,6 +73,14 @@ impl std::ops::Mul<Matrix4> for Matrix4 {
     }
 }
 
+impl std::ops::Mul<Vector4> for Matrix4 {
+    type Output = Vector4;
+
+    fn mul(self, vector: Vector4) -> Self::Output {
+        self.x * vector.x + self.y * vector.y + self.z * vector.z + self.w * vector.w
+    }
+}
+
 impl std::ops::Index<usize> for Matrix4 {
     type Output = Vector4;
 
@@ -175,6 +183,46 @@ impl Matrix4 {
         )
     }
 
+    /// Creates a matrix that rotates around the z-axis. Theta is in radians.
+    pub fn from_angle_z(theta: f32) -> Matrix4 {
+        let c = f32::cos(theta);
+        let s = f32::sin(theta);
+
+        #[cfg_attr(rustfmt, rustfmt_skip)]
+        Matrix4::new(
+            c, s, 0.0, 0.0,
+            -s, c, 0.0, 0.0,
+            0.0, 0.0, 1.0, 0.0,
+            0.0, 0.0, 0.0, 1.0,
+        )
+    }
+
+    pub fn identity() -> Matrix4 {
+        Matrix4::from_scale(1.0)
+    }
+
+    /// Creates a matrix scaling uniformly by `value`.
+    pub fn from_scale(value: f32) -> Matrix4 {
+        #[cfg_attr(rustfmt, rustfmt_skip)]
+        Matrix4::new(
+            value, 0.0, 0.0, 0.0,
+            0.0, value, 0.0, 0.0,
+            0.0, 0.0, value, 0.0,
+            0.0, 0.0, 0.0, 1.0,
+        )
+    }
+
+    /// Creates a matrix scaling each axis by the matching component of `scale`.
+    pub fn from_nonuniform_scale(scale: Vector3) -> Matrix4 {
+        #[cfg_attr(rustfmt, rustfmt_skip)]
+        Matrix4::new(
+            scale.x, 0.0, 0.0, 0.0,
+            0.0, scale.y, 0.0, 0.0,
+            0.0, 0.0, scale.z, 0.0,
+            0.0, 0.0, 0.0, 1.0,
+        )
+    }
+
     pub fn from_translation(translation: Vector3) -> Matrix4 {
         #[cfg_attr(rustfmt, rustfmt_skip)]
         Matrix4::new(
@@ -184,4 +232,21 @@ impl Matrix4 {
             translation.x, translation.y, translation.z, 1.0,
         )
     }
+
+    /// Creates the matrix transforming normals along with this one, the
+    /// inverse transpose of its upper 3 x 3 part. Its scale is arbitrary, the
+    /// transformed normals must be normalized.
+    pub fn normal_matrix(self) -> Matrix4 {
+        let (a, b, c) = (self.x.truncate(), self.y.truncate(), self.z.truncate());
+        // The cofactors are the inverse transpose times the determinant, its
+        // sign is kept so mirroring flips normals.
+        let sign = a.dot(b.cross(c)).signum();
+        let column = |v: Vector3| Vector4::new(v.x, v.y, v.z, 0.0) * sign;
+        Matrix4::from_cols(
+            column(b.cross(c)),
+            column(c.cross(a)),
+            column(a.cross(b)),
+            Vector4::new(0.0, 0.0, 0.0, 1.0),
+        )
+    }
 }
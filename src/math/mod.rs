@@ -1,7 +1,9 @@
 // pub use angle;
 pub use angle::{Deg, Rad};
+pub use color::srgb_to_linear;
+pub use frustum::Frustum;
 pub use matrix::Matrix4;
-pub use projection::perspective;
+pub use projection::{fit_distance, vulkan_perspective, vulkan_reverse_perspective};
 pub use vector::{vec2, vec3, vec4, Vector2, Vector3, Vector4};
 
 pub type Vec2 = Vector2;
@@ -9,6 +11,8 @@ pub type Vec3 = Vector3;
 pub type Vec4 = Vector4;
 
 mod angle;
+mod color;
+mod frustum;
 mod matrix;
 mod projection;
 mod vector;
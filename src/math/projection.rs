@@ -1,6 +1,11 @@
 use super::{Deg, Matrix4, Rad};
 
-/// Create a perspective projection matrix.
+/// Create a perspective projection matrix, looking down -Z and mapping depth to
+/// [-1, 1], with Y pointing down as in Vulkan.
+///
+/// For instance a `fovy` of 90°, an `aspect` of 1, a `near` of 1 and a `far`
+/// of 10 give `c0r0 = 1`, `c1r1 = -1`, `c2r2 = -11/9`, `c2r3 = -1` and
+/// `c3r2 = -20/9`, every other element being 0.
 pub fn perspective(fovy: Deg, aspect: f32, near: f32, far: f32) -> Matrix4 {
     let fovy_rad: Rad = fovy.into();
 
@@ -34,3 +39,95 @@ pub fn perspective(fovy: Deg, aspect: f32, near: f32, far: f32) -> Matrix4 {
 		c3r0, c3r1, c3r2, c3r3,
 	)
 }
+
+/// Distance from the camera at which a sphere of `radius` fits in the view of
+/// `perspective(fovy, aspect, ..)`, horizontally and vertically.
+pub fn fit_distance(radius: f32, fovy: Deg, aspect: f32) -> f32 {
+    let half_fovy = Rad::from(fovy) / 2.0;
+    // Narrow windows are limited by their horizontal field of view.
+    let half_fov = Rad::atan(half_fovy.tan() * aspect.min(1.0));
+    radius / half_fov.sin()
+}
+
+/// Maps the OpenGL style clip space depth produced by `perspective`, in
+/// [-1, 1], to the [0, 1] range Vulkan expects.
+#[rustfmt::skip]
+pub const DEPTH_CORRECTION: Matrix4 = Matrix4::new(
+    1.0, 0.0,       0.0, 0.0,
+    0.0, 1.0,       0.0, 0.0,
+    0.0, 0.0, 1.0 / 2.0, 0.0,
+    0.0, 0.0, 1.0 / 2.0, 1.0,
+);
+
+/// Maps the clip space depth produced by `perspective`, in [-1, 1], to the
+/// reversed [1, 0] range: floating point depth buffers are most precise near
+/// 0, which then holds the distant points.
+#[rustfmt::skip]
+pub const REVERSE_DEPTH_CORRECTION: Matrix4 = Matrix4::new(
+    1.0, 0.0,        0.0, 0.0,
+    0.0, 1.0,        0.0, 0.0,
+    0.0, 0.0, -1.0 / 2.0, 0.0,
+    0.0, 0.0,  1.0 / 2.0, 1.0,
+);
+
+/// Create a perspective projection matrix for Vulkan: after the perspective
+/// divide, points on the near plane have a depth of 0 and points on the far
+/// plane a depth of 1.
+pub fn vulkan_perspective(fovy: Deg, aspect: f32, near: f32, far: f32) -> Matrix4 {
+    DEPTH_CORRECTION * perspective(fovy, aspect, near, far)
+}
+
+/// Same as `vulkan_perspective` with a reversed depth: points on the near
+/// plane have a depth of 1 and points on the far plane a depth of 0.
+pub fn vulkan_reverse_perspective(fovy: Deg, aspect: f32, near: f32, far: f32) -> Matrix4 {
+    REVERSE_DEPTH_CORRECTION * perspective(fovy, aspect, near, far)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::vec4;
+
+    const NEAR: f32 = 0.1;
+    const FAR: f32 = 100.0;
+
+    /// Depth after the perspective divide of the point at `distance` in
+    /// front of the camera.
+    fn depth(projection: Matrix4, distance: f32) -> f32 {
+        (projection * vec4(0.0, 0.0, -distance, 1.0)).homogenize().z
+    }
+
+    #[test]
+    fn perspective_matches_the_reference() {
+        let m = perspective(Deg(90.0), 1.0, 1.0, 10.0);
+        #[rustfmt::skip]
+        let expected = [
+            [1.0,  0.0,         0.0,  0.0],
+            [0.0, -1.0,         0.0,  0.0],
+            [0.0,  0.0, -11.0 / 9.0, -1.0],
+            [0.0,  0.0, -20.0 / 9.0,  0.0],
+        ];
+        for (c, column) in expected.iter().enumerate() {
+            let actual = [m[c].x, m[c].y, m[c].z, m[c].w];
+            for (r, value) in column.iter().enumerate() {
+                assert!((actual[r] - value).abs() < 1e-5, "c{}r{}", c, r);
+            }
+        }
+    }
+
+    #[test]
+    fn depth_goes_from_zero_at_near_to_one_at_far() {
+        let projection = vulkan_perspective(Deg(45.0), 16.0 / 9.0, NEAR, FAR);
+        assert!(depth(projection, NEAR).abs() < 1e-5);
+        assert!((depth(projection, FAR) - 1.0).abs() < 1e-5);
+        let middle = depth(projection, (NEAR + FAR) / 2.0);
+        assert!(middle > 0.0 && middle < 1.0);
+    }
+
+    #[test]
+    fn reverse_depth_goes_from_one_at_near_to_zero_at_far() {
+        let projection = vulkan_reverse_perspective(Deg(45.0), 16.0 / 9.0, NEAR, FAR);
+        assert!((depth(projection, NEAR) - 1.0).abs() < 1e-5);
+        assert!(depth(projection, FAR).abs() < 1e-5);
+    }
+}
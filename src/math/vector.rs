@@ -68,6 +68,26 @@ macro_rules! impl_vector {
             }
         }
 
+        impl std::ops::Div<f32> for $VectorN {
+            type Output = $VectorN;
+
+            fn div(self, scalar: f32) -> Self::Output {
+                $VectorN {
+                    $($field: self.$field / scalar),+
+                }
+            }
+        }
+
+        impl std::ops::Div<$VectorN> for $VectorN {
+            type Output = $VectorN;
+
+            fn div(self, other: $VectorN) -> Self::Output {
+                $VectorN {
+                    $($field: self.$field / other.$field),+
+                }
+            }
+        }
+
         impl std::ops::Mul<$VectorN> for $VectorN {
             type Output = $VectorN;
 
@@ -148,6 +168,12 @@ macro_rules! impl_vector {
             }
         }
 
+        impl std::ops::MulAssign<f32> for $VectorN {
+            fn mul_assign(&mut self, other: f32) {
+                $(self.$field *= other;)+
+            }
+        }
+
         impl std::ops::DivAssign<$VectorN> for $VectorN {
             fn div_assign(&mut self, other: $VectorN) {
                 $(self.$field /= other.$field;)+
@@ -210,6 +236,71 @@ impl Vector3 {
     }
 }
 
+impl Vector4 {
+    /// Returns the `x`, `y` and `z` components, dropping `w`.
+    #[inline]
+    pub fn truncate(self) -> Vector3 {
+        Vector3::new(self.x, self.y, self.z)
+    }
+
+    /// Returns the point in 3D space of homogeneous coordinates, dividing
+    /// them by `w`.
+    // Only the projection tests take points out of clip space so far.
+    #[cfg_attr(not(test), allow(dead_code))]
+    #[inline]
+    pub fn homogenize(self) -> Vector3 {
+        self.truncate() * (1.0 / self.w)
+    }
+}
+
 impl_vector!(Vector2 { x, y }, 2, vec2);
 impl_vector!(Vector3 { x, y, z }, 3, vec3);
 impl_vector!(Vector4 { x, y, z, w }, 4, vec4);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn homogenize_divides_by_w() {
+        assert_eq!(vec4(2.0, 4.0, 6.0, 2.0).homogenize(), vec3(1.0, 2.0, 3.0));
+    }
+
+    #[test]
+    fn truncate_drops_w() {
+        assert_eq!(vec4(2.0, 4.0, 6.0, 2.0).truncate(), vec3(2.0, 4.0, 6.0));
+    }
+
+    #[test]
+    fn negation_flips_every_component() {
+        assert_eq!(-vec2(1.0, -2.0), vec2(-1.0, 2.0));
+        assert_eq!(-vec3(1.0, -2.0, 3.0), vec3(-1.0, 2.0, -3.0));
+        assert_eq!(-vec4(1.0, -2.0, 3.0, -4.0), vec4(-1.0, 2.0, -3.0, 4.0));
+    }
+
+    #[test]
+    fn compound_assignment_updates_in_place() {
+        let mut v = vec3(1.0, 2.0, 3.0);
+        v += vec3(1.0, 1.0, 1.0);
+        assert_eq!(v, vec3(2.0, 3.0, 4.0));
+        v -= vec3(2.0, 2.0, 2.0);
+        assert_eq!(v, vec3(0.0, 1.0, 2.0));
+        v *= 3.0;
+        assert_eq!(v, vec3(0.0, 3.0, 6.0));
+        v *= vec3(1.0, 2.0, 0.5);
+        assert_eq!(v, vec3(0.0, 6.0, 3.0));
+        v /= 3.0;
+        assert_eq!(v, vec3(0.0, 2.0, 1.0));
+        v /= vec3(1.0, 2.0, 4.0);
+        assert_eq!(v, vec3(0.0, 1.0, 0.25));
+    }
+
+    #[test]
+    fn division_by_scalar_and_componentwise() {
+        assert_eq!(vec3(2.0, 4.0, 6.0) / 2.0, vec3(1.0, 2.0, 3.0));
+        assert_eq!(
+            vec3(2.0, 4.0, 6.0) / vec3(2.0, 4.0, 3.0),
+            vec3(1.0, 1.0, 2.0)
+        );
+    }
+}
@@ -1,51 +1,744 @@
-use crate::math::{vec2, vec3};
+use crate::descriptor::Mat4;
+use crate::math::{srgb_to_linear, vec2, vec3, vec4, Vec2, Vec3};
+use crate::textures::MAX_TEXTURES;
 use crate::vertex::Vertex;
 use crate::{obj, AppData};
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use std::collections::HashMap;
+use std::path::Path;
 
-pub fn load_model(data: &mut AppData, obj_path: String) -> Result<()> {
-    let models = obj::load_obj(obj_path)?;
+/// Model shown when none is given. Its embedded copy is loaded when the
+/// file is missing from the working directory.
+pub const DEFAULT_MODEL_PATH: &str = "./resources/texture_cube.obj";
+const DEFAULT_DIRECTORY: &str = "./resources";
+const DEFAULT_MODEL: &[u8] = include_bytes!("../resources/texture_cube.obj");
+const DEFAULT_MATERIALS: &[u8] = include_bytes!("../resources/texture_cube.mtl");
 
-    let mut unique_vertices = HashMap::new();
+/// Key used to deduplicate vertices.
+///
+/// With a tolerance of `0.0` the raw bits of every component are used, so
+/// only bit-exact vertices are merged. Otherwise the position is snapped to
+/// a grid of `tolerance` sized cells, merging vertices that only differ by
+/// floating-point noise. The other attributes are always compared exactly:
+/// the OBJ loader already merged the corners sharing a `v/vt/vn` triple, so
+/// a position shared by two UV islands or smoothing groups keeps its
+/// distinct texture coordinates and normals.
+#[derive(Hash, Eq, PartialEq, Debug, Copy, Clone)]
+struct VertexKey([i64; 20]);
 
-    for model in &models {
-        for index in &model.mesh.indices {
-            let pos_offset = (3 * index) as usize;
-            let tex_coord_offset = (2 * index) as usize;
-
-            let tex_coord = if model.mesh.tex_coords.len() > 0 {
-                vec2(
-                    model.mesh.tex_coords[tex_coord_offset],
-                    1.0 - model.mesh.tex_coords[tex_coord_offset + 1],
-                )
+impl VertexKey {
+    fn new(vertex: &Vertex, lightmap_tex_coord: Vec2, tolerance: f32) -> Self {
+        let components = [
+            vertex.pos.x,
+            vertex.pos.y,
+            vertex.pos.z,
+            vertex.color.x,
+            vertex.color.y,
+            vertex.color.z,
+            vertex.tex_coord.x,
+            vertex.tex_coord.y,
+            vertex.emissive.x,
+            vertex.emissive.y,
+            vertex.emissive.z,
+            vertex.specular.x,
+            vertex.specular.y,
+            vertex.specular.z,
+            vertex.shininess,
+            vertex.normal.x,
+            vertex.normal.y,
+            vertex.normal.z,
+            lightmap_tex_coord.x,
+            lightmap_tex_coord.y,
+        ];
+
+        let mut key = [0; 20];
+        for (i, (k, c)) in key.iter_mut().zip(components).enumerate() {
+            *k = if tolerance > 0.0 && i < 3 {
+                (c / tolerance).round() as i64
             } else {
-                vec2(
-                    model.mesh.positions[pos_offset + 1],
-                    model.mesh.positions[pos_offset + 2],
-                )
+                c.to_bits() as i64
             };
+        }
+        VertexKey(key)
+    }
+}
 
-            let vertex = Vertex {
-                pos: vec3(
-                    model.mesh.positions[pos_offset],
-                    model.mesh.positions[pos_offset + 1],
-                    model.mesh.positions[pos_offset + 2],
-                ),
-                color: vec3(1.0, 1.0, 1.0),
-                tex_coord,
-            };
+/// A range of `AppData::indices` drawn in a single draw call, with one
+/// texture.
+#[derive(Clone, Debug, Default)]
+pub struct Object {
+    pub name: String,
+    pub first_index: u32,
+    pub index_count: u32,
+    /// Index of the texture in `AppData::textures`, 0 being the default one.
+    pub texture_index: u32,
+    /// Bounding sphere of the object, in model space.
+    pub center: Vec3,
+    pub radius: f32,
+}
 
-            if let Some(index) = unique_vertices.get(&vertex) {
-                data.indices.push(*index as u32);
-            } else {
-                let index = data.vertices.len();
-                unique_vertices.insert(vertex, index);
-                data.vertices.push(vertex);
-                data.indices.push(index as u32);
+/// Computes a sphere bounding the vertices referenced by `indices`.
+fn bounding_sphere(vertices: &[Vertex], indices: &[u32]) -> (Vec3, f32) {
+    let Some(&first) = indices.first() else {
+        return (Vec3::default(), 0.0);
+    };
+    let (mut min, mut max) = (vertices[first as usize].pos, vertices[first as usize].pos);
+    for &index in indices {
+        let pos = vertices[index as usize].pos;
+        min = vec3(min.x.min(pos.x), min.y.min(pos.y), min.z.min(pos.z));
+        max = vec3(max.x.max(pos.x), max.y.max(pos.y), max.z.max(pos.z));
+    }
+    let center = (min + max) * 0.5;
+    let radius = indices
+        .iter()
+        .map(|&index| (vertices[index as usize].pos - center).magnitude())
+        .fold(0.0, f32::max);
+    (center, radius)
+}
+
+/// Returns the index of the texture at `path` in `AppData::textures`,
+/// registering it in `data.texture_paths` if needed.
+fn texture_index(data: &mut AppData, path: &str) -> u32 {
+    if let Some(index) = data.texture_paths.iter().position(|p| p == path) {
+        return index as u32 + 1;
+    }
+    if data.texture_paths.len() + 1 >= MAX_TEXTURES {
+        log::warn!("Too many textures, {} uses the default one", path);
+        return 0;
+    }
+    data.texture_paths.push(path.to_owned());
+    data.texture_paths.len() as u32
+}
+
+/// Reads the vertex deduplication tolerance from `SCOP_DEDUP_TOLERANCE`.
+///
+/// Defaults to `0.0` (exact matching) when unset or invalid.
+pub fn dedup_tolerance_from_env() -> f32 {
+    match std::env::var("SCOP_DEDUP_TOLERANCE") {
+        Ok(value) => match value.parse::<f32>() {
+            Ok(tolerance) if tolerance >= 0.0 => tolerance,
+            _ => {
+                log::warn!("Invalid SCOP_DEDUP_TOLERANCE: {}", value);
+                0.0
+            }
+        },
+        Err(_) => 0.0,
+    }
+}
+
+/// Reads the triangle count above which a warning is logged from
+/// `SCOP_MAX_TRIANGLES`. No limit is applied when unset or invalid.
+pub fn max_triangles_from_env() -> Option<usize> {
+    let value = std::env::var("SCOP_MAX_TRIANGLES").ok()?;
+    match value.parse::<usize>() {
+        Ok(max) => Some(max),
+        Err(_) => {
+            log::warn!("Invalid SCOP_MAX_TRIANGLES: {}", value);
+            None
+        }
+    }
+}
+
+/// Reads the angle, in degrees, above which the edges of models without
+/// normals are kept sharp from `SCOP_HARD_EDGE_ANGLE`. No normals are
+/// generated when unset or invalid.
+pub fn hard_edge_angle_from_env() -> Option<f32> {
+    let value = std::env::var("SCOP_HARD_EDGE_ANGLE").ok()?;
+    match value.parse::<f32>() {
+        Ok(angle) if (0.0..=180.0).contains(&angle) => Some(angle),
+        _ => {
+            log::warn!("Invalid SCOP_HARD_EDGE_ANGLE: {}", value);
+            None
+        }
+    }
+}
+
+/// Whether the normals of the file are used as is rather than normalized,
+/// from `SCOP_KEEP_NORMALS`.
+pub fn keep_normals_from_env() -> bool {
+    matches!(
+        std::env::var("SCOP_KEEP_NORMALS").as_deref(),
+        Ok("1") | Ok("true")
+    )
+}
+
+/// Whether triangles without area are dropped, from `SCOP_DROP_DEGENERATE`.
+/// They are kept by default to preserve the topology of the file.
+pub fn drop_degenerate_from_env() -> bool {
+    matches!(
+        std::env::var("SCOP_DROP_DEGENERATE").as_deref(),
+        Ok("1") | Ok("true")
+    )
+}
+
+/// Whether models above the triangle limit get decimated, from `SCOP_DECIMATE`.
+pub fn decimate_from_env() -> bool {
+    matches!(
+        std::env::var("SCOP_DECIMATE").as_deref(),
+        Ok("1") | Ok("true")
+    )
+}
+
+/// Whether OBJ and MTL numbers may use a comma as decimal separator, from
+/// `SCOP_DECIMAL_COMMA`.
+pub fn decimal_comma_from_env() -> bool {
+    matches!(
+        std::env::var("SCOP_DECIMAL_COMMA").as_deref(),
+        Ok("1") | Ok("true")
+    )
+}
+
+/// How the winding of the loaded triangles is fixed, from `SCOP_FLIP_WINDING`:
+/// `1` or `true` flips every triangle and `auto` only flips meshes whose
+/// triangles mostly face away from their normals.
+pub fn winding_from_env() -> obj::Winding {
+    match std::env::var("SCOP_FLIP_WINDING").as_deref() {
+        Ok("1") | Ok("true") => obj::Winding::Flip,
+        Ok("auto") => obj::Winding::Auto,
+        _ => obj::Winding::Keep,
+    }
+}
+
+/// Matrix placing an object in the model, scaling it, rotating it around X, Y
+/// then Z and translating it.
+fn transform_matrix(transform: &obj::Transform) -> Mat4 {
+    let [tx, ty, tz] = transform.translation;
+    let [rx, ry, rz] = transform.rotation.map(f32::to_radians);
+    let [sx, sy, sz] = transform.scale;
+    Mat4::from_translation(vec3(tx, ty, tz))
+        * Mat4::from_angle_z(rz)
+        * Mat4::from_angle_y(ry)
+        * Mat4::from_angle_x(rx)
+        * Mat4::from_nonuniform_scale(vec3(sx, sy, sz))
+}
+
+/// Builds the vertex at `index` of a parsed mesh, colored by its material and
+/// placed by `transform`, whose `Mat4::normal_matrix` is `normal_matrix`.
+fn mesh_vertex(
+    mesh: &obj::Mesh,
+    material: Option<&obj::Material>,
+    transform: &Mat4,
+    normal_matrix: &Mat4,
+    index: u32,
+) -> Vertex {
+    let pos_offset = (3 * index) as usize;
+    let tex_coord_offset = (2 * index) as usize;
+
+    let tex_coord = if mesh.tex_coords.len() > 0 {
+        vec2(
+            mesh.tex_coords[tex_coord_offset],
+            1.0 - mesh.tex_coords[tex_coord_offset + 1],
+        )
+    } else {
+        vec2(
+            mesh.positions[pos_offset + 1],
+            mesh.positions[pos_offset + 2],
+        )
+    };
+
+    let pos = vec4(
+        mesh.positions[pos_offset],
+        mesh.positions[pos_offset + 1],
+        mesh.positions[pos_offset + 2],
+        1.0,
+    );
+
+    // Normals follow the object through the inverse transpose, which keeps
+    // them perpendicular under non-uniform scale. The model may have none.
+    let normal = match mesh.normals.get(pos_offset..pos_offset + 3) {
+        Some(&[x, y, z]) if (x, y, z) != (0.0, 0.0, 0.0) => {
+            (*normal_matrix * vec4(x, y, z, 0.0)).truncate().normalize()
+        }
+        _ => vec3(0.0, 0.0, 0.0),
+    };
+
+    Vertex {
+        pos: (*transform * pos).truncate(),
+        color: match mesh.colors.get(pos_offset..pos_offset + 3) {
+            Some(&[r, g, b]) => color([r, g, b]),
+            _ => material.map_or(vec3(1.0, 1.0, 1.0), |m| color(m.diffuse)),
+        },
+        tex_coord,
+        emissive: material.map_or(vec3(0.0, 0.0, 0.0), |m| color(m.emissive)),
+        specular: material.map_or(vec3(0.0, 0.0, 0.0), |m| color(m.specular)),
+        shininess: material.map_or(0.0, |m| m.shininess),
+        normal,
+    }
+}
+
+/// Lightmap texture coordinates of the vertex at `index` of a parsed mesh,
+/// zero when it has none.
+fn mesh_lightmap_tex_coord(mesh: &obj::Mesh, index: u32) -> Vec2 {
+    let offset = (2 * index) as usize;
+    match mesh.lightmap_tex_coords.get(offset..offset + 2) {
+        Some(&[u, v]) => vec2(u, 1.0 - v),
+        _ => vec2(0.0, 0.0),
+    }
+}
+
+/// Material colors are sRGB, like the colors picked in most editors. They are
+/// made linear here, as the texture sampler does for sRGB textures, so the
+/// shaders only deal with linear colors and the sRGB swapchain encodes them.
+fn color([r, g, b]: [f32; 3]) -> Vec3 {
+    vec3(srgb_to_linear(r), srgb_to_linear(g), srgb_to_linear(b))
+}
+
+/// Returns the index of `vertex` in `data.vertices`, appending it if no
+/// matching vertex exists yet. Its lightmap texture coordinates go to
+/// `data.lightmap_tex_coords` when the model has some.
+fn push_vertex(
+    data: &mut AppData,
+    unique_vertices: &mut HashMap<VertexKey, usize>,
+    vertex: Vertex,
+    lightmap_tex_coord: Vec2,
+) -> u32 {
+    let key = VertexKey::new(&vertex, lightmap_tex_coord, data.dedup_tolerance);
+    if let Some(index) = unique_vertices.get(&key) {
+        *index as u32
+    } else {
+        let index = data.vertices.len();
+        unique_vertices.insert(key, index);
+        data.vertices.push(vertex);
+        if data.has_lightmap_tex_coords {
+            data.lightmap_tex_coords.push(lightmap_tex_coord);
+        }
+        index as u32
+    }
+}
+
+/// Parses the embedded copy of the default model, with its material
+/// textures pointing to `textures::DEFAULT_TEXTURE_PATH`.
+fn load_default_model(data: &AppData) -> Result<(Vec<obj::Model>, Vec<obj::Material>)> {
+    let load_library = |_: &str| {
+        let mut materials = obj::load_mtl_from_reader(DEFAULT_MATERIALS, data.decimal_comma)?;
+        for texture in materials.iter_mut().filter_map(|m| m.texture.as_mut()) {
+            *texture = format!("{}/{}", DEFAULT_DIRECTORY, texture);
+        }
+        Ok(materials)
+    };
+    Ok(obj::load_obj_from_reader_with(
+        DEFAULT_MODEL,
+        load_library,
+        data.decimal_comma,
+        data.winding,
+    )?)
+}
+
+/// Parses the models of `path`, a glTF file with the `gltf` feature, a PLY
+/// file with the `ply` feature or an OBJ file.
+fn load_models(data: &AppData, path: &str) -> Result<(Vec<obj::Model>, Vec<obj::Material>)> {
+    if path == DEFAULT_MODEL_PATH && !Path::new(path).exists() {
+        return load_default_model(data);
+    }
+    #[cfg(feature = "gltf")]
+    if crate::gltf_import::is_gltf(path) {
+        return crate::gltf_import::load_gltf(path);
+    }
+    #[cfg(feature = "ply")]
+    if crate::ply::is_ply(path) {
+        return crate::ply::load_ply(path);
+    }
+    Ok(obj::load_obj(path, data.decimal_comma, data.winding)?)
+}
+
+/// Loads the models of `obj_path`. Objects use the `map_Kd` texture of their
+/// material, unless an `explicit_texture` replaces it.
+pub fn load_model(data: &mut AppData, obj_path: String, explicit_texture: bool) -> Result<()> {
+    let (mut models, materials) = load_models(data, &obj_path)?;
+    for model in &models {
+        log::info!("{} ({}): {}", model.name, model.group, model.stats());
+    }
+
+    if !data.keep_normals {
+        for model in &mut models {
+            obj::normalize_normals(&mut model.mesh);
+        }
+    }
+
+    if data.drop_degenerate {
+        let dropped: usize = models
+            .iter_mut()
+            .map(|m| obj::remove_degenerate_triangles(&mut m.mesh))
+            .sum();
+        if dropped > 0 {
+            log::info!("Dropped {} degenerate triangles", dropped);
+        }
+    }
+
+    let triangles: usize = models.iter().map(|m| m.mesh.indices.len() / 3).sum();
+    if let Some(max) = data.max_triangles.filter(|&max| triangles > max) {
+        log::warn!(
+            "Model has {} triangles, above the limit of {}",
+            triangles,
+            max
+        );
+        if data.decimate {
+            for model in &mut models {
+                // Split the budget between the models by their share of triangles.
+                let target = max * (model.mesh.indices.len() / 3) / triangles;
+                model.mesh = obj::decimate(&model.mesh, target);
             }
+            let decimated: usize = models.iter().map(|m| m.mesh.indices.len() / 3).sum();
+            log::info!("Decimated model to {} triangles", decimated);
+        }
+    }
+
+    if let Some(angle) = data.hard_edge_angle {
+        for model in models.iter_mut().filter(|m| m.mesh.normals.is_empty()) {
+            model.mesh = obj::generate_normals(&model.mesh, angle);
+        }
+    }
+
+    data.has_lightmap_tex_coords = models
+        .iter()
+        .any(|m| !m.mesh.lightmap_tex_coords.is_empty());
+
+    // Models without texture coordinates are drawn untextured.
+    data.has_tex_coords = models
+        .iter()
+        .all(|m| m.mesh.positions.is_empty() || !m.mesh.tex_coords.is_empty());
+
+    let mut unique_vertices = HashMap::new();
+
+    for model in &models {
+        let material = obj::material_for(&materials, &model.mesh);
+        let texture_index = match material.and_then(|m| m.texture.as_deref()) {
+            Some(path) if !explicit_texture => texture_index(data, path),
+            _ => 0,
+        };
+        let transform = transform_matrix(&model.transform);
+        let normal_matrix = transform.normal_matrix();
+        let first_index = data.indices.len();
+        for index in &model.mesh.indices {
+            let vertex = mesh_vertex(&model.mesh, material, &transform, &normal_matrix, *index);
+            let lightmap_tex_coord = mesh_lightmap_tex_coord(&model.mesh, *index);
+            let index = push_vertex(data, &mut unique_vertices, vertex, lightmap_tex_coord);
+            data.indices.push(index);
         }
+        let (center, radius) = bounding_sphere(&data.vertices, &data.indices[first_index..]);
+        data.objects.push(Object {
+            name: model.name.clone(),
+            first_index: first_index as u32,
+            index_count: model.mesh.indices.len() as u32,
+            texture_index,
+            center,
+            radius,
+        });
+        for index in &model.mesh.point_indices {
+            let vertex = mesh_vertex(&model.mesh, material, &transform, &normal_matrix, *index);
+            let lightmap_tex_coord = mesh_lightmap_tex_coord(&model.mesh, *index);
+            let index = push_vertex(data, &mut unique_vertices, vertex, lightmap_tex_coord);
+            data.point_indices.push(index);
+        }
+    }
+
+    // Vulkan rejects zero-size vertex buffers.
+    if data.vertices.is_empty() {
+        return Err(anyhow!("{} has no faces or points to draw", obj_path));
     }
 
     Ok(())
 }
+
+/// Replaces the model in `data` with the models of `obj_path`, as
+/// `load_model` does. The current model is kept when loading fails.
+pub fn reload_model(data: &mut AppData, obj_path: String, explicit_texture: bool) -> Result<()> {
+    let previous = (
+        std::mem::take(&mut data.vertices),
+        std::mem::take(&mut data.lightmap_tex_coords),
+        std::mem::take(&mut data.indices),
+        std::mem::take(&mut data.point_indices),
+        std::mem::take(&mut data.objects),
+        std::mem::take(&mut data.texture_paths),
+        data.has_tex_coords,
+        data.has_lightmap_tex_coords,
+    );
+    let result = load_model(data, obj_path, explicit_texture);
+    if result.is_err() {
+        (
+            data.vertices,
+            data.lightmap_tex_coords,
+            data.indices,
+            data.point_indices,
+            data.objects,
+            data.texture_paths,
+            data.has_tex_coords,
+            data.has_lightmap_tex_coords,
+        ) = previous;
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: Vec3, b: Vec3) {
+        assert!((a - b).magnitude() < 1e-5, "{:?} != {:?}", a, b);
+    }
+
+    fn vertex_at(x: f32) -> Vertex {
+        let zero = vec3(0.0, 0.0, 0.0);
+        Vertex::new(
+            vec3(x, 0.0, 0.0),
+            zero,
+            vec2(0.0, 0.0),
+            zero,
+            zero,
+            0.0,
+            zero,
+        )
+    }
+
+    /// Pushes a vertex at `x = 1` and one `1e-7` further, returning their
+    /// indices.
+    fn push_close_vertices(tolerance: f32) -> (u32, u32) {
+        let mut data = AppData {
+            dedup_tolerance: tolerance,
+            ..Default::default()
+        };
+        let mut unique_vertices = HashMap::new();
+        let no_lightmap = vec2(0.0, 0.0);
+        let a = push_vertex(&mut data, &mut unique_vertices, vertex_at(1.0), no_lightmap);
+        let b = push_vertex(
+            &mut data,
+            &mut unique_vertices,
+            vertex_at(1.0 + 1e-7),
+            no_lightmap,
+        );
+        (a, b)
+    }
+
+    #[test]
+    fn lightmap_tex_coords_follow_the_vertices() {
+        let mut data = AppData {
+            has_lightmap_tex_coords: true,
+            ..Default::default()
+        };
+        let mut unique_vertices = HashMap::new();
+        // Same vertex, lightmapped at two places.
+        for u in [0.25, 0.25, 0.75] {
+            push_vertex(
+                &mut data,
+                &mut unique_vertices,
+                vertex_at(1.0),
+                vec2(u, 0.0),
+            );
+        }
+        assert_eq!(data.vertices.len(), 2);
+        assert_eq!(data.lightmap_tex_coords, [vec2(0.25, 0.0), vec2(0.75, 0.0)]);
+    }
+
+    #[test]
+    fn close_vertices_merge_under_a_tolerance() {
+        let (a, b) = push_close_vertices(1e-5);
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn close_vertices_stay_apart_when_exact() {
+        let (a, b) = push_close_vertices(0.0);
+        assert_ne!(a, b);
+    }
+
+    #[test]
+    fn transform_places_objects_in_the_world() {
+        let transform = transform_matrix(&obj::Transform {
+            translation: [1.0, 2.0, 3.0],
+            rotation: [0.0, 0.0, 90.0],
+            scale: [2.0, 2.0, 2.0],
+        });
+        assert_close(
+            (transform * vec4(0.0, 0.0, 0.0, 1.0)).truncate(),
+            vec3(1.0, 2.0, 3.0),
+        );
+        // Scaled to 2, then rotated a quarter turn from X to Y.
+        assert_close(
+            (transform * vec4(1.0, 0.0, 0.0, 1.0)).truncate(),
+            vec3(1.0, 4.0, 3.0),
+        );
+    }
+
+    #[test]
+    fn normals_stay_perpendicular_under_non_uniform_scale() {
+        // A face along the plane x + y = 1 squashed along Y.
+        let mesh = obj::Mesh {
+            positions: vec![1.0, 0.0, 0.0, 0.0, 1.0, 0.0],
+            normals: vec![1.0, 1.0, 0.0, 1.0, 1.0, 0.0],
+            ..Default::default()
+        };
+        let transform = transform_matrix(&obj::Transform {
+            scale: [1.0, 4.0, 1.0],
+            ..Default::default()
+        });
+        let normal_matrix = transform.normal_matrix();
+        let a = mesh_vertex(&mesh, None, &transform, &normal_matrix, 0);
+        let b = mesh_vertex(&mesh, None, &transform, &normal_matrix, 1);
+        assert!(a.normal.dot(b.pos - a.pos).abs() < 1e-5);
+        assert_close(a.normal, vec3(4.0, 1.0, 0.0).normalize());
+    }
+
+    #[test]
+    fn mirroring_flips_normals() {
+        let transform = Mat4::from_nonuniform_scale(vec3(-1.0, 1.0, 1.0));
+        let normal = transform.normal_matrix() * vec4(1.0, 0.0, 0.0, 0.0);
+        assert_close(normal.truncate().normalize(), vec3(-1.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn reload_swaps_the_model() {
+        let mut data = AppData::default();
+        load_model(&mut data, DEFAULT_MODEL_PATH.to_owned(), false).unwrap();
+
+        let path = std::env::temp_dir().join("scop_reload_triangle.obj");
+        std::fs::write(&path, "v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\n").unwrap();
+        let path = path.to_string_lossy().into_owned();
+        reload_model(&mut data, path, false).unwrap();
+        assert_eq!(data.vertices.len(), 3);
+        assert_eq!(data.indices, [0, 1, 2]);
+        assert_eq!(data.objects.len(), 1);
+    }
+
+    #[test]
+    fn failed_reload_keeps_the_model() {
+        let mut data = AppData::default();
+        load_model(&mut data, DEFAULT_MODEL_PATH.to_owned(), false).unwrap();
+        let (vertices, indices) = (data.vertices.clone(), data.indices.clone());
+
+        assert!(reload_model(&mut data, "missing.obj".to_owned(), false).is_err());
+        assert_eq!(data.vertices, vertices);
+        assert_eq!(data.indices, indices);
+        assert!(!data.objects.is_empty());
+    }
+
+    #[test]
+    fn emissive_color_comes_from_the_material() {
+        let mesh = obj::Mesh {
+            positions: vec![0.0, 0.0, 0.0],
+            ..Default::default()
+        };
+        let material = obj::Material {
+            emissive: [1.0, 0.0, 0.0],
+            ..Default::default()
+        };
+        let identity = Mat4::identity();
+        let vertex = mesh_vertex(&mesh, Some(&material), &identity, &identity, 0);
+        assert_close(vertex.emissive, vec3(1.0, 0.0, 0.0));
+        let vertex = mesh_vertex(&mesh, None, &identity, &identity, 0);
+        assert_close(vertex.emissive, vec3(0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn models_without_tex_coords_are_untextured() {
+        let path = std::env::temp_dir().join("scop_untextured_triangle.obj");
+        std::fs::write(&path, "v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\n").unwrap();
+        let mut data = AppData::default();
+        load_model(&mut data, path.to_string_lossy().into_owned(), false).unwrap();
+        assert!(!data.has_tex_coords);
+
+        load_model(&mut data, DEFAULT_MODEL_PATH.to_owned(), false).unwrap();
+        assert!(data.has_tex_coords);
+    }
+
+    #[test]
+    fn faceless_models_are_rejected() {
+        let path = std::env::temp_dir().join("scop_faceless.obj");
+        std::fs::write(&path, "v 0 0 0\nv 1 0 0\nv 0 1 0\n").unwrap();
+        let mut data = AppData::default();
+        let error = load_model(&mut data, path.to_string_lossy().into_owned(), false).unwrap_err();
+        assert!(error.to_string().contains("no faces"), "{}", error);
+    }
+
+    #[test]
+    fn point_clouds_have_no_triangles() {
+        let path = std::env::temp_dir().join("scop_points.obj");
+        std::fs::write(&path, "v 0 0 0\nv 1 0 0\np 1 2\n").unwrap();
+        let mut data = AppData::default();
+        load_model(&mut data, path.to_string_lossy().into_owned(), false).unwrap();
+        // Left without an index buffer rather than an empty one.
+        assert!(data.indices.is_empty());
+        assert_eq!(data.point_indices.len(), 2);
+    }
+
+    #[test]
+    fn map_kd_is_used_without_a_texture_argument() {
+        let dir = std::env::temp_dir().join("scop_map_kd");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("brick.mtl"), "newmtl brick\nmap_Kd brick.png\n").unwrap();
+        let obj =
+            "mtllib brick.mtl\nv 0 0 0\nv 1 0 0\nv 0 1 0\nvt 0 0\nusemtl brick\nf 1/1 2/1 3/1\n";
+        let path = dir.join("brick.obj");
+        std::fs::write(&path, obj).unwrap();
+        let path = path.to_string_lossy().into_owned();
+
+        let mut data = AppData::default();
+        load_model(&mut data, path.clone(), false).unwrap();
+        assert_eq!(
+            data.texture_paths,
+            [dir.join("brick.png").to_string_lossy()]
+        );
+        assert_eq!(data.objects[0].texture_index, 1);
+
+        let mut data = AppData::default();
+        load_model(&mut data, path, true).unwrap();
+        assert!(data.texture_paths.is_empty());
+        assert_eq!(data.objects[0].texture_index, 0);
+    }
+
+    #[test]
+    fn uv_islands_sharing_a_position_stay_apart() {
+        let path = std::env::temp_dir().join("scop_uv_islands.obj");
+        let obj = "v 0 0 0\nv 1 0 0\nv 0 1 0\nv -1 0 0\nvt 0 0\nvt 0.001 0\nvt 0 1\n\
+                   f 1/1 2/1 3/1\nf 1/2 3/3 4/3\n";
+        std::fs::write(&path, obj).unwrap();
+        let mut data = AppData {
+            dedup_tolerance: 0.01,
+            ..Default::default()
+        };
+        load_model(&mut data, path.to_string_lossy().into_owned(), false).unwrap();
+        let shared: Vec<_> = data
+            .vertices
+            .iter()
+            .filter(|v| v.pos == vec3(0.0, 0.0, 0.0))
+            .map(|v| v.tex_coord)
+            .collect();
+        assert_eq!(shared, [vec2(0.0, 1.0), vec2(0.001, 1.0)]);
+    }
+
+    #[test]
+    fn degenerate_triangles_are_dropped_on_request() {
+        let path = std::env::temp_dir().join("scop_degenerate.obj");
+        // The second triangle is flat, its corners lying on a line.
+        let obj = "v 0 0 0\nv 1 0 0\nv 0 1 0\nv 2 0 0\nf 1 2 3\nf 1 2 4\n";
+        std::fs::write(&path, obj).unwrap();
+        let path = path.to_string_lossy().into_owned();
+
+        let mut data = AppData::default();
+        load_model(&mut data, path.clone(), false).unwrap();
+        assert_eq!(data.indices.len(), 6);
+
+        let mut data = AppData {
+            drop_degenerate: true,
+            ..Default::default()
+        };
+        load_model(&mut data, path, false).unwrap();
+        assert_eq!(data.indices.len(), 3);
+    }
+
+    #[test]
+    fn objects_index_their_own_texture() {
+        let mut data = AppData::default();
+        assert_eq!(texture_index(&mut data, "a.png"), 1);
+        assert_eq!(texture_index(&mut data, "b.png"), 2);
+        assert_eq!(texture_index(&mut data, "a.png"), 1);
+        assert_eq!(data.texture_paths, ["a.png", "b.png"]);
+    }
+
+    #[test]
+    fn embedded_default_model_parses() {
+        let (models, materials) = load_default_model(&AppData::default()).unwrap();
+        assert!(models.iter().any(|m| !m.mesh.indices.is_empty()));
+        let texture = materials[0].texture.as_deref();
+        assert_eq!(texture, Some(crate::textures::DEFAULT_TEXTURE_PATH));
+    }
+}
@@ -1,6 +1,6 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs::File;
-use std::io::{BufRead, BufReader};
+use std::io::{BufRead, BufReader, Read};
 use std::path::Path;
 use std::str::SplitWhitespace;
 
@@ -9,6 +9,7 @@ pub enum ObjError {
     OpenFileFailed,
     ParseFailed,
     FaceParseError,
+    VertexParseError,
 
     FaceVertexOutOfBounds,
     FaceTexCoordOutOfBounds,
@@ -22,6 +23,7 @@ impl std::fmt::Display for ObjError {
             ObjError::OpenFileFailed => write!(f, "Failed to open file"),
             ObjError::ParseFailed => write!(f, "Failed to parse file"),
             ObjError::FaceParseError => write!(f, "Failed to parse face"),
+            ObjError::VertexParseError => write!(f, "Failed to parse vertex"),
             ObjError::FaceVertexOutOfBounds => write!(f, "Face vertex out of bounds"),
             ObjError::FaceTexCoordOutOfBounds => write!(f, "Face texture coordinate out of bounds"),
             ObjError::FaceNormalOutOfBounds => write!(f, "Face normal out of bounds"),
@@ -34,13 +36,111 @@ impl std::error::Error for ObjError {}
 
 #[derive(Clone, Debug)]
 pub struct Model {
+    /// Name of the object (`o`) the mesh belongs to.
     pub name: String,
+    /// Name of the group (`g`) the mesh belongs to, within its object.
+    pub group: String,
     pub mesh: Mesh,
+    /// Placement of the object, from a `# scop:transform` comment.
+    pub transform: Transform,
 }
 
 impl Model {
-    fn new(name: String, mesh: Mesh) -> Self {
-        Model { name, mesh }
+    fn new(name: String, group: String, mesh: Mesh, transform: Transform) -> Self {
+        Model {
+            name,
+            group,
+            mesh,
+            transform,
+        }
+    }
+
+    /// Counts, attributes and bounds of the mesh, before its transform.
+    pub fn stats(&self) -> MeshStats {
+        let mesh = &self.mesh;
+        let vertices = mesh.positions.len() / 3;
+        let positions: Vec<&[f32]> = mesh.positions.chunks_exact(3).collect();
+        let unique_positions = positions
+            .iter()
+            .map(|p| p.iter().map(|c| c.to_bits()).collect::<Vec<_>>())
+            .collect::<HashSet<_>>()
+            .len();
+        let bounds = positions.split_first().map(|(first, rest)| {
+            let corner = [first[0], first[1], first[2]];
+            let (mut min, mut max) = (corner, corner);
+            for position in rest {
+                for i in 0..3 {
+                    min[i] = min[i].min(position[i]);
+                    max[i] = max[i].max(position[i]);
+                }
+            }
+            (min, max)
+        });
+        MeshStats {
+            vertices,
+            unique_positions,
+            triangles: mesh.indices.len() / 3,
+            points: mesh.point_indices.len(),
+            has_normals: vertices > 0 && mesh.normals.len() == vertices * 3,
+            has_tex_coords: vertices > 0 && mesh.tex_coords.len() == vertices * 2,
+            has_colors: vertices > 0 && mesh.colors.len() == vertices * 3,
+            bounds,
+        }
+    }
+}
+
+/// What `Model::stats` reports about a mesh.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MeshStats {
+    pub vertices: usize,
+    /// Vertices at distinct positions, seams in the other attributes split
+    /// vertices but not positions.
+    pub unique_positions: usize,
+    pub triangles: usize,
+    pub points: usize,
+    pub has_normals: bool,
+    pub has_tex_coords: bool,
+    pub has_colors: bool,
+    /// Smallest and largest corners of the positions, if there are any.
+    pub bounds: Option<([f32; 3], [f32; 3])>,
+}
+
+impl std::fmt::Display for MeshStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{} vertices at {} positions, {} triangles, {} points, normals: {}, UVs: {}, colors: {}",
+            self.vertices,
+            self.unique_positions,
+            self.triangles,
+            self.points,
+            self.has_normals,
+            self.has_tex_coords,
+            self.has_colors
+        )?;
+        if let Some((min, max)) = self.bounds {
+            write!(f, ", bounds: {:?} to {:?}", min, max)?;
+        }
+        Ok(())
+    }
+}
+
+/// Translation, rotation in degrees around X, Y then Z, and scale of an
+/// object.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transform {
+    pub translation: [f32; 3],
+    pub rotation: [f32; 3],
+    pub scale: [f32; 3],
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Transform {
+            translation: [0.0; 3],
+            rotation: [0.0; 3],
+            scale: [1.0; 3],
+        }
     }
 }
 
@@ -50,21 +150,70 @@ pub struct Mesh {
     pub vertices: Vec<f32>,
     pub normals: Vec<f32>,
     pub tex_coords: Vec<f32>,
+    /// RGB color of each vertex, replacing the material color. OBJ files
+    /// have none.
+    pub colors: Vec<f32>,
+    /// Second texture coordinates, for a lightmap. Only glTF models have
+    /// them.
+    pub lightmap_tex_coords: Vec<f32>,
     pub indices: Vec<u32>,
+    pub point_indices: Vec<u32>,
     pub material_id: Option<usize>,
 }
 
+/// Number of floats per vertex returned by `Mesh::interleaved`.
+// The renderer builds its own vertices, this is for other users of the
+// module.
+#[cfg_attr(not(test), allow(dead_code))]
+pub const INTERLEAVED_STRIDE: usize = 8;
+
+impl Mesh {
+    /// Returns the vertices packed as `[x, y, z, nx, ny, nz, u, v]`, with
+    /// zeros for missing normals or texture coordinates, and the triangle
+    /// indices into them.
+    #[cfg_attr(not(test), allow(dead_code))]
+    pub fn interleaved(&self) -> (Vec<f32>, Vec<u32>) {
+        let count = self.positions.len() / 3;
+        let mut vertices = Vec::with_capacity(count * INTERLEAVED_STRIDE);
+        for i in 0..count {
+            vertices.extend_from_slice(&self.positions[3 * i..3 * i + 3]);
+            match self.normals.get(3 * i..3 * i + 3) {
+                Some(normal) => vertices.extend_from_slice(normal),
+                None => vertices.extend_from_slice(&[0.0; 3]),
+            }
+            match self.tex_coords.get(2 * i..2 * i + 2) {
+                Some(tex_coord) => vertices.extend_from_slice(tex_coord),
+                None => vertices.extend_from_slice(&[0.0; 2]),
+            }
+        }
+        (vertices, self.indices.clone())
+    }
+}
+
 #[derive(Debug, Clone, Default)]
 pub struct Material {
     pub name: String,
     pub ambient: [f32; 3],
     pub diffuse: [f32; 3],
     pub specular: [f32; 3],
+    pub emissive: [f32; 3],
     pub shininess: f32,
     pub texture: Option<String>,
     pub unknown_param: HashMap<String, String>,
 }
 
+/// How the winding of the imported triangles is fixed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Winding {
+    /// Triangles are kept as written in the file.
+    #[default]
+    Keep,
+    /// Every triangle is reversed.
+    Flip,
+    /// Triangles are reversed when most of them face away from their `vn`.
+    Auto,
+}
+
 /// Some vertices may not have texture coordinates or normals, 0 is used to
 /// indicate this as OBJ indices begin at 1
 #[derive(Hash, Eq, PartialEq, PartialOrd, Ord, Debug, Copy, Clone)]
@@ -84,24 +233,42 @@ impl VertexIndices {
         tex_sz: usize,
         norm_sz: usize,
     ) -> Option<VertexIndices> {
+        let components = face_str.split('/').collect::<Vec<_>>();
+        // Only `v`, `v/vt`, `v//vn` and `v/vt/vn` are valid, the texture
+        // coordinate being the only index that may be left empty.
+        let valid = match components.as_slice() {
+            [v] => !v.is_empty(),
+            [v, vt] => !v.is_empty() && !vt.is_empty(),
+            [v, _, vn] => !v.is_empty() && !vn.is_empty(),
+            _ => false,
+        };
+        if !valid {
+            return None;
+        }
+
         let mut indices = [MISSING_INDEX; 3];
-        for i in face_str.split('/').enumerate() {
+        let sizes = [pos_sz, tex_sz, norm_sz];
+        for i in components.into_iter().enumerate() {
             // Catch case of v//vn where we'll find an empty string in one of our splits
             // since there are no texcoords for the mesh.
             if !i.1.is_empty() {
                 match isize::from_str_radix(i.1, 10) {
                     Ok(x) => {
+                        // Invalid number of elements for a face
+                        let size = *sizes.get(i.0)? as isize;
                         // Handle relative indices
-                        *indices.get_mut(i.0)? = if x < 0 {
-                            match i.0 {
-                                0 => (pos_sz as isize + x) as _,
-                                1 => (tex_sz as isize + x) as _,
-                                2 => (norm_sz as isize + x) as _,
-                                _ => return None, // Invalid number of elements for a face
+                        let index = if x < 0 { size.checked_add(x)? } else { x - 1 };
+                        // Reject indices before the first or after the last element,
+                        // including 0 which isn't a valid OBJ index. Indices are
+                        // resolved against the data read so far, so a face can't
+                        // reference a vertex defined after it.
+                        if !(0..size).contains(&index) {
+                            if index >= size {
+                                log::warn!("Face references undefined vertex data: {}", face_str);
                             }
-                        } else {
-                            (x - 1) as _
-                        };
+                            return None;
+                        }
+                        indices[i.0] = index as usize;
                     }
                     Err(_) => return None,
                 }
@@ -125,27 +292,94 @@ enum Face {
     Polygon(Vec<VertexIndices>),
 }
 
+/// Parse a number, scientific notation included. With `decimal_comma`,
+/// commas are accepted as decimal separators, e.g. `0,5`.
+fn parse_number(value: &str, decimal_comma: bool) -> Option<f32> {
+    if decimal_comma {
+        value.replace(',', ".").parse().ok()
+    } else {
+        value.parse().ok()
+    }
+}
+
+/// Parse `size` numbers and append them to `target`. Trailing numbers are
+/// ignored, with a warning unless there are as many as one of the `optional`
+/// counts the format allows.
+///
+/// Returns `false` if a value is invalid or missing, in which case `0.0` is
+/// appended in its place.
 fn parse_vertex_data(
     words: &mut std::str::SplitWhitespace,
     target: &mut Vec<f32>,
     size: usize,
+    optional: &[usize],
     line: &str,
     log_prefix: &str,
-) {
+    decimal_comma: bool,
+) -> bool {
     let old_len = target.len();
+    let mut valid = true;
     for value in words.by_ref().take(size) {
-        target.push(value.parse().unwrap_or_else(|_| {
+        target.push(parse_number(value, decimal_comma).unwrap_or_else(|| {
             log::warn!("Invalid {} vertex: {}", log_prefix, line);
+            valid = false;
             f32::default()
         }));
     }
-    if target.len() - old_len != size {
-        log::warn!("Invalid {} vertex: {}", log_prefix, line);
-        target.truncate(old_len);
+    if target.len() - old_len < size {
+        log::warn!("Missing values in {} vertex: {}", log_prefix, line);
+        target.resize(old_len + size, f32::default());
+        valid = false;
+    }
+
+    let extra: Vec<&str> = words.collect();
+    let numbers = extra
+        .iter()
+        .all(|v| parse_number(v, decimal_comma).is_some());
+    if !extra.is_empty() && (!numbers || !optional.contains(&extra.len())) {
+        log::warn!("Ignored extra values in {} vertex: {}", log_prefix, line);
+    }
+    valid
+}
+
+/// Parse the `translate x y z`, `rotate x y z` and `scale x y z` parts of a
+/// transform, the scale also accepting a single uniform factor. Parsing stops
+/// at the first invalid part.
+fn parse_transform(mut words: SplitWhitespace, line: &str, decimal_comma: bool) -> Transform {
+    let mut transform = Transform::default();
+    while let Some(word) = words.next() {
+        let target = match word {
+            "translate" => &mut transform.translation,
+            "rotate" => &mut transform.rotation,
+            "scale" => &mut transform.scale,
+            _ => {
+                log::warn!("Invalid transform: {}", line);
+                break;
+            }
+        };
+        let values = words
+            .clone()
+            .map_while(|w| parse_number(w, decimal_comma))
+            .take(3)
+            .collect::<Vec<_>>();
+        for _ in &values {
+            words.next();
+        }
+        match values[..] {
+            [x, y, z] => *target = [x, y, z],
+            [factor] if word == "scale" => *target = [factor; 3],
+            _ => {
+                log::warn!("Invalid transform: {}", line);
+                break;
+            }
+        }
     }
+    transform
 }
 
 /// Parse vertex indices for a face and append it to the list of faces passed.
+/// The sizes are those of the vertex data read before the face, which is all
+/// it may reference.
 ///
 /// Returns `false` if an error occured parsing the face.
 fn parse_face(
@@ -176,6 +410,8 @@ fn parse_face(
 /// Add a vertex to a mesh by either re-using an existing index (e.g. it's in
 /// the `index_map`) or appending the position, texcoord and normal as
 /// appropriate and creating a new vertex.
+///
+/// Returns the index of the vertex in the mesh.
 fn add_vertex(
     mesh: &mut Mesh,
     index_map: &mut HashMap<VertexIndices, u32>,
@@ -183,9 +419,9 @@ fn add_vertex(
     pos: &[f32],
     normal: &[f32],
     tex_coord: &[f32],
-) -> Result<(), ObjError> {
+) -> Result<u32, ObjError> {
     match index_map.get(vert) {
-        Some(&i) => mesh.indices.push(i),
+        Some(&i) => Ok(i),
         None => {
             let v = vert.v;
             if v.saturating_mul(3).saturating_add(2) >= pos.len() {
@@ -211,12 +447,31 @@ fn add_vertex(
                 mesh.normals.push(normal[vn * 3]);
                 mesh.normals.push(normal[vn * 3 + 1]);
                 mesh.normals.push(normal[vn * 3 + 2]);
+            } else if !normal.is_empty() {
+                // Keep the normals aligned with the positions, the missing
+                // ones are generated by `fill_missing_normals`.
+                mesh.normals.extend([0.0; 3]);
             }
             let next = index_map.len() as u32;
-            mesh.indices.push(next);
             index_map.insert(*vert, next);
+            Ok(next)
         }
     }
+}
+
+/// Add the three vertices of a triangle to the mesh indices.
+fn add_triangle(
+    mesh: &mut Mesh,
+    index_map: &mut HashMap<VertexIndices, u32>,
+    verts: [&VertexIndices; 3],
+    pos: &[f32],
+    normal: &[f32],
+    tex_coord: &[f32],
+) -> Result<(), ObjError> {
+    for vert in verts {
+        let i = add_vertex(mesh, index_map, vert, pos, normal, tex_coord)?;
+        mesh.indices.push(i);
+    }
     Ok(())
 }
 
@@ -227,6 +482,7 @@ fn export_faces(
     normal: &[f32],
     faces: &[Face],
     material_id: Option<usize>,
+    winding: Winding,
 ) -> Result<Mesh, ObjError> {
     let mut index_map: HashMap<VertexIndices, u32> = HashMap::new();
     let mut mesh = Mesh {
@@ -236,42 +492,39 @@ fn export_faces(
 
     for face in faces {
         match *face {
-            Face::Point(_) => {
-                log::warn!("Point faces are not supported");
+            Face::Point(ref a) => {
+                let i = add_vertex(&mut mesh, &mut index_map, a, pos, normal, tex_coords)?;
+                mesh.point_indices.push(i);
             },
             Face::Line(_, _) => {
                 log::warn!("Line faces are not supported");
             },
             Face::Triangle(ref a, ref b, ref c) => {
-                add_vertex(&mut mesh, &mut index_map, a, pos, normal, tex_coords)?;
-                add_vertex(&mut mesh, &mut index_map, b, pos, normal, tex_coords)?;
-                add_vertex(&mut mesh, &mut index_map, c, pos, normal, tex_coords)?;
+                add_triangle(&mut mesh, &mut index_map, [a, b, c], pos, normal, tex_coords)?;
             },
             Face::Quad(ref a, ref b, ref c, ref d) => {
-                add_vertex(&mut mesh, &mut index_map, a, pos, normal, tex_coords)?;
-                add_vertex(&mut mesh, &mut index_map, b, pos, normal, tex_coords)?;
-                add_vertex(&mut mesh, &mut index_map, c, pos, normal, tex_coords)?;
-
-                add_vertex(&mut mesh, &mut index_map, a, pos, normal, tex_coords)?;
-                add_vertex(&mut mesh, &mut index_map, c, pos, normal, tex_coords)?;
-                add_vertex(&mut mesh, &mut index_map, d, pos, normal, tex_coords)?;
+                add_triangle(&mut mesh, &mut index_map, [a, b, c], pos, normal, tex_coords)?;
+                add_triangle(&mut mesh, &mut index_map, [a, c, d], pos, normal, tex_coords)?;
             },
             Face::Polygon(ref indices) => {
                 let mut iter = indices.iter();
                 let first = iter.next().unwrap();
                 let second = iter.next().unwrap();
                 for vert in iter {
-                    add_vertex(&mut mesh, &mut index_map, first, pos, normal, tex_coords)?;
-                    add_vertex(&mut mesh, &mut index_map, second, pos, normal, tex_coords)?;
-                    add_vertex(&mut mesh, &mut index_map, vert, pos, normal, tex_coords)?;
+                    add_triangle(
+                        &mut mesh,
+                        &mut index_map,
+                        [first, second, vert],
+                        pos,
+                        normal,
+                        tex_coords,
+                    )?;
                 }
 
                 let a = indices.first().ok_or(ObjError::InvalidPolygon)?;
                 let mut b = indices.get(1).ok_or(ObjError::InvalidPolygon)?;
                 for c in indices.iter().skip(2) {
-                    add_vertex(&mut mesh, &mut index_map, a, pos, normal, tex_coords)?;
-                    add_vertex(&mut mesh, &mut index_map, b, pos, normal, tex_coords)?;
-                    add_vertex(&mut mesh, &mut index_map, c, pos, normal, tex_coords)?;
+                    add_triangle(&mut mesh, &mut index_map, [a, b, c], pos, normal, tex_coords)?;
                     b = c;
                 }
 
@@ -279,11 +532,399 @@ fn export_faces(
         }
     }
 
+    let flip = match winding {
+        Winding::Keep => false,
+        Winding::Flip => true,
+        Winding::Auto => is_inverted(&mesh),
+    };
+    if flip {
+        for triangle in mesh.indices.chunks_exact_mut(3) {
+            triangle.swap(1, 2);
+        }
+    }
+
+    if !mesh.normals.is_empty() {
+        fill_missing_normals(&mut mesh);
+    }
+
     Ok(mesh)
 }
 
+/// Normal of a triangle of `mesh`, its length being twice the triangle area.
+fn triangle_normal(mesh: &Mesh, triangle: &[u32]) -> [f32; 3] {
+    let position = |i: u32| {
+        let i = i as usize * 3;
+        [
+            mesh.positions[i],
+            mesh.positions[i + 1],
+            mesh.positions[i + 2],
+        ]
+    };
+    let [a, b, c] = [0, 1, 2].map(|i| position(triangle[i]));
+    let ab = [b[0] - a[0], b[1] - a[1], b[2] - a[2]];
+    let ac = [c[0] - a[0], c[1] - a[1], c[2] - a[2]];
+    [
+        ab[1] * ac[2] - ab[2] * ac[1],
+        ab[2] * ac[0] - ab[0] * ac[2],
+        ab[0] * ac[1] - ab[1] * ac[0],
+    ]
+}
+
+/// Whether most triangles of `mesh` face away from the normals provided for
+/// their vertices. Vertices without normals are ignored.
+fn is_inverted(mesh: &Mesh) -> bool {
+    if mesh.normals.is_empty() {
+        return false;
+    }
+    let (mut agree, mut disagree) = (0, 0);
+    for triangle in mesh.indices.chunks_exact(3) {
+        let normal = triangle_normal(mesh, triangle);
+        let dot: f32 = triangle
+            .iter()
+            .flat_map(|&i| &mesh.normals[i as usize * 3..i as usize * 3 + 3])
+            .zip(normal.iter().cycle())
+            .map(|(n, g)| n * g)
+            .sum();
+        if dot > 0.0 {
+            agree += 1;
+        } else if dot < 0.0 {
+            disagree += 1;
+        }
+    }
+    disagree > agree
+}
+
+/// Replaces the zero normals of `mesh` by the average of the normals of the
+/// triangles using the vertex, weighted by their area. Provided normals are
+/// left untouched.
+fn fill_missing_normals(mesh: &mut Mesh) {
+    let missing = mesh
+        .normals
+        .chunks(3)
+        .map(|n| n.iter().all(|&c| c == 0.0))
+        .collect::<Vec<_>>();
+    if !missing.contains(&true) {
+        return;
+    }
+
+    let mut generated = vec![[0.0f32; 3]; missing.len()];
+    for triangle in mesh.indices.chunks_exact(3) {
+        let normal = triangle_normal(mesh, triangle);
+        for &index in triangle {
+            for (g, n) in generated[index as usize].iter_mut().zip(normal) {
+                *g += n;
+            }
+        }
+    }
+
+    for (i, normal) in generated.into_iter().enumerate() {
+        let length = normal.iter().map(|c| c * c).sum::<f32>().sqrt();
+        if missing[i] && length > 0.0 {
+            for (n, g) in mesh.normals[i * 3..i * 3 + 3].iter_mut().zip(normal) {
+                *n = g / length;
+            }
+        }
+    }
+}
+
+/// Scales the normals of `mesh` to unit length, as some exporters write them
+/// unnormalized. Zero normals are left as is.
+pub fn normalize_normals(mesh: &mut Mesh) {
+    for normal in mesh.normals.chunks_exact_mut(3) {
+        let length = normal.iter().map(|c| c * c).sum::<f32>().sqrt();
+        if length > 0.0 {
+            normal.iter_mut().for_each(|c| *c /= length);
+        }
+    }
+}
+
+/// Returns a copy of `mesh` with smooth normals, except across the edges
+/// whose faces meet at more than `angle_threshold_deg`, which stay sharp.
+///
+/// Each corner averages the normals of the faces around its position within
+/// the threshold of its own face, weighted by their angle at that position so
+/// the way faces are triangulated doesn't matter. Corners of a vertex
+/// ending up with different normals get their own copy of the vertex.
+pub fn generate_normals(mesh: &Mesh, angle_threshold_deg: f32) -> Mesh {
+    let vertex_count = mesh.positions.len() / 3;
+    let has_tex_coords = mesh.tex_coords.len() == vertex_count * 2;
+    let has_colors = mesh.colors.len() == vertex_count * 3;
+    let has_lightmap = mesh.lightmap_tex_coords.len() == vertex_count * 2;
+    let min_cos = angle_threshold_deg.to_radians().cos();
+
+    let unit = |n: [f32; 3]| {
+        let length = n.iter().map(|c| c * c).sum::<f32>().sqrt();
+        if length > 0.0 {
+            n.map(|c| c / length)
+        } else {
+            n
+        }
+    };
+    let dot = |a: [f32; 3], b: [f32; 3]| a[0] * b[0] + a[1] * b[1] + a[2] * b[2];
+    let position = |index: u32| {
+        let i = index as usize * 3;
+        [
+            mesh.positions[i],
+            mesh.positions[i + 1],
+            mesh.positions[i + 2],
+        ]
+    };
+
+    let face_normals = mesh
+        .indices
+        .chunks_exact(3)
+        .map(|triangle| unit(triangle_normal(mesh, triangle)))
+        .collect::<Vec<_>>();
+    // Angle of each corner of the faces.
+    let corner_angles = mesh
+        .indices
+        .chunks_exact(3)
+        .map(|triangle| {
+            [0, 1, 2].map(|corner| {
+                let p = position(triangle[corner]);
+                let [a, b] = [1, 2].map(|offset| {
+                    let q = position(triangle[(corner + offset) % 3]);
+                    unit([q[0] - p[0], q[1] - p[1], q[2] - p[2]])
+                });
+                dot(a, b).clamp(-1.0, 1.0).acos()
+            })
+        })
+        .collect::<Vec<_>>();
+
+    // Corners around each position, vertices split by texture coordinates
+    // still being the same point.
+    let position_key = |index: u32| position(index).map(f32::to_bits);
+    let mut corners_at: HashMap<[u32; 3], Vec<(usize, usize)>> = HashMap::new();
+    for (face, triangle) in mesh.indices.chunks_exact(3).enumerate() {
+        for (corner, &index) in triangle.iter().enumerate() {
+            corners_at
+                .entry(position_key(index))
+                .or_default()
+                .push((face, corner));
+        }
+    }
+
+    let mut generated = Mesh {
+        material_id: mesh.material_id,
+        ..Default::default()
+    };
+    let mut vertices: HashMap<(u32, [u32; 3]), u32> = HashMap::new();
+    let mut add = |generated: &mut Mesh, index: u32, normal: [f32; 3]| {
+        let key = (index, normal.map(f32::to_bits));
+        *vertices.entry(key).or_insert_with(|| {
+            let i = index as usize;
+            generated
+                .positions
+                .extend_from_slice(&mesh.positions[i * 3..i * 3 + 3]);
+            if has_tex_coords {
+                generated
+                    .tex_coords
+                    .extend_from_slice(&mesh.tex_coords[i * 2..i * 2 + 2]);
+            }
+            if has_colors {
+                generated
+                    .colors
+                    .extend_from_slice(&mesh.colors[i * 3..i * 3 + 3]);
+            }
+            if has_lightmap {
+                generated
+                    .lightmap_tex_coords
+                    .extend_from_slice(&mesh.lightmap_tex_coords[i * 2..i * 2 + 2]);
+            }
+            generated.normals.extend_from_slice(&normal);
+            (generated.positions.len() / 3 - 1) as u32
+        })
+    };
+
+    for (face, triangle) in mesh.indices.chunks_exact(3).enumerate() {
+        for &index in triangle {
+            let mut normal = [0.0; 3];
+            for &(other, corner) in &corners_at[&position_key(index)] {
+                if dot(face_normals[face], face_normals[other]) >= min_cos {
+                    let weight = corner_angles[other][corner];
+                    for (n, c) in normal.iter_mut().zip(face_normals[other]) {
+                        *n += c * weight;
+                    }
+                }
+            }
+            let index = add(&mut generated, index, unit(normal));
+            generated.indices.push(index);
+        }
+    }
+    for &index in &mesh.point_indices {
+        let index = add(&mut generated, index, [0.0; 3]);
+        generated.point_indices.push(index);
+    }
+
+    generated
+}
+
+/// Returns the material of `mesh`, if it has one.
+pub fn material_for<'a>(materials: &'a [Material], mesh: &Mesh) -> Option<&'a Material> {
+    materials.get(mesh.material_id?)
+}
+
+/// Adds the materials of a library, replacing those with the same name so the
+/// last definition wins.
+fn add_materials(materials: &mut Vec<Material>, library: Vec<Material>) {
+    for material in library {
+        match materials.iter_mut().find(|m| m.name == material.name) {
+            Some(existing) => {
+                log::warn!("Material {} is defined more than once", material.name);
+                *existing = material;
+            }
+            None => materials.push(material),
+        }
+    }
+}
+
+/// Parse a color, e.g. `Kd 1.0 0.5 0.0`, falling back to `default` if invalid.
+fn parse_color(
+    words: &mut SplitWhitespace,
+    line: &str,
+    default: [f32; 3],
+    decimal_comma: bool,
+) -> [f32; 3] {
+    let mut color = Vec::new();
+    if !parse_vertex_data(words, &mut color, 3, &[], line, "color", decimal_comma) {
+        return default;
+    }
+    color.try_into().unwrap_or(default)
+}
+
+// Follow the MTL file format specification (https://paulbourke.net/dataformats/mtl/)
+pub fn load_mtl<F>(file_name: F, decimal_comma: bool) -> Result<Vec<Material>, ObjError>
+where
+    F: AsRef<Path> + std::fmt::Debug,
+{
+    let file = File::open(file_name.as_ref()).map_err(|error| {
+        log::error!("Failed to open file {:?} due to {}", file_name, error);
+        ObjError::OpenFileFailed
+    })?;
+    let mut materials = load_mtl_from_reader(BufReader::new(file), decimal_comma)?;
+
+    // Textures are relative to the MTL file rather than the working directory.
+    let directory = file_name.as_ref().parent().unwrap_or(Path::new(""));
+    for texture in materials.iter_mut().filter_map(|m| m.texture.as_mut()) {
+        *texture = resolve_path(directory, texture);
+    }
+    Ok(materials)
+}
+
+/// Resolves `path` against `directory`, unless it is absolute.
+fn resolve_path(directory: &Path, path: &str) -> String {
+    directory.join(path).to_string_lossy().into_owned()
+}
+
+/// Parse the materials of an MTL file from `reader`.
+pub fn load_mtl_from_reader<R: BufRead>(
+    reader: R,
+    decimal_comma: bool,
+) -> Result<Vec<Material>, ObjError> {
+    let mut materials: Vec<Material> = Vec::new();
+
+    for line in reader.lines() {
+        let (line, mut words) = match line {
+            Ok(ref line) => (line.trim(), line.split_whitespace()),
+            Err(err) => {
+                log::error!("Failed to read line due to {}", err);
+                return Err(ObjError::ParseFailed);
+            }
+        };
+
+        // Comments may or may not have a space after the `#`.
+        if line.starts_with('#') {
+            continue;
+        }
+
+        let key = match words.next() {
+            None => continue,
+            Some("newmtl") => {
+                materials.push(Material {
+                    name: line["newmtl".len()..].trim().to_owned(),
+                    diffuse: [1.0; 3],
+                    ..Default::default()
+                });
+                continue;
+            }
+            Some(key) => key,
+        };
+
+        let Some(material) = materials.last_mut() else {
+            log::warn!("Material parameter outside of a material: {}", line);
+            continue;
+        };
+        match key {
+            "Ka" => {
+                material.ambient = parse_color(&mut words, line, material.ambient, decimal_comma)
+            }
+            "Kd" => {
+                material.diffuse = parse_color(&mut words, line, material.diffuse, decimal_comma)
+            }
+            "Ks" => {
+                material.specular = parse_color(&mut words, line, material.specular, decimal_comma)
+            }
+            "Ke" => {
+                material.emissive = parse_color(&mut words, line, material.emissive, decimal_comma)
+            }
+            "Ns" => match words.next().and_then(|w| parse_number(w, decimal_comma)) {
+                Some(shininess) => material.shininess = shininess,
+                _ => log::warn!("Invalid shininess: {}", line),
+            },
+            "map_Kd" => material.texture = Some(line[key.len()..].trim().to_owned()),
+            _ => {
+                let value = line[key.len()..].trim().to_owned();
+                material.unknown_param.insert(key.to_owned(), value);
+            }
+        }
+    }
+
+    Ok(materials)
+}
+
+/// Files above this size log their loading progress.
+const PROGRESS_MIN_SIZE: u64 = 16 * 1024 * 1024;
+/// Percentage of the file read between two progress messages.
+const PROGRESS_STEP: u32 = 10;
+
+/// Percentage of a file of `total` bytes that `read` bytes represent.
+pub fn progress_percent(read: u64, total: u64) -> u32 {
+    if total == 0 {
+        return 100;
+    }
+    (read.min(total) * 100 / total) as u32
+}
+
+/// Reader logging how much of a file of `total` bytes was read, every
+/// `PROGRESS_STEP` percent.
+struct ProgressReader<R> {
+    inner: R,
+    read: u64,
+    total: u64,
+    next_percent: u32,
+}
+
+impl<R: Read> Read for ProgressReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let count = self.inner.read(buf)?;
+        self.read += count as u64;
+        let percent = progress_percent(self.read, self.total);
+        if percent >= self.next_percent && percent < 100 {
+            log::info!("Loading OBJ: {}%", percent);
+            self.next_percent = percent - percent % PROGRESS_STEP + PROGRESS_STEP;
+        }
+        Ok(count)
+    }
+}
+
 // Follow the Wavefront .obj file format specification (https://paulbourke.net/dataformats/obj/)
-pub fn load_obj<F>(file_name: F) -> Result<Vec<Model>, ObjError>
+//
+// Positions that fail to parse are an error rather than silently becoming 0.
+pub fn load_obj<F>(
+    file_name: F,
+    decimal_comma: bool,
+    winding: Winding,
+) -> Result<(Vec<Model>, Vec<Material>), ObjError>
 where
     F: AsRef<Path> + std::fmt::Debug,
 {
@@ -291,12 +932,55 @@ where
         log::error!("Failed to open file {:?} due to {}", file_name, error);
         ObjError::OpenFileFailed
     })?;
-    let reader = BufReader::new(file);
+    // Material libraries are relative to the OBJ file.
+    let directory = file_name.as_ref().parent().unwrap_or(Path::new(""));
+    let size = file.metadata().map(|metadata| metadata.len()).unwrap_or(0);
+    if size < PROGRESS_MIN_SIZE {
+        return load_obj_from_reader(BufReader::new(file), directory, decimal_comma, winding);
+    }
+    let reader = ProgressReader {
+        inner: file,
+        read: 0,
+        total: size,
+        next_percent: PROGRESS_STEP,
+    };
+    load_obj_from_reader(BufReader::new(reader), directory, decimal_comma, winding)
+}
 
-    // let mut materials = Vec::new();
+/// Parse the models of an OBJ file from `reader`, loading the material
+/// libraries it references from `directory`.
+pub fn load_obj_from_reader<R: BufRead>(
+    reader: R,
+    directory: &Path,
+    decimal_comma: bool,
+    winding: Winding,
+) -> Result<(Vec<Model>, Vec<Material>), ObjError> {
+    let load_library = |library: &str| load_mtl(directory.join(library), decimal_comma);
+    load_obj_from_reader_with(reader, load_library, decimal_comma, winding)
+}
+
+/// Parse the models of an OBJ file from `reader`, loading the material
+/// libraries it references, by name, with `load_library`.
+pub fn load_obj_from_reader_with<R, L>(
+    reader: R,
+    mut load_library: L,
+    decimal_comma: bool,
+    winding: Winding,
+) -> Result<(Vec<Model>, Vec<Material>), ObjError>
+where
+    R: BufRead,
+    L: FnMut(&str) -> Result<Vec<Material>, ObjError>,
+{
+    let mut materials: Vec<Material> = Vec::new();
+    let mut current_material: Option<usize> = None;
     let mut models: Vec<Model> = Vec::new();
 
     let mut current_name = "undefined".to_owned();
+    let mut current_group = "undefined".to_owned();
+    let mut current_transform = Transform::default();
+    // A transform met once the current object has faces places the next one.
+    let mut next_transform: Option<Transform> = None;
+    let mut object_has_faces = false;
 
     let mut current_pos: Vec<f32> = Vec::new();
     let mut current_normals: Vec<f32> = Vec::new();
@@ -312,46 +996,153 @@ where
             }
         };
 
+        // Comments may or may not have a space after the `#`, `# scop:transform`
+        // ones place the current object until its first face, then the next
+        // one, so they may come before or after its `o` line.
+        if let Some(comment) = line.strip_prefix('#') {
+            let mut words = comment.split_whitespace();
+            if words.next() == Some("scop:transform") {
+                let transform = parse_transform(words, line, decimal_comma);
+                if object_has_faces || !current_faces.is_empty() {
+                    next_transform = Some(transform);
+                } else {
+                    current_transform = transform;
+                }
+            }
+            continue;
+        }
+
         match words.next() {
-            Some("#") | None => continue,
-            Some("v") => parse_vertex_data(&mut words, &mut current_pos, 3, line, "position"),
-            Some("vn") => parse_vertex_data(&mut words, &mut current_normals, 3, line, "normal"),
+            None => continue,
+            Some("v") => {
+                if !parse_vertex_data(
+                    &mut words,
+                    &mut current_pos,
+                    3,
+                    // A weight, or the vertex color some exporters write.
+                    &[1, 3],
+                    line,
+                    "position",
+                    decimal_comma,
+                ) {
+                    return Err(ObjError::VertexParseError);
+                }
+            }
+            Some("vn") => {
+                parse_vertex_data(
+                    &mut words,
+                    &mut current_normals,
+                    3,
+                    &[],
+                    line,
+                    "normal",
+                    decimal_comma,
+                );
+            }
             Some("vt") => {
-                parse_vertex_data(&mut words, &mut current_tex_coords, 2, line, "texture")
+                parse_vertex_data(
+                    &mut words,
+                    &mut current_tex_coords,
+                    2,
+                    // A depth for 3D textures.
+                    &[1],
+                    line,
+                    "texture",
+                    decimal_comma,
+                );
+            }
+            Some("p") => {
+                for point in words {
+                    match VertexIndices::parse(
+                        point,
+                        current_pos.len() / 3,
+                        current_tex_coords.len() / 2,
+                        current_normals.len() / 3,
+                    ) {
+                        Some(v) => current_faces.push(Face::Point(v)),
+                        None => return Err(ObjError::FaceParseError),
+                    }
+                }
             }
             Some("f") | Some("l") => {
                 if !parse_face(
                     words,
                     &mut current_faces,
                     current_pos.len() / 3,
-                    current_normals.len() / 3,
                     current_tex_coords.len() / 2,
+                    current_normals.len() / 3,
                 ) {
                     return Err(ObjError::FaceParseError);
                 }
             }
             Some("o") | Some("g") => {
                 if !current_faces.is_empty() {
+                    object_has_faces = true;
                     models.push(Model::new(
-                        current_name,
+                        current_name.clone(),
+                        current_group.clone(),
                         export_faces(
                             &current_pos,
                             &current_tex_coords,
                             &current_normals,
                             &current_faces,
-                            None,
+                            current_material,
+                            winding,
                         )?,
+                        current_transform,
                     ));
                     current_faces.clear();
                 }
                 let size = line.chars().next().unwrap().len_utf8();
-                current_name = line[size..].trim().to_owned();
-                if current_name.is_empty() {
-                    current_name = "undefined".to_owned();
+                let mut name = line[size..].trim().to_owned();
+                if name.is_empty() {
+                    name = "undefined".to_owned();
+                }
+                if line.starts_with('o') {
+                    current_name = name;
+                    // Groups and transforms don't carry over to the next object.
+                    current_group = "undefined".to_owned();
+                    if object_has_faces {
+                        current_transform = next_transform.take().unwrap_or_default();
+                    }
+                    object_has_faces = false;
+                } else {
+                    current_group = name;
                 }
             }
             Some("mtllib") => {
-                log::trace!("mtllib not implemented");
+                for library in words {
+                    match load_library(library) {
+                        Ok(library) => add_materials(&mut materials, library),
+                        Err(err) => log::warn!("Failed to load material library: {}", err),
+                    }
+                }
+            }
+            Some("usemtl") => {
+                let name = line["usemtl".len()..].trim();
+                let material = materials.iter().position(|m| m.name == name);
+                if material.is_none() {
+                    log::warn!("Unknown material: {}", name);
+                }
+                // Faces using different materials go to different meshes.
+                if material != current_material && !current_faces.is_empty() {
+                    object_has_faces = true;
+                    models.push(Model::new(
+                        current_name.clone(),
+                        current_group.clone(),
+                        export_faces(
+                            &current_pos,
+                            &current_tex_coords,
+                            &current_normals,
+                            &current_faces,
+                            current_material,
+                            winding,
+                        )?,
+                        current_transform,
+                    ));
+                    current_faces.clear();
+                }
+                current_material = material;
             }
             Some(_) => {
                 log::warn!("Unknown line: {}", line);
@@ -361,14 +1152,630 @@ where
 
     models.push(Model::new(
         current_name,
+        current_group,
         export_faces(
             &current_pos,
             &current_tex_coords,
             &current_normals,
             &current_faces,
-            None,
+            current_material,
+            winding,
         )?,
+        current_transform,
     ));
+    if next_transform.is_some() {
+        log::warn!("Transform after the faces of the last object, ignored");
+    }
+
+    Ok((models, materials))
+}
+
+/// Drops the triangles of `mesh` repeating a vertex or without area, which
+/// draw nothing and have no normal. Returns how many were dropped.
+pub fn remove_degenerate_triangles(mesh: &mut Mesh) -> usize {
+    let triangles = mesh.indices.len() / 3;
+    let indices: Vec<u32> = mesh
+        .indices
+        .chunks_exact(3)
+        .filter(|t| t[0] != t[1] && t[1] != t[2] && t[2] != t[0])
+        .filter(|t| triangle_normal(mesh, t) != [0.0; 3])
+        .flatten()
+        .copied()
+        .collect();
+    mesh.indices = indices;
+    triangles - mesh.indices.len() / 3
+}
+
+/// Simplifies a mesh by vertex clustering so it has at most `target_tris`
+/// triangles.
+///
+/// Vertices are snapped to a uniform grid spanning the mesh bounding box and
+/// each occupied cell is collapsed to the average position of its vertices.
+/// Triangles collapsing to a line or a point are dropped. The grid gets
+/// coarser until the target is reached.
+pub fn decimate(mesh: &Mesh, target_tris: usize) -> Mesh {
+    let vertex_count = mesh.positions.len() / 3;
+    if mesh.indices.len() / 3 <= target_tris || vertex_count == 0 {
+        return mesh.clone();
+    }
+
+    let mut min = [f32::MAX; 3];
+    let mut max = [f32::MIN; 3];
+    for pos in mesh.positions.chunks_exact(3) {
+        for axis in 0..3 {
+            min[axis] = min[axis].min(pos[axis]);
+            max[axis] = max[axis].max(pos[axis]);
+        }
+    }
+
+    // A surface crossing an n^3 grid touches about n^2 cells, each giving
+    // about two triangles.
+    let mut resolution = ((target_tris as f32 / 2.0).sqrt() as usize).max(1);
+    loop {
+        let decimated = cluster_vertices(mesh, &min, &max, resolution);
+        if decimated.indices.len() / 3 <= target_tris || resolution == 1 {
+            return decimated;
+        }
+        resolution = resolution * 3 / 4;
+    }
+}
+
+/// Collapses the vertices of `mesh` falling in the same cell of a
+/// `resolution`^3 grid over the `min`..`max` box.
+fn cluster_vertices(mesh: &Mesh, min: &[f32; 3], max: &[f32; 3], resolution: usize) -> Mesh {
+    let vertex_count = mesh.positions.len() / 3;
+    let has_tex_coords = mesh.tex_coords.len() == vertex_count * 2;
+    let has_normals = mesh.normals.len() == vertex_count * 3;
+    let has_colors = mesh.colors.len() == vertex_count * 3;
+    let has_lightmap = mesh.lightmap_tex_coords.len() == vertex_count * 2;
+
+    let mut cells: HashMap<[usize; 3], u32> = HashMap::new();
+    let mut remap = Vec::with_capacity(vertex_count);
+    let mut sums: Vec<([f32; 3], u32)> = Vec::new();
+    let mut decimated = Mesh {
+        material_id: mesh.material_id,
+        ..Default::default()
+    };
+
+    for (i, pos) in mesh.positions.chunks_exact(3).enumerate() {
+        let mut cell = [0; 3];
+        for axis in 0..3 {
+            let extent = max[axis] - min[axis];
+            if extent > 0.0 {
+                let t = (pos[axis] - min[axis]) / extent;
+                cell[axis] = ((t * resolution as f32) as usize).min(resolution - 1);
+            }
+        }
+
+        let index = *cells.entry(cell).or_insert_with(|| {
+            sums.push(([0.0; 3], 0));
+            if has_tex_coords {
+                decimated
+                    .tex_coords
+                    .extend_from_slice(&mesh.tex_coords[i * 2..i * 2 + 2]);
+            }
+            if has_normals {
+                decimated
+                    .normals
+                    .extend_from_slice(&mesh.normals[i * 3..i * 3 + 3]);
+            }
+            if has_colors {
+                decimated
+                    .colors
+                    .extend_from_slice(&mesh.colors[i * 3..i * 3 + 3]);
+            }
+            if has_lightmap {
+                decimated
+                    .lightmap_tex_coords
+                    .extend_from_slice(&mesh.lightmap_tex_coords[i * 2..i * 2 + 2]);
+            }
+            (sums.len() - 1) as u32
+        });
+        let (sum, count) = &mut sums[index as usize];
+        for axis in 0..3 {
+            sum[axis] += pos[axis];
+        }
+        *count += 1;
+        remap.push(index);
+    }
+
+    for (sum, count) in &sums {
+        decimated
+            .positions
+            .extend(sum.iter().map(|c| c / *count as f32));
+    }
+
+    let mut seen = HashSet::new();
+    for triangle in mesh.indices.chunks_exact(3) {
+        let [a, b, c] = [0, 1, 2].map(|i| remap[triangle[i] as usize]);
+        if a == b || b == c || a == c {
+            continue;
+        }
+        let mut key = [a, b, c];
+        key.sort_unstable();
+        if seen.insert(key) {
+            decimated.indices.extend_from_slice(&[a, b, c]);
+        }
+    }
+
+    let mut seen = HashSet::new();
+    for &point in &mesh.point_indices {
+        let index = remap[point as usize];
+        if seen.insert(index) {
+            decimated.point_indices.push(index);
+        }
+    }
+
+    decimated
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parses `text` as an OBJ file without material libraries.
+    fn parse(text: &str) -> Result<(Vec<Model>, Vec<Material>), ObjError> {
+        let no_library = |_: &str| Ok(Vec::new());
+        load_obj_from_reader_with(text.as_bytes(), no_library, false, Winding::Keep)
+    }
+
+    #[test]
+    fn point_elements() {
+        let (models, _) = parse("v 0 0 0\nv 1 0 0\nv 0 1 0\np 1 2\np 3\n").unwrap();
+        assert_eq!(models.len(), 1);
+        assert_eq!(models[0].mesh.point_indices.len(), 3);
+        assert!(models[0].mesh.indices.is_empty());
+    }
+
+    /// UV sphere of radius 1 with `rings` rings of `2 * rings` quads.
+    fn sphere(rings: usize) -> Mesh {
+        let segments = rings * 2;
+        let mut mesh = Mesh::default();
+        for ring in 0..=rings {
+            let theta = std::f32::consts::PI * ring as f32 / rings as f32;
+            for segment in 0..=segments {
+                let phi = std::f32::consts::TAU * segment as f32 / segments as f32;
+                mesh.positions.extend_from_slice(&[
+                    theta.sin() * phi.cos(),
+                    theta.cos(),
+                    theta.sin() * phi.sin(),
+                ]);
+            }
+        }
+        let stride = (segments + 1) as u32;
+        for ring in 0..rings as u32 {
+            for segment in 0..segments as u32 {
+                let a = ring * stride + segment;
+                let b = a + stride;
+                mesh.indices
+                    .extend_from_slice(&[a, b, a + 1, a + 1, b, b + 1]);
+            }
+        }
+        remove_degenerate_triangles(&mut mesh);
+        mesh
+    }
+
+    /// Smallest and largest corners of the positions of `mesh`.
+    fn bounds(mesh: &Mesh) -> ([f32; 3], [f32; 3]) {
+        let mut min = [f32::MAX; 3];
+        let mut max = [f32::MIN; 3];
+        for position in mesh.positions.chunks_exact(3) {
+            for axis in 0..3 {
+                min[axis] = min[axis].min(position[axis]);
+                max[axis] = max[axis].max(position[axis]);
+            }
+        }
+        (min, max)
+    }
+
+    #[test]
+    fn decimation_reaches_the_target_and_keeps_the_bounds() {
+        let mesh = sphere(64);
+        let target = 500;
+        assert!(mesh.indices.len() / 3 > target);
+
+        let decimated = decimate(&mesh, target);
+        let triangles = decimated.indices.len() / 3;
+        assert!(triangles <= target, "{}", triangles);
+        assert!(triangles > 0);
+
+        let (min, max) = bounds(&mesh);
+        let (decimated_min, decimated_max) = bounds(&decimated);
+        for axis in 0..3 {
+            assert!((decimated_min[axis] - min[axis]).abs() < 0.25);
+            assert!((decimated_max[axis] - max[axis]).abs() < 0.25);
+        }
+    }
+
+    #[test]
+    fn small_meshes_are_not_decimated() {
+        let mesh = sphere(4);
+        assert_eq!(decimate(&mesh, 1000).indices, mesh.indices);
+    }
+
+    #[test]
+    fn material_id_resolves_to_its_material() {
+        let materials = load_mtl_from_reader("newmtl a\nnewmtl b\n".as_bytes(), false).unwrap();
+        let mut mesh = Mesh {
+            material_id: Some(1),
+            ..Default::default()
+        };
+        assert_eq!(material_for(&materials, &mesh).unwrap().name, "b");
+        mesh.material_id = Some(2);
+        assert!(material_for(&materials, &mesh).is_none());
+        mesh.material_id = None;
+        assert!(material_for(&materials, &mesh).is_none());
+    }
+
+    #[test]
+    fn ke_is_the_emissive_color() {
+        let materials = load_mtl_from_reader("newmtl glow\nKe 1 0 0\n".as_bytes(), false).unwrap();
+        assert_eq!(materials[0].emissive, [1.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn groups_split_objects_by_material() {
+        let text = "mtllib a.mtl\n\
+            v 0 0 0\nv 1 0 0\nv 0 1 0\n\
+            o car\ng body\nusemtl red\nf 1 2 3\n\
+            g wheels\nusemtl black\nf 1 2 3\nusemtl red\nf 1 2 3\n\
+            o road\nf 1 2 3\n";
+        let library =
+            |_: &str| load_mtl_from_reader("newmtl red\nnewmtl black\n".as_bytes(), false);
+        let (models, materials) =
+            load_obj_from_reader_with(text.as_bytes(), library, false, Winding::Keep).unwrap();
+
+        let meshes: Vec<_> = models
+            .iter()
+            .map(|m| {
+                let material = material_for(&materials, &m.mesh).map(|m| m.name.as_str());
+                (m.name.as_str(), m.group.as_str(), material)
+            })
+            .collect();
+        assert_eq!(
+            meshes,
+            [
+                ("car", "body", Some("red")),
+                ("car", "wheels", Some("black")),
+                ("car", "wheels", Some("red")),
+                ("road", "undefined", Some("red")),
+            ]
+        );
+    }
 
-    Ok(models)
+    #[test]
+    fn pathological_face_indices_are_rejected() {
+        for face in [
+            "0",
+            "4",
+            "-4",
+            "-9223372036854775808",
+            "9223372036854775807",
+            "99999999999999999999",
+            "1/4",
+            "1//-4",
+            "1/1/1/1",
+            "/1",
+            "1//",
+            "x",
+            "",
+            "1.5",
+        ] {
+            assert!(VertexIndices::parse(face, 3, 3, 3).is_none(), "{:?}", face);
+        }
+    }
+
+    #[test]
+    fn relative_face_indices_resolve() {
+        let indices = VertexIndices::parse("-1/-3/2", 3, 3, 3).unwrap();
+        assert_eq!((indices.v, indices.vt, indices.vn), (2, 0, 1));
+        let indices = VertexIndices::parse("1//-1", 3, 0, 3).unwrap();
+        assert_eq!((indices.v, indices.vt, indices.vn), (0, MISSING_INDEX, 2));
+    }
+
+    #[test]
+    fn numbers_in_any_notation() {
+        assert_eq!(parse_number("1.5e3", false), Some(1500.0));
+        assert_eq!(parse_number("-0.0", false), Some(-0.0));
+        assert!(parse_number("-0.0", false).unwrap().is_sign_negative());
+        assert_eq!(parse_number("0,5", false), None);
+        assert_eq!(parse_number("0,5", true), Some(0.5));
+        assert_eq!(parse_number("1.2.3", false), None);
+    }
+
+    #[test]
+    fn malformed_positions_fail_the_load() {
+        assert!(matches!(
+            parse("v 1.0 abc 0.0\n"),
+            Err(ObjError::VertexParseError)
+        ));
+    }
+
+    #[test]
+    fn faces_with_wrong_slashes_are_rejected() {
+        for face in ["3/", "3//", "3/4/5/6", "3///4"] {
+            assert!(VertexIndices::parse(face, 6, 6, 6).is_none(), "{:?}", face);
+            let text = format!("v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 {}\n", face);
+            assert!(matches!(parse(&text), Err(ObjError::FaceParseError)));
+        }
+    }
+
+    #[test]
+    fn obj_and_mtl_from_memory() {
+        let text: &[u8] = b"v 0 0 0\nv 1 0 0\nv 0 1 0\nvt 0 0\nf 1/1 2/1 3/1\n";
+        let (models, materials) = load_obj_from_reader(
+            std::io::Cursor::new(text),
+            Path::new(""),
+            false,
+            Winding::Keep,
+        )
+        .unwrap();
+        assert!(materials.is_empty());
+        assert_eq!(models.len(), 1);
+        assert_eq!(models[0].mesh.positions.len(), 9);
+        assert_eq!(models[0].mesh.indices, [0, 1, 2]);
+
+        let mtl = std::io::Cursor::new(b"newmtl red\nKd 1 0 0\n");
+        let materials = load_mtl_from_reader(mtl, false).unwrap();
+        assert_eq!(materials[0].name, "red");
+        assert_eq!(materials[0].diffuse, [1.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn materials_of_every_library() {
+        let library = |name: &str| {
+            let text = match name {
+                "a.mtl" => "newmtl red\nKd 1 0 0\nnewmtl shared\nKd 1 1 1\n",
+                "b.mtl" => "newmtl blue\nKd 0 0 1\n",
+                "c.mtl" => "newmtl shared\nKd 0 0 0\n",
+                _ => return Err(ObjError::OpenFileFailed),
+            };
+            load_mtl_from_reader(text.as_bytes(), false)
+        };
+        let text = "mtllib a.mtl b.mtl\nmtllib c.mtl missing.mtl\n";
+        let (_, materials) =
+            load_obj_from_reader_with(text.as_bytes(), library, false, Winding::Keep).unwrap();
+
+        let names: Vec<_> = materials.iter().map(|m| m.name.as_str()).collect();
+        assert_eq!(names, ["red", "shared", "blue"]);
+        // The last definition wins.
+        assert_eq!(materials[1].diffuse, [0.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn missing_normals_are_generated_next_to_provided_ones() {
+        let text = "v 0 0 0\nv 1 0 0\nv 0 1 0\nv 1 1 0\nvn 0 0.6 0.8\n\
+            f 1//1 2//1 3//1\nf 2 4 3\n";
+        let (models, _) = parse(text).unwrap();
+        let mesh = &models[0].mesh;
+        assert_eq!(mesh.normals.len(), mesh.positions.len());
+        for (i, normal) in mesh.normals.chunks_exact(3).enumerate() {
+            let length = normal.iter().map(|c| c * c).sum::<f32>().sqrt();
+            assert!((length - 1.0).abs() < 1e-5, "{}: {:?}", i, normal);
+        }
+        // The first triangle keeps its normals, the second faces +Z.
+        for &index in &mesh.indices[..3] {
+            let i = index as usize * 3;
+            assert_eq!(mesh.normals[i..i + 3], [0.0, 0.6, 0.8]);
+        }
+        for &index in &mesh.indices[3..] {
+            let i = index as usize * 3;
+            assert_eq!(mesh.normals[i..i + 3], [0.0, 0.0, 1.0]);
+        }
+    }
+
+    /// A cube around the origin whose faces wind inward, against their
+    /// outward `vn`.
+    const INWARD_CUBE: &str = "v -1 -1 -1\nv 1 -1 -1\nv -1 1 -1\nv 1 1 -1\n\
+        v -1 -1 1\nv 1 -1 1\nv -1 1 1\nv 1 1 1\n\
+        vn -1 0 0\nvn 1 0 0\nvn 0 -1 0\nvn 0 1 0\nvn 0 0 -1\nvn 0 0 1\n\
+        f 1//1 3//1 7//1 5//1\nf 6//2 8//2 4//2 2//2\nf 5//3 6//3 2//3 1//3\n\
+        f 3//4 4//4 8//4 7//4\nf 1//5 2//5 4//5 3//5\nf 7//6 8//6 6//6 5//6\n";
+
+    /// Number of triangles of `mesh` facing away from the origin.
+    fn outward_triangles(mesh: &Mesh) -> usize {
+        mesh.indices
+            .chunks_exact(3)
+            .filter(|triangle| {
+                let normal = triangle_normal(mesh, triangle);
+                let i = triangle[0] as usize * 3;
+                let corner = &mesh.positions[i..i + 3];
+                normal.iter().zip(corner).map(|(n, c)| n * c).sum::<f32>() > 0.0
+            })
+            .count()
+    }
+
+    #[test]
+    fn reversed_winding_is_detected() {
+        let no_library = |_: &str| Ok(Vec::new());
+        let load = |winding| {
+            load_obj_from_reader_with(INWARD_CUBE.as_bytes(), no_library, false, winding)
+                .unwrap()
+                .0
+                .remove(0)
+                .mesh
+        };
+        assert_eq!(outward_triangles(&load(Winding::Keep)), 0);
+        assert_eq!(outward_triangles(&load(Winding::Flip)), 12);
+        assert_eq!(outward_triangles(&load(Winding::Auto)), 12);
+    }
+
+    #[test]
+    fn faces_cannot_reference_later_vertices() {
+        assert!(matches!(
+            parse("f 1 2 3\nv 0 0 0\nv 1 0 0\nv 0 1 0\n"),
+            Err(ObjError::FaceParseError)
+        ));
+        assert!(matches!(
+            parse("f -1 -2 -3\nv 0 0 0\nv 1 0 0\nv 0 1 0\n"),
+            Err(ObjError::FaceParseError)
+        ));
+        assert!(parse("v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\n").is_ok());
+    }
+
+    #[test]
+    fn textures_are_relative_to_the_mtl() {
+        let directory = std::env::temp_dir().join("scop_mtl_paths").join("models");
+        std::fs::create_dir_all(&directory).unwrap();
+        let mtl = directory.join("scene.mtl");
+        std::fs::write(&mtl, "newmtl a\nmap_Kd ../tex/foo.png\n").unwrap();
+
+        let materials = load_mtl(&mtl, false).unwrap();
+        let texture = materials[0].texture.as_deref().unwrap();
+        assert_eq!(Path::new(texture), directory.join("../tex/foo.png"));
+        assert!(Path::new(texture).is_absolute());
+
+        assert_eq!(
+            resolve_path(&directory, "/textures/foo.png"),
+            "/textures/foo.png"
+        );
+    }
+
+    #[test]
+    fn comments_need_no_space() {
+        let text = "#nospace\n# spaced\nv 0 0 0\n#v 1 1 1\np 1\n";
+        let (models, _) = parse(text).unwrap();
+        assert_eq!(models[0].mesh.positions, [0.0, 0.0, 0.0]);
+
+        // Transform comments are read without a space as well.
+        let text = "#scop:transform translate 1 2 3\nv 0 0 0\np 1\n";
+        let (models, _) = parse(text).unwrap();
+        assert_eq!(models[0].transform.translation, [1.0, 2.0, 3.0]);
+    }
+
+    #[test]
+    fn transforms_place_their_own_object() {
+        // Before the `o` line of the first object, then after the faces of
+        // the first object and before the `o` line of the second.
+        let text = "#scop:transform translate 1 0 0\no first\nv 0 0 0\np 1\n\
+                    #scop:transform translate 0 2 0\no second\np 1\n\
+                    o third\n#scop:transform translate 0 0 3\np 1\n\
+                    o fourth\np 1\n";
+        let (models, _) = parse(text).unwrap();
+        let translations: Vec<_> = models
+            .iter()
+            .map(|model| (model.name.as_str(), model.transform.translation))
+            .collect();
+        assert_eq!(
+            translations,
+            [
+                ("first", [1.0, 0.0, 0.0]),
+                ("second", [0.0, 2.0, 0.0]),
+                ("third", [0.0, 0.0, 3.0]),
+                ("fourth", [0.0, 0.0, 0.0]),
+            ]
+        );
+    }
+
+    /// The cube of `INWARD_CUBE` turned outward, without its normals.
+    fn cube() -> Mesh {
+        let no_library = |_: &str| Ok(Vec::new());
+        let (mut models, _) =
+            load_obj_from_reader_with(INWARD_CUBE.as_bytes(), no_library, false, Winding::Flip)
+                .unwrap();
+        let mut mesh = models.remove(0).mesh;
+        mesh.normals.clear();
+        mesh
+    }
+
+    #[test]
+    fn sharp_cube_edges_split_the_normals() {
+        let mesh = generate_normals(&cube(), 30.0);
+        // Four corners on each of the six faces.
+        assert_eq!(mesh.positions.len() / 3, 24);
+        for triangle in mesh.indices.chunks_exact(3) {
+            let face = triangle_normal(&mesh, triangle);
+            let length = face.iter().map(|c| c * c).sum::<f32>().sqrt();
+            for &index in triangle {
+                let i = index as usize * 3;
+                for (n, f) in mesh.normals[i..i + 3].iter().zip(face) {
+                    assert!((n - f / length).abs() < 1e-5);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn smooth_cube_corners_share_a_normal() {
+        let mesh = generate_normals(&cube(), 180.0);
+        let diagonal = 1.0 / 3.0f32.sqrt();
+        let normals = mesh.normals.chunks_exact(3);
+        for (position, normal) in mesh.positions.chunks_exact(3).zip(normals) {
+            for (p, n) in position.iter().zip(normal) {
+                assert!((n - p * diagonal).abs() < 1e-5);
+            }
+        }
+    }
+
+    #[test]
+    fn progress_is_the_share_of_bytes_read() {
+        assert_eq!(progress_percent(0, 200), 0);
+        assert_eq!(progress_percent(50, 200), 25);
+        assert_eq!(progress_percent(199, 200), 99);
+        assert_eq!(progress_percent(300, 200), 100);
+        assert_eq!(progress_percent(0, 0), 100);
+    }
+
+    #[test]
+    fn interleaved_matches_the_separate_arrays() {
+        let text = "v 0 0 0\nv 1 0 0\nv 0 1 0\nvt 0 0\nvt 1 0\nvt 0 1\nvn 0 0 1\n\
+                    f 1/1/1 2/2/1 3/3/1\n";
+        let (models, _) = parse(text).unwrap();
+        let mesh = &models[0].mesh;
+        let (vertices, indices) = mesh.interleaved();
+        assert_eq!(indices, mesh.indices);
+        assert_eq!(
+            vertices.len(),
+            mesh.positions.len() / 3 * INTERLEAVED_STRIDE
+        );
+        for (i, vertex) in vertices.chunks_exact(INTERLEAVED_STRIDE).enumerate() {
+            assert_eq!(vertex[..3], mesh.positions[3 * i..3 * i + 3]);
+            assert_eq!(vertex[3..6], mesh.normals[3 * i..3 * i + 3]);
+            assert_eq!(vertex[6..], mesh.tex_coords[2 * i..2 * i + 2]);
+        }
+
+        let (models, _) = parse("v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\n").unwrap();
+        let (vertices, _) = models[0].mesh.interleaved();
+        for vertex in vertices.chunks_exact(INTERLEAVED_STRIDE) {
+            assert_eq!(vertex[3..], [0.0; 5]);
+        }
+    }
+
+    #[test]
+    fn imported_normals_are_renormalized() {
+        let (mut models, _) =
+            parse("v 0 0 0\nv 1 0 0\nv 0 1 0\nvn 0 0 5\nf 1//1 2//1 3//1\n").unwrap();
+        let mesh = &mut models[0].mesh;
+        assert_eq!(mesh.normals[..3], [0.0, 0.0, 5.0]);
+        normalize_normals(mesh);
+        for normal in mesh.normals.chunks_exact(3) {
+            assert_eq!(normal, [0.0, 0.0, 1.0]);
+        }
+    }
+
+    #[test]
+    fn cube_stats() {
+        let (models, _) = parse(INWARD_CUBE).unwrap();
+        let stats = models[0].stats();
+        // Each corner is split between the three faces around it.
+        assert_eq!(stats.vertices, 24);
+        assert_eq!(stats.unique_positions, 8);
+        assert_eq!(stats.triangles, 12);
+        assert_eq!(stats.points, 0);
+        assert!(stats.has_normals);
+        assert!(!stats.has_tex_coords);
+        assert!(!stats.has_colors);
+        assert_eq!(stats.bounds, Some(([-1.0; 3], [1.0; 3])));
+    }
+
+    #[test]
+    fn missing_position_values_fail_and_extra_ones_are_ignored() {
+        assert!(matches!(
+            parse("v 1 2\nv 1 0 0\nv 0 1 0\nf 1 2 3\n"),
+            Err(ObjError::VertexParseError)
+        ));
+        let (models, _) = parse("v 1 2 3 extra\nv 1 0 0\nv 0 1 0\nf 1 2 3\n").unwrap();
+        assert_eq!(models[0].mesh.positions[..3], [1.0, 2.0, 3.0]);
+        assert!(models[0].mesh.colors.is_empty());
+    }
 }
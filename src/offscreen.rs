@@ -0,0 +1,139 @@
+use anyhow::{anyhow, Result};
+use vulkanalia::prelude::v1_2::*;
+
+use crate::config::AppConfig;
+use crate::textures::create_image;
+use crate::{App, AppData, Target, MAX_FRAMES_IN_FLIGHT};
+
+/// Format of the offscreen images, the one preferred for the swapchain.
+pub const OFFSCREEN_FORMAT: vk::Format = vk::Format::B8G8R8A8_SRGB;
+
+/// Creates the image rendered to instead of the swapchain ones, of `extent`.
+/// It can be copied from, to read the rendered pixels back.
+pub unsafe fn create_offscreen_images(
+    instance: &Instance,
+    device: &Device,
+    data: &mut AppData,
+    extent: vk::Extent2D,
+) -> Result<()> {
+    let usage = vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::TRANSFER_SRC;
+    let (image, memory) = create_image(
+        instance,
+        device,
+        data,
+        extent.width,
+        extent.height,
+        1,
+        OFFSCREEN_FORMAT,
+        vk::ImageTiling::OPTIMAL,
+        usage,
+        vk::MemoryPropertyFlags::DEVICE_LOCAL,
+    )?;
+
+    data.swapchain_format = OFFSCREEN_FORMAT;
+    data.swapchain_extent = extent;
+    data.swapchain_usage = usage;
+    data.swapchain_images = vec![image];
+    data.offscreen_images_memory = vec![memory];
+
+    Ok(())
+}
+
+/// Destroys the offscreen images, if the frames are rendered to them.
+pub unsafe fn destroy_offscreen_images(device: &Device, data: &AppData) {
+    for (&image, &memory) in data
+        .swapchain_images
+        .iter()
+        .zip(&data.offscreen_images_memory)
+    {
+        device.destroy_image(image, None);
+        device.free_memory(memory, None);
+    }
+}
+
+impl App {
+    /// Creates an app rendering to an image of `extent` instead of a window,
+    /// for `render_once`.
+    ///
+    /// # Safety
+    ///
+    /// The app must only render with `render_once`, it has no swapchain to
+    /// acquire from, present to or recreate.
+    pub unsafe fn create_offscreen(config: AppConfig, extent: vk::Extent2D) -> Result<Self> {
+        Self::create_for(Target::Offscreen(extent), config)
+    }
+
+    /// Renders one frame of an app created by `create_offscreen`, then
+    /// returns the RGBA color of its pixel at `x`, `y`.
+    ///
+    /// # Safety
+    ///
+    /// The app must render offscreen, there is no swapchain image to acquire.
+    pub unsafe fn render_once(&mut self, x: u32, y: u32) -> Result<[u8; 4]> {
+        let in_flight_fence = self.data.in_flight_fences[self.frame];
+        self.device
+            .wait_for_fences(&[in_flight_fence], true, u64::MAX)?;
+
+        self.request_pixel(x, y)?;
+        let readback = self.submit_frame(0, &[], &[])?;
+        self.frame = (self.frame + 1) % MAX_FRAMES_IN_FLIGHT;
+
+        let readback = readback.ok_or_else(|| anyhow!("Failed to copy pixel {}, {}", x, y))?;
+        self.finish_pixel_readback(readback, in_flight_fence)
+    }
+}
+
+/// Whether a Vulkan loader is installed with at least one device.
+#[cfg(test)]
+unsafe fn vulkan_device_available() -> bool {
+    use vulkanalia::loader::{LibloadingLoader, LIBRARY};
+
+    let Ok(loader) = LibloadingLoader::new(LIBRARY) else {
+        return false;
+    };
+    let Ok(entry) = Entry::new(loader) else {
+        return false;
+    };
+    let Ok(instance) = entry.create_instance(&vk::InstanceCreateInfo::builder(), None) else {
+        return false;
+    };
+    let available = instance
+        .enumerate_physical_devices()
+        .is_ok_and(|devices| !devices.is_empty());
+    instance.destroy_instance(None);
+    available
+}
+
+/// App rendering the default cube to a 64x64 image, for the tests needing a
+/// device. `None` when no Vulkan loader or device is available.
+#[cfg(test)]
+pub unsafe fn test_app() -> Option<App> {
+    if !vulkan_device_available() {
+        eprintln!("No Vulkan device, skipping the test");
+        return None;
+    }
+    let extent = vk::Extent2D {
+        width: 64,
+        height: 64,
+    };
+    // The validation layers may not be installed along the driver.
+    let config = AppConfig::default().validation(false);
+    Some(App::create_offscreen(config, extent).unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_the_default_cube() {
+        unsafe {
+            let Some(mut app) = test_app() else {
+                return;
+            };
+            let pixel = app.render_once(32, 32).unwrap();
+            // The color attachment is cleared to opaque black.
+            assert_ne!(pixel, [0, 0, 0, 255]);
+        }
+    }
+}
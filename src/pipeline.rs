@@ -1,31 +1,147 @@
-use anyhow::{Ok, Result};
+use anyhow::{anyhow, Ok, Result};
 
 use vulkanalia::bytecode::Bytecode;
 use vulkanalia::prelude::v1_2::*;
 
+use crate::depth::closer_compare_op;
+use crate::descriptor;
+use crate::gizmo::GIZMO_SIZE;
+use crate::math::Vec3;
 use crate::vertex::Vertex;
-use crate::{depth, AppData};
+use crate::AppData;
+
+/// Values pushed to both shader stages, matching the `PushConstants` block
+/// of the shaders. Each `vec3` is 16 bytes aligned and followed by a 4 bytes
+/// value so no padding is needed.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default)]
+pub struct PushConstants {
+    pub wireframe_color: Vec3,
+    pub color_mode: u32,
+    pub solid_color: Vec3,
+    pub point_size: f32,
+    pub wireframe: u32,
+    pub outline: u32,
+    pub gizmo: u32,
+    pub texture_index: u32,
+    pub depth_view: u32,
+    /// Factor the color is multiplied by, dimming the inactive objects.
+    pub brightness: f32,
+}
+
+impl PushConstants {
+    pub fn as_bytes(&self) -> &[u8] {
+        unsafe {
+            std::slice::from_raw_parts(
+                (self as *const Self).cast::<u8>(),
+                std::mem::size_of::<Self>(),
+            )
+        }
+    }
+}
+
+#[cfg(feature = "shaders-from-disk")]
+pub const VERT_SHADER_PATH: &str = "shaders_compiled/shader.vert.spv";
+#[cfg(feature = "shaders-from-disk")]
+pub const FRAG_SHADER_PATH: &str = "shaders_compiled/shader.frag.spv";
+
+#[cfg(not(feature = "shaders-from-disk"))]
+const VERT_SHADER: &[u8] = include_bytes!("../shaders_compiled/shader.vert.spv");
+#[cfg(not(feature = "shaders-from-disk"))]
+const FRAG_SHADER: &[u8] = include_bytes!("../shaders_compiled/shader.frag.spv");
+
+/// Returns the vertex and fragment SPIR-V embedded in the binary.
+#[cfg(not(feature = "shaders-from-disk"))]
+fn shader_bytecode() -> Result<(Vec<u8>, Vec<u8>)> {
+    Ok((VERT_SHADER.to_vec(), FRAG_SHADER.to_vec()))
+}
+
+/// Reads the vertex and fragment SPIR-V from disk, to iterate on shaders
+/// without rebuilding.
+#[cfg(feature = "shaders-from-disk")]
+fn shader_bytecode() -> Result<(Vec<u8>, Vec<u8>)> {
+    let vert = std::fs::read(VERT_SHADER_PATH)
+        .map_err(|error| anyhow!("Failed to read {}: {}", VERT_SHADER_PATH, error))?;
+    let frag = std::fs::read(FRAG_SHADER_PATH)
+        .map_err(|error| anyhow!("Failed to read {}: {}", FRAG_SHADER_PATH, error))?;
+    Ok((vert, frag))
+}
+
+/// The GLSL sources compiled into `shaders_compiled/`.
+#[cfg(feature = "shader-reload")]
+pub const SHADER_SOURCE_PATHS: [&str; 2] = ["shaders/shader.vert", "shaders/shader.frag"];
+
+/// Recompiles the GLSL sources to SPIR-V through the Makefile.
+#[cfg(feature = "shader-reload")]
+pub fn compile_shaders() -> Result<()> {
+    let status = std::process::Command::new("make").arg("shader").status()?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(anyhow!("Shader compilation failed ({})", status))
+    }
+}
 
 pub unsafe fn create(device: &Device, data: &mut AppData) -> Result<()> {
-    let vert = include_bytes!("../shaders_compiled/shader.vert.spv");
-    let frag = include_bytes!("../shaders_compiled/shader.frag.spv");
+    let (vert, frag) = shader_bytecode()?;
 
-    let vert_shader_module = create_shader_module(device, vert)?;
-    let frag_shader_module = create_shader_module(device, frag)?;
+    let vert_shader_module = create_shader_module(device, &vert)?;
+    let frag_shader_module = create_shader_module(device, &frag)?;
 
     let vert_stage = vk::PipelineShaderStageCreateInfo::builder()
         .stage(vk::ShaderStageFlags::VERTEX)
         .module(vert_shader_module)
         .name(b"main\0");
+    // `HAS_TEX_COORDS` of the fragment shader, to skip sampling the texture
+    // for models without texture coordinates, `SOLID_COLOR` to draw the
+    // model in a single color, `NORMAL_COLOR` to draw its normals and
+    // `REVERSE_Z` to read the depth view from a reversed depth buffer.
+    // `TEXTURE_COUNT` sizes the texture array.
+    let bool_size = std::mem::size_of::<vk::Bool32>();
+    let map_entries = &[
+        vk::SpecializationMapEntry::builder()
+            .constant_id(0)
+            .offset(0)
+            .size(bool_size)
+            .build(),
+        vk::SpecializationMapEntry::builder()
+            .constant_id(1)
+            .offset(bool_size as u32)
+            .size(bool_size)
+            .build(),
+        vk::SpecializationMapEntry::builder()
+            .constant_id(2)
+            .offset(2 * bool_size as u32)
+            .size(bool_size)
+            .build(),
+        vk::SpecializationMapEntry::builder()
+            .constant_id(3)
+            .offset(3 * bool_size as u32)
+            .size(bool_size)
+            .build(),
+        vk::SpecializationMapEntry::builder()
+            .constant_id(4)
+            .offset(4 * bool_size as u32)
+            .size(std::mem::size_of::<u32>())
+            .build(),
+    ];
+    let specialization_data = specialization_constants(data)
+        .map(u32::to_ne_bytes)
+        .concat();
+    let specialization_info = vk::SpecializationInfo::builder()
+        .map_entries(map_entries)
+        .data(&specialization_data);
+
     let frag_stage = vk::PipelineShaderStageCreateInfo::builder()
         .stage(vk::ShaderStageFlags::FRAGMENT)
         .module(frag_shader_module)
-        .name(b"main\0");
+        .name(b"main\0")
+        .specialization_info(&specialization_info);
 
-    let binding_descriptions = &[Vertex::binding_description()];
-    let attribute_descriptions = Vertex::attribute_descriptions();
+    let binding_descriptions = Vertex::binding_descriptions(data.has_lightmap_tex_coords);
+    let attribute_descriptions = Vertex::attribute_descriptions(data.has_lightmap_tex_coords);
     let vertex_input_state = vk::PipelineVertexInputStateCreateInfo::builder()
-        .vertex_binding_descriptions(binding_descriptions)
+        .vertex_binding_descriptions(&binding_descriptions)
         .vertex_attribute_descriptions(&attribute_descriptions);
 
     //
@@ -34,6 +150,14 @@ pub unsafe fn create(device: &Device, data: &mut AppData) -> Result<()> {
         .topology(vk::PrimitiveTopology::TRIANGLE_LIST)
         .primitive_restart_enable(false);
 
+    let point_input_assembly_state = vk::PipelineInputAssemblyStateCreateInfo::builder()
+        .topology(vk::PrimitiveTopology::POINT_LIST)
+        .primitive_restart_enable(false);
+
+    let gizmo_input_assembly_state = vk::PipelineInputAssemblyStateCreateInfo::builder()
+        .topology(vk::PrimitiveTopology::LINE_LIST)
+        .primitive_restart_enable(false);
+
     //
 
     let viewport = vk::Viewport::builder()
@@ -54,6 +178,23 @@ pub unsafe fn create(device: &Device, data: &mut AppData) -> Result<()> {
         .viewports(viewports)
         .scissors(scissors);
 
+    // The axis gizmo is drawn in the bottom left corner.
+    let gizmo_size = GIZMO_SIZE
+        .min(data.swapchain_extent.width)
+        .min(data.swapchain_extent.height);
+    let gizmo_viewport = vk::Viewport::builder()
+        .x(0.0)
+        .y((data.swapchain_extent.height - gizmo_size) as f32)
+        .width(gizmo_size as f32)
+        .height(gizmo_size as f32)
+        .min_depth(0.0)
+        .max_depth(1.0);
+
+    let gizmo_viewports = &[gizmo_viewport];
+    let gizmo_viewport_state = vk::PipelineViewportStateCreateInfo::builder()
+        .viewports(gizmo_viewports)
+        .scissors(scissors);
+
     //
 
     let rasterization_state = vk::PipelineRasterizationStateCreateInfo::builder()
@@ -64,7 +205,7 @@ pub unsafe fn create(device: &Device, data: &mut AppData) -> Result<()> {
         } else {
             vk::PolygonMode::FILL
         },)
-        .line_width(1.0)
+        .line_width(data.line_width)
         .cull_mode(vk::CullModeFlags::BACK)
         .front_face(vk::FrontFace::COUNTER_CLOCKWISE)
         .depth_bias_enable(false);
@@ -77,10 +218,60 @@ pub unsafe fn create(device: &Device, data: &mut AppData) -> Result<()> {
 
     //
 
+    // Mark the pixels covered by the model in the stencil buffer, for the outline.
+    let stencil_write = vk::StencilOpState::builder()
+        .fail_op(vk::StencilOp::KEEP)
+        .pass_op(vk::StencilOp::REPLACE)
+        .depth_fail_op(vk::StencilOp::KEEP)
+        .compare_op(vk::CompareOp::ALWAYS)
+        .compare_mask(0xFF)
+        .write_mask(0xFF)
+        .reference(1)
+        .build();
+
     let depth_stencil_state = vk::PipelineDepthStencilStateCreateInfo::builder()
         .depth_test_enable(true)
         .depth_write_enable(true)
-        .depth_compare_op(vk::CompareOp::LESS)
+        .depth_compare_op(closer_compare_op(data.reverse_z, false))
+        .depth_bounds_test_enable(false)
+        .stencil_test_enable(true)
+        .front(stencil_write)
+        .back(stencil_write);
+
+    let outline_depth_stencil_state = outline_depth_stencil_state();
+
+    let outline_rasterization_state = vk::PipelineRasterizationStateCreateInfo::builder()
+        .depth_clamp_enable(false)
+        .rasterizer_discard_enable(false)
+        .polygon_mode(vk::PolygonMode::FILL)
+        .line_width(1.0)
+        .cull_mode(vk::CullModeFlags::BACK)
+        .front_face(vk::FrontFace::COUNTER_CLOCKWISE)
+        .depth_bias_enable(false);
+
+    // The fullscreen triangle of the depth view is seen from both sides
+    // depending on the viewport orientation.
+    let post_rasterization_state = vk::PipelineRasterizationStateCreateInfo::builder()
+        .depth_clamp_enable(false)
+        .rasterizer_discard_enable(false)
+        .polygon_mode(vk::PolygonMode::FILL)
+        .line_width(1.0)
+        .cull_mode(vk::CullModeFlags::NONE)
+        .front_face(vk::FrontFace::COUNTER_CLOCKWISE)
+        .depth_bias_enable(false);
+
+    // The bounding box lines are hidden by the model, without touching the stencil.
+    let bounds_depth_stencil_state = vk::PipelineDepthStencilStateCreateInfo::builder()
+        .depth_test_enable(true)
+        .depth_write_enable(true)
+        .depth_compare_op(closer_compare_op(data.reverse_z, true))
+        .depth_bounds_test_enable(false)
+        .stencil_test_enable(false);
+
+    // The gizmo is drawn over the model.
+    let gizmo_depth_stencil_state = vk::PipelineDepthStencilStateCreateInfo::builder()
+        .depth_test_enable(false)
+        .depth_write_enable(false)
         .depth_bounds_test_enable(false)
         .stencil_test_enable(false);
 
@@ -99,13 +290,8 @@ pub unsafe fn create(device: &Device, data: &mut AppData) -> Result<()> {
 
     //
 
-    let frag_push_constant = vk::PushConstantRange::builder()
-        .stage_flags(vk::ShaderStageFlags::FRAGMENT)
-        .offset(0)
-        .size(std::mem::size_of::<u32>() as u32);
-
     //
-    let constant_ranges = &[frag_push_constant];
+    let constant_ranges = &[push_constant_range()];
     let set_layouts = &[data.descriptor_set_layout];
     let layout_info = vk::PipelineLayoutCreateInfo::builder()
         .push_constant_ranges(constant_ranges)
@@ -127,35 +313,161 @@ pub unsafe fn create(device: &Device, data: &mut AppData) -> Result<()> {
         .render_pass(data.render_pass)
         .subpass(0);
 
-    data.pipeline = device
-        .create_graphics_pipelines(vk::PipelineCache::null(), &[pipeline_info], None)?
-        .0[0];
+    // Same state, drawing the point elements of the model.
+    let point_pipeline_info = vk::GraphicsPipelineCreateInfo::builder()
+        .stages(stages)
+        .vertex_input_state(&vertex_input_state)
+        .input_assembly_state(&point_input_assembly_state)
+        .viewport_state(&viewport_state)
+        .rasterization_state(&rasterization_state)
+        .multisample_state(&multisample_state)
+        .depth_stencil_state(&depth_stencil_state)
+        .color_blend_state(&color_blend_state)
+        .layout(data.pipeline_layout)
+        .render_pass(data.render_pass)
+        .subpass(0);
+
+    let outline_pipeline_info = vk::GraphicsPipelineCreateInfo::builder()
+        .stages(stages)
+        .vertex_input_state(&vertex_input_state)
+        .input_assembly_state(&input_assembly_state)
+        .viewport_state(&viewport_state)
+        .rasterization_state(&outline_rasterization_state)
+        .multisample_state(&multisample_state)
+        .depth_stencil_state(&outline_depth_stencil_state)
+        .color_blend_state(&color_blend_state)
+        .layout(data.pipeline_layout)
+        .render_pass(data.render_pass)
+        .subpass(0);
+
+    let gizmo_pipeline_info = vk::GraphicsPipelineCreateInfo::builder()
+        .stages(stages)
+        .vertex_input_state(&vertex_input_state)
+        .input_assembly_state(&gizmo_input_assembly_state)
+        .viewport_state(&gizmo_viewport_state)
+        .rasterization_state(&outline_rasterization_state)
+        .multisample_state(&multisample_state)
+        .depth_stencil_state(&gizmo_depth_stencil_state)
+        .color_blend_state(&color_blend_state)
+        .layout(data.pipeline_layout)
+        .render_pass(data.render_pass)
+        .subpass(0);
+
+    // Lines of the model bounding box.
+    let bounds_pipeline_info = vk::GraphicsPipelineCreateInfo::builder()
+        .stages(stages)
+        .vertex_input_state(&vertex_input_state)
+        .input_assembly_state(&gizmo_input_assembly_state)
+        .viewport_state(&viewport_state)
+        .rasterization_state(&outline_rasterization_state)
+        .multisample_state(&multisample_state)
+        .depth_stencil_state(&bounds_depth_stencil_state)
+        .color_blend_state(&color_blend_state)
+        .layout(data.pipeline_layout)
+        .render_pass(data.render_pass)
+        .subpass(0);
+
+    // Depth view, drawn in the second subpass while the depth buffer is read only.
+    let post_pipeline_info = vk::GraphicsPipelineCreateInfo::builder()
+        .stages(stages)
+        .vertex_input_state(&vertex_input_state)
+        .input_assembly_state(&input_assembly_state)
+        .viewport_state(&viewport_state)
+        .rasterization_state(&post_rasterization_state)
+        .multisample_state(&multisample_state)
+        .depth_stencil_state(&gizmo_depth_stencil_state)
+        .color_blend_state(&color_blend_state)
+        .layout(data.pipeline_layout)
+        .render_pass(data.render_pass)
+        .subpass(1);
+
+    let pipelines = device
+        .create_graphics_pipelines(
+            vk::PipelineCache::null(),
+            &[
+                pipeline_info,
+                point_pipeline_info,
+                outline_pipeline_info,
+                gizmo_pipeline_info,
+                post_pipeline_info,
+                bounds_pipeline_info,
+            ],
+            None,
+        )?
+        .0;
+    data.pipeline = pipelines[0];
+    data.point_pipeline = pipelines[1];
+    data.outline_pipeline = pipelines[2];
+    data.gizmo_pipeline = pipelines[3];
+    data.post_pipeline = pipelines[4];
+    data.bounds_pipeline = pipelines[5];
 
     device.destroy_shader_module(vert_shader_module, None);
     device.destroy_shader_module(frag_shader_module, None);
     Ok(())
 }
 
+/// Values of the specialization constants of the fragment shader, by
+/// `constant_id`. All are 32 bits wide.
+fn specialization_constants(data: &AppData) -> [u32; 5] {
+    [
+        data.has_tex_coords as vk::Bool32,
+        data.solid_color.is_some() as vk::Bool32,
+        data.normal_color as vk::Bool32,
+        data.reverse_z as vk::Bool32,
+        descriptor::texture_count(data) as u32,
+    ]
+}
+
+/// Outline: only draw the scaled-up model where the model itself wasn't.
+fn outline_depth_stencil_state() -> vk::PipelineDepthStencilStateCreateInfo {
+    let stencil_outline = vk::StencilOpState::builder()
+        .fail_op(vk::StencilOp::KEEP)
+        .pass_op(vk::StencilOp::KEEP)
+        .depth_fail_op(vk::StencilOp::KEEP)
+        .compare_op(vk::CompareOp::NOT_EQUAL)
+        .compare_mask(0xFF)
+        .write_mask(0x00)
+        .reference(1)
+        .build();
+
+    vk::PipelineDepthStencilStateCreateInfo::builder()
+        .depth_test_enable(false)
+        .depth_write_enable(false)
+        .depth_bounds_test_enable(false)
+        .stencil_test_enable(true)
+        .front(stencil_outline)
+        .back(stencil_outline)
+        .build()
+}
+
+/// The whole `PushConstants` block, seen by both shader stages.
+fn push_constant_range() -> vk::PushConstantRange {
+    vk::PushConstantRange::builder()
+        .stage_flags(vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::FRAGMENT)
+        .offset(0)
+        .size(std::mem::size_of::<PushConstants>() as u32)
+        .build()
+}
+
 unsafe fn create_shader_module(device: &Device, bytecode: &[u8]) -> Result<vk::ShaderModule> {
-    let bytecode = Bytecode::new(bytecode).unwrap();
+    // Copies the bytes into a 4-byte aligned buffer, as required for SPIR-V words.
+    let bytecode =
+        Bytecode::new(bytecode).map_err(|error| anyhow!("Invalid shader bytecode: {:?}", error))?;
     let create_info = vk::ShaderModuleCreateInfo::builder()
         .code_size(bytecode.code_size())
         .code(bytecode.code());
     Ok(device.create_shader_module(&create_info, None)?)
 }
 
-pub unsafe fn create_render_pass(
-    instance: &Instance,
-    device: &Device,
-    data: &mut AppData,
-) -> Result<()> {
+pub unsafe fn create_render_pass(device: &Device, data: &mut AppData) -> Result<()> {
     // Depth
     let depth_stencil_attachment = vk::AttachmentDescription::builder()
-        .format(depth::get_depth_format(instance, data)?)
+        .format(data.depth_format)
         .samples(vk::SampleCountFlags::_1)
         .load_op(vk::AttachmentLoadOp::CLEAR)
         .store_op(vk::AttachmentStoreOp::DONT_CARE)
-        .stencil_load_op(vk::AttachmentLoadOp::DONT_CARE)
+        .stencil_load_op(vk::AttachmentLoadOp::CLEAR)
         .stencil_store_op(vk::AttachmentStoreOp::DONT_CARE)
         .initial_layout(vk::ImageLayout::UNDEFINED)
         .final_layout(vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL);
@@ -185,6 +497,16 @@ pub unsafe fn create_render_pass(
         .color_attachments(color_attachments)
         .depth_stencil_attachment(&depth_stencil_attachment_ref);
 
+    // Second subpass, sampling the depth buffer for the depth view.
+    let read_only_depth_stencil_attachment_ref = vk::AttachmentReference::builder()
+        .attachment(1)
+        .layout(vk::ImageLayout::DEPTH_STENCIL_READ_ONLY_OPTIMAL);
+
+    let post_subpass = vk::SubpassDescription::builder()
+        .pipeline_bind_point(vk::PipelineBindPoint::GRAPHICS)
+        .color_attachments(color_attachments)
+        .depth_stencil_attachment(&read_only_depth_stencil_attachment_ref);
+
     let dependency = vk::SubpassDependency::builder()
         .src_subpass(vk::SUBPASS_EXTERNAL)
         .dst_subpass(0)
@@ -202,9 +524,27 @@ pub unsafe fn create_render_pass(
                 | vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE,
         );
 
+    let post_dependency = vk::SubpassDependency::builder()
+        .src_subpass(0)
+        .dst_subpass(1)
+        .src_stage_mask(
+            vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT
+                | vk::PipelineStageFlags::LATE_FRAGMENT_TESTS,
+        )
+        .src_access_mask(
+            vk::AccessFlags::COLOR_ATTACHMENT_WRITE
+                | vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE,
+        )
+        .dst_stage_mask(
+            vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT
+                | vk::PipelineStageFlags::FRAGMENT_SHADER,
+        )
+        .dst_access_mask(vk::AccessFlags::COLOR_ATTACHMENT_WRITE | vk::AccessFlags::SHADER_READ)
+        .dependency_flags(vk::DependencyFlags::BY_REGION);
+
     let attachments = &[color_attachment, depth_stencil_attachment];
-    let subpasses = &[subpass];
-    let dependencies = &[dependency];
+    let subpasses = &[subpass, post_subpass];
+    let dependencies = &[dependency, post_dependency];
     let render_pass_info = vk::RenderPassCreateInfo::builder()
         .attachments(attachments)
         .subpasses(subpasses)
@@ -214,3 +554,96 @@ pub unsafe fn create_render_pass(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::mem::{offset_of, size_of};
+
+    /// First word of every SPIR-V module.
+    const SPIRV_MAGIC: u32 = 0x0723_0203;
+
+    #[test]
+    fn shaders_are_spirv() {
+        let (vert, frag) = shader_bytecode().unwrap();
+        for bytecode in [vert, frag] {
+            assert_eq!(bytecode.len() % 4, 0);
+            let magic = u32::from_le_bytes(bytecode[..4].try_into().unwrap());
+            assert_eq!(magic, SPIRV_MAGIC);
+        }
+    }
+
+    #[test]
+    fn texture_sampling_follows_the_tex_coords() {
+        let mut data = AppData {
+            has_tex_coords: true,
+            ..Default::default()
+        };
+        assert_eq!(specialization_constants(&data)[0], vk::TRUE);
+        data.has_tex_coords = false;
+        assert_eq!(specialization_constants(&data)[0], vk::FALSE);
+    }
+
+    #[test]
+    fn solid_color_skips_the_texture() {
+        let mut data = AppData {
+            has_tex_coords: true,
+            ..Default::default()
+        };
+        assert_eq!(specialization_constants(&data)[1], vk::FALSE);
+        data.solid_color = Some(0);
+        assert_eq!(specialization_constants(&data)[1], vk::TRUE);
+    }
+
+    #[test]
+    fn normal_color_skips_the_texture() {
+        let mut data = AppData {
+            has_tex_coords: true,
+            ..Default::default()
+        };
+        assert_eq!(specialization_constants(&data)[2], vk::FALSE);
+        data.normal_color = true;
+        assert_eq!(specialization_constants(&data)[2], vk::TRUE);
+    }
+
+    #[test]
+    fn texture_array_is_sized_by_the_model() {
+        let mut data = AppData::default();
+        assert_eq!(specialization_constants(&data)[4], 1);
+        data.texture_paths = vec!["a.png".to_owned(), "b.png".to_owned()];
+        assert_eq!(specialization_constants(&data)[4], 3);
+    }
+
+    #[test]
+    fn outline_is_drawn_outside_the_model_stencil() {
+        let state = outline_depth_stencil_state();
+        assert_eq!(state.stencil_test_enable, vk::TRUE);
+        for stencil in [state.front, state.back] {
+            // The model pipeline writes 1 where the model is.
+            assert_eq!(stencil.compare_op, vk::CompareOp::NOT_EQUAL);
+            assert_eq!(stencil.reference, 1);
+            assert_eq!(stencil.write_mask, 0);
+        }
+    }
+
+    #[test]
+    fn push_constants_match_the_shader_block() {
+        // Offsets of the `PushConstants` block of the shaders.
+        assert_eq!(offset_of!(PushConstants, wireframe_color), 0);
+        assert_eq!(offset_of!(PushConstants, color_mode), 12);
+        assert_eq!(offset_of!(PushConstants, solid_color), 16);
+        assert_eq!(offset_of!(PushConstants, point_size), 28);
+        assert_eq!(offset_of!(PushConstants, brightness), 52);
+        // The smallest push constant size devices must support.
+        assert!(size_of::<PushConstants>() <= 128);
+    }
+
+    #[test]
+    fn push_constant_range_includes_the_wireframe_color() {
+        let range = push_constant_range();
+        let color_end = offset_of!(PushConstants, wireframe_color) + size_of::<Vec3>();
+        assert_eq!(range.offset, 0);
+        assert!(color_end as u32 <= range.size);
+        assert!(range.stage_flags.contains(vk::ShaderStageFlags::FRAGMENT));
+    }
+}
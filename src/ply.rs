@@ -0,0 +1,332 @@
+use std::path::Path;
+
+use anyhow::{anyhow, Result};
+
+use crate::obj::{Material, Mesh, Model, Transform};
+
+/// Whether `path` names a PLY file, by its extension.
+pub fn is_ply(path: &str) -> bool {
+    path.to_lowercase().ends_with(".ply")
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Format {
+    Ascii,
+    BinaryLittleEndian,
+}
+
+/// Type of a property value.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Scalar {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+impl Scalar {
+    fn parse(name: &str) -> Result<Self> {
+        Ok(match name {
+            "char" | "int8" => Scalar::I8,
+            "uchar" | "uint8" => Scalar::U8,
+            "short" | "int16" => Scalar::I16,
+            "ushort" | "uint16" => Scalar::U16,
+            "int" | "int32" => Scalar::I32,
+            "uint" | "uint32" => Scalar::U32,
+            "float" | "float32" => Scalar::F32,
+            "double" | "float64" => Scalar::F64,
+            _ => return Err(anyhow!("Unknown PLY property type: {}", name)),
+        })
+    }
+
+    fn size(self) -> usize {
+        match self {
+            Scalar::I8 | Scalar::U8 => 1,
+            Scalar::I16 | Scalar::U16 => 2,
+            Scalar::I32 | Scalar::U32 | Scalar::F32 => 4,
+            Scalar::F64 => 8,
+        }
+    }
+
+    /// Decodes a little endian value of `self.size()` bytes.
+    fn decode(self, bytes: &[u8]) -> f64 {
+        match self {
+            Scalar::I8 => bytes[0] as i8 as f64,
+            Scalar::U8 => bytes[0] as f64,
+            Scalar::I16 => i16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+            Scalar::U16 => u16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+            Scalar::I32 => i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64,
+            Scalar::U32 => u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64,
+            Scalar::F32 => f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64,
+            Scalar::F64 => f64::from_le_bytes(bytes[..8].try_into().unwrap()),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Property {
+    Scalar(Scalar, String),
+    /// A list of values, preceded by their count.
+    List(Scalar, Scalar, String),
+}
+
+#[derive(Clone, Debug)]
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+/// Values following the header.
+enum Body<'a> {
+    Ascii(std::str::SplitWhitespace<'a>),
+    Binary(&'a [u8]),
+}
+
+impl Body<'_> {
+    fn read(&mut self, scalar: Scalar) -> Result<f64> {
+        match self {
+            Body::Ascii(words) => {
+                let word = words
+                    .next()
+                    .ok_or_else(|| anyhow!("Unexpected end of PLY data"))?;
+                word.parse()
+                    .map_err(|_| anyhow!("Invalid PLY value: {}", word))
+            }
+            Body::Binary(bytes) => {
+                if bytes.len() < scalar.size() {
+                    return Err(anyhow!("Unexpected end of PLY data"));
+                }
+                let (value, rest) = bytes.split_at(scalar.size());
+                *bytes = rest;
+                Ok(scalar.decode(value))
+            }
+        }
+    }
+}
+
+/// Parses the header of a PLY file, returning its format, its elements and
+/// the offset of the data following it.
+fn parse_header(bytes: &[u8]) -> Result<(Format, Vec<Element>, usize)> {
+    let mut format = None;
+    let mut elements: Vec<Element> = Vec::new();
+    let mut offset = 0;
+
+    for line in bytes.split_inclusive(|&b| b == b'\n') {
+        offset += line.len();
+        let line = std::str::from_utf8(line).map_err(|_| anyhow!("Invalid PLY header"))?;
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.as_slice() {
+            ["ply"] | [] => {}
+            ["comment", ..] | ["obj_info", ..] => {}
+            ["format", "ascii", _] => format = Some(Format::Ascii),
+            ["format", "binary_little_endian", _] => format = Some(Format::BinaryLittleEndian),
+            ["format", other, _] => return Err(anyhow!("Unsupported PLY format: {}", other)),
+            ["element", name, count] => elements.push(Element {
+                name: name.to_string(),
+                count: count
+                    .parse()
+                    .map_err(|_| anyhow!("Invalid PLY element count: {}", count))?,
+                properties: Vec::new(),
+            }),
+            ["property", "list", count, item, name] => elements
+                .last_mut()
+                .ok_or_else(|| anyhow!("PLY property outside of an element"))?
+                .properties
+                .push(Property::List(
+                    Scalar::parse(count)?,
+                    Scalar::parse(item)?,
+                    name.to_string(),
+                )),
+            ["property", scalar, name] => elements
+                .last_mut()
+                .ok_or_else(|| anyhow!("PLY property outside of an element"))?
+                .properties
+                .push(Property::Scalar(Scalar::parse(scalar)?, name.to_string())),
+            ["end_header"] => {
+                let format = format.ok_or_else(|| anyhow!("Missing PLY format"))?;
+                return Ok((format, elements, offset));
+            }
+            _ => return Err(anyhow!("Invalid PLY header line: {}", line.trim())),
+        }
+    }
+    Err(anyhow!("Missing PLY end_header"))
+}
+
+/// Parses the vertices, with their optional normals, colors and texture
+/// coordinates, and the faces of a PLY file. Faces are triangulated as fans,
+/// and files without faces give a point cloud.
+pub fn parse_ply(bytes: &[u8]) -> Result<Mesh> {
+    if !bytes.starts_with(b"ply") {
+        return Err(anyhow!("Not a PLY file"));
+    }
+    let (format, elements, offset) = parse_header(bytes)?;
+    let mut body = match format {
+        Format::Ascii => {
+            let text = std::str::from_utf8(&bytes[offset..])
+                .map_err(|_| anyhow!("Invalid ASCII PLY data"))?;
+            Body::Ascii(text.split_whitespace())
+        }
+        Format::BinaryLittleEndian => Body::Binary(&bytes[offset..]),
+    };
+
+    let mut mesh = Mesh::default();
+    let (mut normals, mut colors, mut tex_coords) = (Vec::new(), Vec::new(), Vec::new());
+    for element in &elements {
+        for _ in 0..element.count {
+            let (mut normal, mut color, mut tex_coord) = ([0.0; 3], [1.0; 3], [0.0; 2]);
+            for property in &element.properties {
+                match property {
+                    Property::Scalar(scalar, name) => {
+                        let value = body.read(*scalar)?;
+                        if element.name != "vertex" {
+                            continue;
+                        }
+                        // 8 bit colors range from 0 to 255.
+                        let channel = if *scalar == Scalar::U8 {
+                            value as f32 / 255.0
+                        } else {
+                            value as f32
+                        };
+                        match name.as_str() {
+                            "x" | "y" | "z" => mesh.positions.push(value as f32),
+                            "nx" => normal[0] = value as f32,
+                            "ny" => normal[1] = value as f32,
+                            "nz" => normal[2] = value as f32,
+                            "red" => color[0] = channel,
+                            "green" => color[1] = channel,
+                            "blue" => color[2] = channel,
+                            "s" | "u" | "texture_u" => tex_coord[0] = value as f32,
+                            "t" | "v" | "texture_v" => tex_coord[1] = value as f32,
+                            _ => {}
+                        }
+                    }
+                    Property::List(count, item, name) => {
+                        let count = body.read(*count)? as usize;
+                        let mut indices = Vec::with_capacity(count);
+                        for _ in 0..count {
+                            indices.push(body.read(*item)? as u32);
+                        }
+                        let is_face = element.name == "face"
+                            && (name == "vertex_indices" || name == "vertex_index");
+                        if is_face && indices.len() >= 3 {
+                            for i in 1..indices.len() - 1 {
+                                mesh.indices
+                                    .extend([indices[0], indices[i], indices[i + 1]]);
+                            }
+                        }
+                    }
+                }
+            }
+            if element.name == "vertex" {
+                normals.extend(normal);
+                colors.extend(color);
+                tex_coords.extend(tex_coord);
+            }
+        }
+    }
+
+    let vertex_count = mesh.positions.len() / 3;
+    if mesh.indices.iter().any(|&i| i as usize >= vertex_count) {
+        return Err(anyhow!("PLY face vertex out of bounds"));
+    }
+    let has = |names: &[&str]| {
+        elements.iter().filter(|e| e.name == "vertex").any(|e| {
+            e.properties.iter().any(|p| match p {
+                Property::Scalar(_, name) => names.contains(&name.as_str()),
+                Property::List(..) => false,
+            })
+        })
+    };
+    if has(&["nx"]) {
+        mesh.normals = normals;
+    }
+    if has(&["red"]) {
+        mesh.colors = colors;
+    }
+    if has(&["s", "u", "texture_u"]) {
+        mesh.tex_coords = tex_coords;
+    }
+    if mesh.indices.is_empty() {
+        mesh.point_indices = (0..vertex_count as u32).collect();
+    }
+    Ok(mesh)
+}
+
+/// Loads a PLY file as a single model, without materials.
+pub fn load_ply(path: &str) -> Result<(Vec<Model>, Vec<Material>)> {
+    let bytes = std::fs::read(path)?;
+    let mesh = parse_ply(&bytes)?;
+    let name = Path::new(path)
+        .file_stem()
+        .map_or("undefined".into(), |stem| {
+            stem.to_string_lossy().into_owned()
+        });
+    let model = Model {
+        name,
+        group: "undefined".to_owned(),
+        mesh,
+        transform: Transform::default(),
+    };
+    Ok((vec![model], Vec::new()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CUBE: &str = "ply
+format ascii 1.0
+element vertex 8
+property float x
+property float y
+property float z
+element face 6
+property list uchar int vertex_indices
+end_header
+0 0 0
+1 0 0
+1 1 0
+0 1 0
+0 0 1
+1 0 1
+1 1 1
+0 1 1
+4 0 3 2 1
+4 4 5 6 7
+4 0 1 5 4
+4 2 3 7 6
+4 1 2 6 5
+4 3 0 4 7
+";
+
+    #[test]
+    fn ascii_cube_is_triangulated() {
+        let mesh = parse_ply(CUBE.as_bytes()).unwrap();
+        assert_eq!(mesh.positions.len(), 8 * 3);
+        assert_eq!(mesh.indices.len(), 12 * 3);
+        assert!(mesh.point_indices.is_empty());
+        assert!(mesh.normals.is_empty() && mesh.colors.is_empty());
+    }
+
+    #[test]
+    fn vertices_without_faces_are_points() {
+        let points = "ply
+format ascii 1.0
+element vertex 2
+property float x
+property float y
+property float z
+end_header
+0 0 0
+1 0 0
+";
+        let mesh = parse_ply(points.as_bytes()).unwrap();
+        assert!(mesh.indices.is_empty());
+        assert_eq!(mesh.point_indices, [0, 1]);
+    }
+}
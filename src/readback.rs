@@ -0,0 +1,254 @@
+use anyhow::{anyhow, Result};
+use vulkanalia::prelude::v1_2::*;
+
+use crate::buffers::{begin_single_time_commands, create_buffer};
+use crate::color;
+use crate::App;
+
+/// Converts a pixel of a swapchain image in `format` to RGBA.
+pub fn to_rgba(pixel: [u8; 4], format: vk::Format) -> [u8; 4] {
+    match format {
+        vk::Format::B8G8R8A8_SRGB | vk::Format::B8G8R8A8_UNORM => color::bgra_to_rgba(pixel),
+        _ => pixel,
+    }
+}
+
+/// Whether the pixel at `x`, `y` lies within `extent`.
+pub fn in_extent(x: u32, y: u32, extent: vk::Extent2D) -> bool {
+    x < extent.width && y < extent.height
+}
+
+/// Moves a presentable swapchain image to and from the layout it is copied
+/// from.
+unsafe fn transition(
+    device: &Device,
+    command_buffer: vk::CommandBuffer,
+    image: vk::Image,
+    (old_layout, src_access_mask): (vk::ImageLayout, vk::AccessFlags),
+    (new_layout, dst_access_mask): (vk::ImageLayout, vk::AccessFlags),
+) {
+    let subresource = vk::ImageSubresourceRange::builder()
+        .aspect_mask(vk::ImageAspectFlags::COLOR)
+        .base_mip_level(0)
+        .level_count(1)
+        .base_array_layer(0)
+        .layer_count(1);
+
+    let barrier = vk::ImageMemoryBarrier::builder()
+        .old_layout(old_layout)
+        .new_layout(new_layout)
+        .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+        .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+        .image(image)
+        .subresource_range(subresource)
+        .src_access_mask(src_access_mask)
+        .dst_access_mask(dst_access_mask);
+
+    device.cmd_pipeline_barrier(
+        command_buffer,
+        vk::PipelineStageFlags::ALL_COMMANDS,
+        vk::PipelineStageFlags::TRANSFER | vk::PipelineStageFlags::BOTTOM_OF_PIPE,
+        vk::DependencyFlags::empty(),
+        &[] as &[vk::MemoryBarrier],
+        &[] as &[vk::BufferMemoryBarrier],
+        &[barrier],
+    );
+}
+
+/// A pixel of a frame being copied to a host visible buffer, by commands
+/// submitted along with the frame.
+pub struct PixelReadback {
+    pub x: u32,
+    pub y: u32,
+    buffer: vk::Buffer,
+    memory: vk::DeviceMemory,
+    pub command_buffer: vk::CommandBuffer,
+}
+
+impl App {
+    /// Requests the RGBA color of the pixel at `x`, `y` of the next frame.
+    /// Fails when the coordinate is outside the swapchain extent.
+    pub fn request_pixel(&mut self, x: u32, y: u32) -> Result<()> {
+        let data = &mut self.data;
+        if !in_extent(x, y, data.swapchain_extent) {
+            return Err(anyhow!(
+                "Pixel {}, {} is outside the {}x{} frame",
+                x,
+                y,
+                data.swapchain_extent.width,
+                data.swapchain_extent.height
+            ));
+        }
+        if !data
+            .swapchain_usage
+            .contains(vk::ImageUsageFlags::TRANSFER_SRC)
+        {
+            return Err(anyhow!("The swapchain images can't be copied from"));
+        }
+        data.pixel_request = Some((x, y));
+        Ok(())
+    }
+
+    /// Records the copy of the requested pixel, if any, out of the swapchain
+    /// image at `image_index`. The commands are submitted after the frame's,
+    /// so they run before the image is presented, while it is still ours.
+    ///
+    /// # Safety
+    ///
+    /// `image_index` must be the swapchain image just acquired and rendered
+    /// to, and the returned readback must be submitted with that frame.
+    pub unsafe fn record_pixel_readback(
+        &mut self,
+        image_index: usize,
+    ) -> Result<Option<PixelReadback>> {
+        let Some((x, y)) = self.data.pixel_request.take() else {
+            return Ok(None);
+        };
+        let data = &self.data;
+        // The swapchain may have been resized since the request.
+        if !in_extent(x, y, data.swapchain_extent) {
+            return Err(anyhow!("Pixel {}, {} is outside the frame", x, y));
+        }
+        let image = data.swapchain_images[image_index];
+
+        let (buffer, memory) = create_buffer(
+            &self.instance,
+            &self.device,
+            data,
+            4,
+            vk::BufferUsageFlags::TRANSFER_DST,
+            vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+        )?;
+        let mut readback = PixelReadback {
+            x,
+            y,
+            buffer,
+            memory,
+            command_buffer: vk::CommandBuffer::null(),
+        };
+
+        if let Err(error) = self.record_pixel_copy(image, &mut readback) {
+            self.release_pixel_readback(&readback);
+            return Err(error);
+        }
+        Ok(Some(readback))
+    }
+
+    /// Records the commands of `readback`, copying its pixel out of `image`.
+    unsafe fn record_pixel_copy(
+        &self,
+        image: vk::Image,
+        readback: &mut PixelReadback,
+    ) -> Result<()> {
+        let command_buffer = begin_single_time_commands(&self.device, &self.data)?;
+        readback.command_buffer = command_buffer;
+        // The render pass leaves the image ready to present.
+        let rendered = (
+            vk::ImageLayout::PRESENT_SRC_KHR,
+            vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+        );
+        let transfer = (
+            vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+            vk::AccessFlags::TRANSFER_READ,
+        );
+        let present = (vk::ImageLayout::PRESENT_SRC_KHR, vk::AccessFlags::empty());
+        transition(&self.device, command_buffer, image, rendered, transfer);
+
+        let subresource = vk::ImageSubresourceLayers::builder()
+            .aspect_mask(vk::ImageAspectFlags::COLOR)
+            .mip_level(0)
+            .base_array_layer(0)
+            .layer_count(1);
+        let region = vk::BufferImageCopy::builder()
+            .buffer_offset(0)
+            .buffer_row_length(0)
+            .buffer_image_height(0)
+            .image_subresource(subresource)
+            .image_offset(vk::Offset3D {
+                x: readback.x as i32,
+                y: readback.y as i32,
+                z: 0,
+            })
+            .image_extent(vk::Extent3D {
+                width: 1,
+                height: 1,
+                depth: 1,
+            });
+        self.device.cmd_copy_image_to_buffer(
+            command_buffer,
+            image,
+            vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+            readback.buffer,
+            &[region],
+        );
+
+        transition(&self.device, command_buffer, image, transfer, present);
+        self.device.end_command_buffer(command_buffer)?;
+
+        Ok(())
+    }
+
+    /// Frees the command buffer, if recorded, and the buffer of `readback`.
+    unsafe fn release_pixel_readback(&self, readback: &PixelReadback) {
+        if !readback.command_buffer.is_null() {
+            self.device
+                .free_command_buffers(self.data.command_pool, &[readback.command_buffer]);
+        }
+        self.device.destroy_buffer(readback.buffer, None);
+        self.device.free_memory(readback.memory, None);
+    }
+
+    /// Waits for `fence`, signaled once the frame submitted along with
+    /// `readback` completes, then returns the RGBA pixel and releases the
+    /// readback resources.
+    ///
+    /// # Safety
+    ///
+    /// `fence` must be the fence of the submission `readback` was part of.
+    pub unsafe fn finish_pixel_readback(
+        &self,
+        readback: PixelReadback,
+        fence: vk::Fence,
+    ) -> Result<[u8; 4]> {
+        self.device.wait_for_fences(&[fence], true, u64::MAX)?;
+
+        let mut pixel = [0u8; 4];
+        let mapped = self
+            .device
+            .map_memory(readback.memory, 0, 4, vk::MemoryMapFlags::empty());
+        if let Ok(memory_ptr) = mapped {
+            std::ptr::copy_nonoverlapping(memory_ptr.cast(), pixel.as_mut_ptr(), 4);
+            self.device.unmap_memory(readback.memory);
+        }
+
+        self.release_pixel_readback(&readback);
+
+        mapped?;
+        Ok(to_rgba(pixel, self.data.swapchain_format))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bgra_swapchains_are_swizzled() {
+        let pixel = [10, 20, 30, 40];
+        assert_eq!(to_rgba(pixel, vk::Format::B8G8R8A8_SRGB), [30, 20, 10, 40]);
+        assert_eq!(to_rgba(pixel, vk::Format::B8G8R8A8_UNORM), [30, 20, 10, 40]);
+        assert_eq!(to_rgba(pixel, vk::Format::R8G8B8A8_SRGB), pixel);
+    }
+
+    #[test]
+    fn pixels_must_lie_within_the_extent() {
+        let extent = vk::Extent2D {
+            width: 800,
+            height: 600,
+        };
+        assert!(in_extent(0, 0, extent));
+        assert!(in_extent(799, 599, extent));
+        assert!(!in_extent(800, 0, extent));
+        assert!(!in_extent(0, 600, extent));
+    }
+}
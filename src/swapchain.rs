@@ -1,9 +1,11 @@
-use crate::{buffers, depth, descriptor, pipeline, textures, App, AppData};
+use crate::{buffers, depth, descriptor, offscreen, pipeline, textures, timing, App, AppData};
 
-use anyhow::{Ok, Result};
+use anyhow::Result;
 use log::*;
 
 use vulkanalia::vk::KhrSwapchainExtension;
+use vulkanalia::window as vk_window;
+use vulkanalia::VkResult;
 use vulkanalia::{prelude::v1_2::*, vk::KhrSurfaceExtension};
 use winit::window::Window;
 
@@ -14,27 +16,106 @@ impl App {
         self.device.device_wait_idle()?;
 
         self.destroy_swapchain();
+        self.create_swapchain_objects(window)
+    }
+
+    /// Recreates the surface after it was lost, then the swapchain presenting
+    /// to it.
+    ///
+    /// # Safety
+    ///
+    /// `window` must be the window the surface was created for, and no frame
+    /// may be recorded while the swapchain objects are rebuilt.
+    pub unsafe fn recreate_surface(&mut self, window: &Window) -> Result<()> {
+        warn!("Surface lost, recreating it.");
+        self.device.device_wait_idle()?;
 
+        self.destroy_swapchain();
+        self.instance.destroy_surface_khr(self.data.surface, None);
+        self.data.surface = vk_window::create_surface(&self.instance, &window, &window)?;
+        self.create_swapchain_objects(window)
+    }
+
+    /// Creates the swapchain and everything depending on it.
+    unsafe fn create_swapchain_objects(&mut self, window: &Window) -> Result<()> {
         create_swapchain(window, &self.instance, &self.device, &mut self.data)?;
         create_swapchain_image_views(&self.device, &mut self.data)?;
-        pipeline::create_render_pass(&self.instance, &self.device, &mut self.data)?;
+        pipeline::create_render_pass(&self.device, &mut self.data)?;
         pipeline::create(&self.device, &mut self.data)?;
         depth::create_depth_objects(&self.instance, &self.device, &mut self.data)?;
         buffers::create_framebuffers(&self.device, &mut self.data)?;
         descriptor::create_uniform_buffers(&self.instance, &self.device, &mut self.data)?;
+        buffers::create_indirect_buffers(&self.instance, &self.device, &mut self.data)?;
+        timing::create_query_pool(&self.device, &mut self.data)?;
         descriptor::create_descriptor_pool(&self.device, &mut self.data)?;
         descriptor::create_descriptor_sets(&self.device, &mut self.data)?;
         buffers::create_command_buffers(&self.device, &mut self.data)?;
         self.data
             .images_in_flight
             .resize(self.data.swapchain_images.len(), vk::Fence::null());
+        // The aspect ratio changed, frame the model again.
+        self.fit_camera();
         Ok(())
     }
 
+    /// Rebuilds the graphics pipeline alone, leaving the swapchain and buffers
+    /// untouched. The previous pipeline is kept if the new one fails to build.
+    ///
+    /// # Safety
+    ///
+    /// The render pass and descriptor set layout must be alive, and no frame
+    /// may be recorded while the pipelines are swapped.
+    pub unsafe fn recreate_pipeline(&mut self) -> Result<()> {
+        self.device.device_wait_idle()?;
+
+        let pipelines = [
+            self.data.pipeline,
+            self.data.point_pipeline,
+            self.data.outline_pipeline,
+            self.data.gizmo_pipeline,
+            self.data.post_pipeline,
+            self.data.bounds_pipeline,
+        ];
+        let pipeline_layout = self.data.pipeline_layout;
+        if let Err(error) = pipeline::create(&self.device, &mut self.data) {
+            [
+                self.data.pipeline,
+                self.data.point_pipeline,
+                self.data.outline_pipeline,
+                self.data.gizmo_pipeline,
+                self.data.post_pipeline,
+                self.data.bounds_pipeline,
+            ] = pipelines;
+            self.data.pipeline_layout = pipeline_layout;
+            return Err(error);
+        }
+        for pipeline in pipelines {
+            self.device.destroy_pipeline(pipeline, None);
+        }
+        self.device.destroy_pipeline_layout(pipeline_layout, None);
+
+        // The command buffers bind the pipeline, record them again.
+        buffers::record_command_buffers(&self.device, &self.data)
+    }
+
+    /// Records the command buffers again, for the settings only affecting
+    /// the draws.
+    ///
+    /// # Safety
+    ///
+    /// Every object bound by the command buffers, from the pipelines to the
+    /// vertex buffers, must be alive.
+    pub unsafe fn rerecord_command_buffers(&mut self) -> Result<()> {
+        self.device.device_wait_idle()?;
+        buffers::record_command_buffers(&self.device, &self.data)
+    }
+
     pub unsafe fn destroy_swapchain(&mut self) {
         // Image depth
         self.device
             .destroy_image_view(self.data.depth_image_view, None);
+        self.device
+            .destroy_image_view(self.data.depth_sample_view, None);
         self.device.free_memory(self.data.depth_image_memory, None);
         self.device.destroy_image(self.data.depth_image, None);
         // Destroy descriptor buffers
@@ -44,10 +125,16 @@ impl App {
             .uniform_buffers
             .iter()
             .for_each(|b| self.device.destroy_buffer(*b, None));
+        self.data
+            .uniform_buffers_memory
+            .iter()
+            .for_each(|m| self.device.unmap_memory(*m));
         self.data
             .uniform_buffers_memory
             .iter()
             .for_each(|m| self.device.free_memory(*m, None));
+        buffers::destroy_indirect_buffers(&self.device, &self.data);
+        self.device.destroy_query_pool(self.data.query_pool, None);
 
         self.device
             .free_command_buffers(self.data.command_pool, &self.data.command_buffers);
@@ -56,6 +143,13 @@ impl App {
             .iter()
             .for_each(|framebuffer| self.device.destroy_framebuffer(*framebuffer, None));
         self.device.destroy_pipeline(self.data.pipeline, None);
+        self.device.destroy_pipeline(self.data.point_pipeline, None);
+        self.device
+            .destroy_pipeline(self.data.outline_pipeline, None);
+        self.device.destroy_pipeline(self.data.gizmo_pipeline, None);
+        self.device.destroy_pipeline(self.data.post_pipeline, None);
+        self.device
+            .destroy_pipeline(self.data.bounds_pipeline, None);
         self.device
             .destroy_pipeline_layout(self.data.pipeline_layout, None);
         self.device.destroy_render_pass(self.data.render_pass, None);
@@ -63,10 +157,49 @@ impl App {
             .swapchain_images_views
             .iter()
             .for_each(|image_view| self.device.destroy_image_view(*image_view, None));
+        offscreen::destroy_offscreen_images(&self.device, &self.data);
         self.device.destroy_swapchain_khr(self.data.swapchain, None);
     }
 }
 
+/// What to do after acquiring or presenting a swapchain image.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SwapchainStatus {
+    /// The swapchain is still usable as is.
+    Ok,
+    /// The swapchain no longer matches the surface and must be recreated.
+    Recreate,
+    /// The surface was lost, it must be recreated along with the swapchain.
+    RecreateSurface,
+    /// An unrecoverable error occured.
+    Fatal(vk::ErrorCode),
+}
+
+impl SwapchainStatus {
+    pub fn from_result(result: VkResult<vk::SuccessCode>) -> Self {
+        match result {
+            Ok(vk::SuccessCode::SUBOPTIMAL_KHR) | Err(vk::ErrorCode::OUT_OF_DATE_KHR) => {
+                Self::Recreate
+            }
+            Err(vk::ErrorCode::SURFACE_LOST_KHR) => Self::RecreateSurface,
+            Ok(_) => Self::Ok,
+            Err(error) => Self::Fatal(error),
+        }
+    }
+
+    /// Status of the swapchain after a frame failed to render with `error`.
+    /// Errors which don't come from Vulkan are fatal.
+    pub fn from_error(error: &anyhow::Error) -> Self {
+        let code = error
+            .chain()
+            .find_map(|e| e.downcast_ref::<vk::ErrorCode>());
+        match code {
+            Some(&code) => Self::from_result(Err(code)),
+            None => Self::Fatal(vk::ErrorCode::UNKNOWN),
+        }
+    }
+}
+
 pub unsafe fn create_swapchain(
     window: &Window,
     instance: &Instance,
@@ -80,12 +213,11 @@ pub unsafe fn create_swapchain(
     let present_mode = get_swapchain_present_mode(&support.present_modes);
     let extent = get_swapchain_extent(window, support.capabilities);
 
-    let mut image_count = support.capabilities.min_image_count + 1;
-    if support.capabilities.max_image_count != 0
-        && image_count > support.capabilities.max_image_count
-    {
-        image_count = support.capabilities.max_image_count;
-    }
+    let image_count = get_swapchain_image_count(data.swapchain_image_count, support.capabilities);
+
+    // Copying from the images lets `App::read_pixel` read the presented frame.
+    let image_usage = vk::ImageUsageFlags::COLOR_ATTACHMENT
+        | (support.capabilities.supported_usage_flags & vk::ImageUsageFlags::TRANSFER_SRC);
 
     let mut queue_family_indices = vec![];
     let image_sharing_mode = if indices.graphics() != indices.present() {
@@ -103,7 +235,7 @@ pub unsafe fn create_swapchain(
         .image_color_space(surface_format.color_space)
         .image_extent(extent)
         .image_array_layers(1)
-        .image_usage(vk::ImageUsageFlags::COLOR_ATTACHMENT)
+        .image_usage(image_usage)
         .image_sharing_mode(image_sharing_mode)
         .queue_family_indices(&queue_family_indices)
         .pre_transform(support.capabilities.current_transform)
@@ -114,6 +246,7 @@ pub unsafe fn create_swapchain(
 
     data.swapchain_format = surface_format.format;
     data.swapchain_extent = extent;
+    data.swapchain_usage = image_usage;
 
     data.swapchain = device.create_swapchain_khr(&swapchain_info, None)?;
 
@@ -165,6 +298,37 @@ fn get_swapchain_extent(window: &Window, capabilities: vk::SurfaceCapabilitiesKH
     }
 }
 
+/// Width over height of `extent`. A zero size counts as one pixel so the
+/// projection never gets a NaN or infinite aspect.
+pub fn aspect_ratio(extent: vk::Extent2D) -> f32 {
+    extent.width.max(1) as f32 / extent.height.max(1) as f32
+}
+
+/// Clamps the desired image count, one more than the minimum by default, to
+/// what the surface supports. A `max_image_count` of 0 means no maximum.
+fn get_swapchain_image_count(
+    desired: Option<u32>,
+    capabilities: vk::SurfaceCapabilitiesKHR,
+) -> u32 {
+    let image_count = desired.unwrap_or(capabilities.min_image_count + 1);
+    let image_count = image_count.max(capabilities.min_image_count);
+    if capabilities.max_image_count != 0 {
+        image_count.min(capabilities.max_image_count)
+    } else {
+        image_count
+    }
+}
+
+/// Reads the desired swapchain image count from `SCOP_SWAPCHAIN_IMAGES`.
+pub fn image_count_from_env() -> Option<u32> {
+    let value = std::env::var("SCOP_SWAPCHAIN_IMAGES").ok()?;
+    let count = value.parse::<u32>().ok();
+    if count.is_none() {
+        warn!("Invalid SCOP_SWAPCHAIN_IMAGES: {}", value);
+    }
+    count
+}
+
 pub unsafe fn create_swapchain_image_views(device: &Device, data: &mut AppData) -> Result<()> {
     data.swapchain_images_views = data
         .swapchain_images
@@ -214,3 +378,93 @@ impl SwapchainSupport {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::{vulkan_perspective, Deg};
+
+    #[test]
+    fn acquire_and_present_results() {
+        assert_eq!(
+            SwapchainStatus::from_result(Ok(vk::SuccessCode::SUCCESS)),
+            SwapchainStatus::Ok
+        );
+        assert_eq!(
+            SwapchainStatus::from_result(Ok(vk::SuccessCode::SUBOPTIMAL_KHR)),
+            SwapchainStatus::Recreate
+        );
+        assert_eq!(
+            SwapchainStatus::from_result(Err(vk::ErrorCode::OUT_OF_DATE_KHR)),
+            SwapchainStatus::Recreate
+        );
+        assert_eq!(
+            SwapchainStatus::from_result(Err(vk::ErrorCode::DEVICE_LOST)),
+            SwapchainStatus::Fatal(vk::ErrorCode::DEVICE_LOST)
+        );
+    }
+
+    fn capabilities(min_image_count: u32, max_image_count: u32) -> vk::SurfaceCapabilitiesKHR {
+        vk::SurfaceCapabilitiesKHR {
+            min_image_count,
+            max_image_count,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn image_count_is_clamped_to_the_surface() {
+        assert_eq!(get_swapchain_image_count(None, capabilities(2, 8)), 3);
+        assert_eq!(get_swapchain_image_count(None, capabilities(2, 2)), 2);
+        assert_eq!(get_swapchain_image_count(Some(1), capabilities(2, 8)), 2);
+        assert_eq!(get_swapchain_image_count(Some(5), capabilities(2, 8)), 5);
+        assert_eq!(get_swapchain_image_count(Some(16), capabilities(2, 8)), 8);
+    }
+
+    #[test]
+    fn image_count_has_no_maximum_at_zero() {
+        assert_eq!(get_swapchain_image_count(None, capabilities(2, 0)), 3);
+        assert_eq!(get_swapchain_image_count(Some(16), capabilities(2, 0)), 16);
+    }
+
+    #[test]
+    fn flat_extents_keep_the_projection_finite() {
+        for (width, height) in [(800, 0), (800, 1), (0, 0), (0, 600)] {
+            let aspect = aspect_ratio(vk::Extent2D { width, height });
+            assert!(aspect.is_finite() && aspect > 0.0);
+            let proj = vulkan_perspective(Deg(45.0), aspect, 0.1, 100.0);
+            for i in 0..4 {
+                let column = proj[i];
+                assert!([column.x, column.y, column.z, column.w]
+                    .iter()
+                    .all(|c| c.is_finite()));
+            }
+        }
+    }
+
+    #[test]
+    fn lost_surfaces_are_recreated() {
+        assert_eq!(
+            SwapchainStatus::from_result(Err(vk::ErrorCode::SURFACE_LOST_KHR)),
+            SwapchainStatus::RecreateSurface
+        );
+    }
+
+    #[test]
+    fn render_errors_decide_between_recovery_and_exit() {
+        let out_of_date = anyhow::Error::new(vk::ErrorCode::OUT_OF_DATE_KHR).context("present");
+        assert_eq!(
+            SwapchainStatus::from_error(&out_of_date),
+            SwapchainStatus::Recreate
+        );
+        let device_lost = anyhow::Error::new(vk::ErrorCode::DEVICE_LOST);
+        assert_eq!(
+            SwapchainStatus::from_error(&device_lost),
+            SwapchainStatus::Fatal(vk::ErrorCode::DEVICE_LOST)
+        );
+        assert_eq!(
+            SwapchainStatus::from_error(&anyhow::anyhow!("not from Vulkan")),
+            SwapchainStatus::Fatal(vk::ErrorCode::UNKNOWN)
+        );
+    }
+}
@@ -1,33 +1,206 @@
 use anyhow::{anyhow, Result};
 use std::fs::File;
+use std::io::Read;
 use std::ptr::copy_nonoverlapping as memcpy;
 use vk::MemoryBarrier;
 use vulkanalia::prelude::v1_2::*;
 
 use crate::{
     buffers::{self, begin_single_time_commands, end_single_time_commands},
+    color,
     device::get_memory_type_index,
     AppData,
 };
 
-pub unsafe fn create_texture_image(
+/// Size in pixels of the generated textures.
+const GENERATED_TEXTURE_SIZE: u32 = 256;
+
+/// Texture of the default model. Its embedded copy is loaded when the file
+/// is missing from the working directory.
+pub const DEFAULT_TEXTURE_PATH: &str = "./resources/orange_texture.png";
+const DEFAULT_TEXTURE: &[u8] = include_bytes!("../resources/orange_texture.png");
+
+/// RGBA pixels ready to be uploaded to a texture.
+#[derive(Clone, Debug)]
+pub struct DecodedImage {
+    pub width: u32,
+    pub height: u32,
+    pub format: vk::Format,
+    pub pixels: Vec<u8>,
+}
+
+/// Procedural textures used when no texture file is given.
+///
+/// Like PNG files, their pixels are authored in sRGB space and uploaded as
+/// `R8G8B8A8_SRGB`, so sampling decodes them to linear colors. A byte of 200
+/// is therefore displayed as 200 on the sRGB swapchain, not as a linear 0.78.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum GeneratedTexture {
+    #[default]
+    Checkerboard,
+    UvGrid,
+    Solid,
+}
+
+impl GeneratedTexture {
+    pub fn next(self) -> Self {
+        match self {
+            GeneratedTexture::Checkerboard => GeneratedTexture::UvGrid,
+            GeneratedTexture::UvGrid => GeneratedTexture::Solid,
+            GeneratedTexture::Solid => GeneratedTexture::Checkerboard,
+        }
+    }
+
+    pub fn generate(self, size: u32) -> DecodedImage {
+        match self {
+            GeneratedTexture::Checkerboard => generate_checkerboard(size),
+            GeneratedTexture::UvGrid => generate_uv_grid(size),
+            // A light gray, about 0.58 once decoded to linear.
+            GeneratedTexture::Solid => generate_image(size, |_, _| [200, 200, 200, 255]),
+        }
+    }
+}
+
+/// Builds a square image from the sRGB encoded color of each pixel.
+fn generate_image(size: u32, pixel: impl Fn(u32, u32) -> [u8; 4]) -> DecodedImage {
+    let mut pixels = Vec::with_capacity((size * size * 4) as usize);
+    for y in 0..size {
+        for x in 0..size {
+            pixels.extend_from_slice(&pixel(x, y));
+        }
+    }
+    DecodedImage {
+        width: size,
+        height: size,
+        format: vk::Format::R8G8B8A8_SRGB,
+        pixels,
+    }
+}
+
+/// A black and white checkerboard of 8 x 8 cells.
+pub fn generate_checkerboard(size: u32) -> DecodedImage {
+    let cell = (size / 8).max(1);
+    generate_image(size, |x, y| {
+        if (x / cell + y / cell).is_multiple_of(2) {
+            [255, 255, 255, 255]
+        } else {
+            [0, 0, 0, 255]
+        }
+    })
+}
+
+/// The U and V coordinates as red and green, with grid lines every 1/8.
+pub fn generate_uv_grid(size: u32) -> DecodedImage {
+    let cell = (size / 8).max(1);
+    generate_image(size, |x, y| {
+        if x % cell == 0 || y % cell == 0 {
+            [255, 255, 255, 255]
+        } else {
+            let u = (x * 255 / size) as u8;
+            let v = (y * 255 / size) as u8;
+            [u, v, 0, 255]
+        }
+    })
+}
+
+fn decode_png(texture_path: &str) -> Result<DecodedImage> {
+    match File::open(texture_path) {
+        Err(_) if texture_path == DEFAULT_TEXTURE_PATH => decode_png_from(DEFAULT_TEXTURE),
+        image => decode_png_from(image?),
+    }
+}
+
+fn decode_png_from<R: Read>(image: R) -> Result<DecodedImage> {
+    let mut decoder = png::Decoder::new(image);
+    // Palettes and low bit depths become 8 bit channels, expanded to RGBA
+    // below.
+    decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+    let mut reader = decoder.read_info()?;
+
+    let mut pixels = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut pixels)?;
+    pixels.truncate(info.buffer_size());
+    let channels = info.color_type.samples();
+
+    let (width, height) = reader.info().size();
+    Ok(DecodedImage {
+        width,
+        height,
+        format: texture_format(reader.info()),
+        pixels: color::expand_pixels_to_rgba(&pixels, channels)?,
+    })
+}
+
+/// Picks the texture format matching how the PNG is encoded.
+///
+/// PNGs are sRGB unless a `gAMA` chunk without an `sRGB` chunk says they are
+/// linear, in which case sampling them as sRGB would apply the gamma twice.
+fn texture_format(info: &png::Info) -> vk::Format {
+    match (info.srgb, info.gama_chunk) {
+        (None, Some(gamma)) if (gamma.into_value() - 1.0).abs() < 0.01 => {
+            vk::Format::R8G8B8A8_UNORM
+        }
+        _ => vk::Format::R8G8B8A8_SRGB,
+    }
+}
+
+/// Maximum number of textures of a model, the default one included. Those of
+/// further materials fall back to the default texture.
+pub const MAX_TEXTURES: usize = 16;
+
+/// A sampled image and its view.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Texture {
+    pub image: vk::Image,
+    pub memory: vk::DeviceMemory,
+    pub view: vk::ImageView,
+    pub mip_levels: u32,
+}
+
+/// Creates the default texture, from the PNG at `texture_path` or generated
+/// when there is none, followed by the textures of the materials.
+///
+/// A material texture failing to load is replaced by a generated one.
+pub unsafe fn create_texture_images(
     instance: &Instance,
     device: &Device,
     data: &mut AppData,
-    texture_path: String
+    texture_path: Option<&str>,
 ) -> Result<()> {
-    let image = File::open(texture_path)?;
+    let image = match texture_path {
+        Some(texture_path) => decode_png(texture_path)?,
+        None => data.generated_texture.generate(GENERATED_TEXTURE_SIZE),
+    };
+    data.textures = vec![create_texture(instance, device, data, image)?];
 
-    let decoder = png::Decoder::new(image);
-    let mut reader = decoder.read_info()?;
+    for path in data.texture_paths.clone() {
+        let image = decode_png(&path).unwrap_or_else(|error| {
+            log::warn!("Failed to load texture {}: {}", path, error);
+            data.generated_texture.generate(GENERATED_TEXTURE_SIZE)
+        });
+        let texture = create_texture(instance, device, data, image)?;
+        data.textures.push(texture);
+    }
 
-    let mut pixels = vec![0; reader.info().raw_bytes()];
-    reader.next_frame(&mut pixels)?;
+    Ok(())
+}
 
-    let size = reader.info().raw_bytes() as u64;
-    let (width, height) = reader.info().size();
+unsafe fn create_texture(
+    instance: &Instance,
+    device: &Device,
+    data: &mut AppData,
+    image: DecodedImage,
+) -> Result<Texture> {
+    let DecodedImage {
+        width,
+        height,
+        format,
+        pixels,
+    } = image;
 
-    data.mip_levels = (width.max(height) as f32).log2().floor() as u32 + 1;
+    let size = pixels.len() as u64;
+
+    let mip_levels = (width.max(height) as f32).log2().floor() as u32 + 1;
 
     let (staging_buffer, staging_buffer_memory) = buffers::create_buffer(
         instance,
@@ -50,8 +223,8 @@ pub unsafe fn create_texture_image(
         data,
         width,
         height,
-        data.mip_levels,
-        vk::Format::R8G8B8A8_SRGB,
+        mip_levels,
+        format,
         vk::ImageTiling::OPTIMAL,
         vk::ImageUsageFlags::SAMPLED
             | vk::ImageUsageFlags::TRANSFER_DST
@@ -59,43 +232,44 @@ pub unsafe fn create_texture_image(
         vk::MemoryPropertyFlags::DEVICE_LOCAL,
     )?;
 
-    data.texture_image = texture_image;
-    data.texture_image_memory = texture_image_memory;
-
-    transition_image_layout(
-        device,
-        data,
-        data.texture_image,
-        vk::Format::R8G8B8A8_SRGB,
-        vk::ImageLayout::UNDEFINED,
-        vk::ImageLayout::TRANSFER_DST_OPTIMAL,
-        data.mip_levels,
-    )?;
-
     copy_buffer_to_image(
         device,
         data,
         staging_buffer,
-        data.texture_image,
+        texture_image,
         width,
         height,
+        mip_levels,
     )?;
 
     generate_mipmaps(
         instance,
         device,
         data,
-        data.texture_image,
-        vk::Format::R8G8B8A8_SRGB,
+        texture_image,
+        format,
         width,
         height,
-        data.mip_levels,
+        mip_levels,
     )?;
 
     device.destroy_buffer(staging_buffer, None);
     device.free_memory(staging_buffer_memory, None);
 
-    Ok(())
+    let view = create_image_view(
+        device,
+        texture_image,
+        format,
+        vk::ImageAspectFlags::COLOR,
+        mip_levels,
+    )?;
+
+    Ok(Texture {
+        image: texture_image,
+        memory: texture_image_memory,
+        view,
+        mip_levels,
+    })
 }
 
 pub unsafe fn generate_mipmaps(
@@ -238,25 +412,44 @@ pub unsafe fn generate_mipmaps(
     Ok(())
 }
 
-pub unsafe fn create_texture_image_view(device: &Device, data: &mut AppData) -> Result<()> {
-    data.texture_image_view = create_image_view(
-        device,
-        data.texture_image,
-        vk::Format::R8G8B8A8_SRGB,
-        vk::ImageAspectFlags::COLOR,
-        data.mip_levels,
-    )?;
+/// Clamps the mip level bias to the `max` supported by the device.
+pub fn clamp_lod_bias(bias: f32, max: f32) -> f32 {
+    bias.clamp(-max, max)
+}
 
-    Ok(())
+/// Address mode following `mode`: repeat, mirrored repeat, then clamp to
+/// edge.
+pub fn next_address_mode(mode: vk::SamplerAddressMode) -> vk::SamplerAddressMode {
+    match mode {
+        vk::SamplerAddressMode::REPEAT => vk::SamplerAddressMode::MIRRORED_REPEAT,
+        vk::SamplerAddressMode::MIRRORED_REPEAT => vk::SamplerAddressMode::CLAMP_TO_EDGE,
+        _ => vk::SamplerAddressMode::REPEAT,
+    }
 }
 
+/// Creates the sampler of `data`, left untouched when the creation fails.
 pub unsafe fn create_texture_sampler(device: &Device, data: &mut AppData) -> Result<()> {
-    let info = vk::SamplerCreateInfo::builder()
+    data.texture_sampler = device.create_sampler(&sampler_info(data), None)?;
+
+    Ok(())
+}
+
+/// Sampler following the settings of `data`.
+fn sampler_info(data: &AppData) -> vk::SamplerCreateInfo {
+    // Shared by all textures, allow sampling the smallest mip of the largest.
+    let max_mip_levels = data
+        .textures
+        .iter()
+        .map(|t| t.mip_levels)
+        .max()
+        .unwrap_or(1);
+
+    vk::SamplerCreateInfo::builder()
         .mag_filter(vk::Filter::LINEAR)
         .min_filter(vk::Filter::LINEAR)
-        .address_mode_u(vk::SamplerAddressMode::REPEAT)
-        .address_mode_v(vk::SamplerAddressMode::REPEAT)
-        .address_mode_w(vk::SamplerAddressMode::REPEAT)
+        .address_mode_u(data.address_mode)
+        .address_mode_v(data.address_mode)
+        .address_mode_w(data.address_mode)
         .anisotropy_enable(true)
         .max_anisotropy(16.0)
         .border_color(vk::BorderColor::INT_OPAQUE_BLACK)
@@ -264,13 +457,10 @@ pub unsafe fn create_texture_sampler(device: &Device, data: &mut AppData) -> Res
         .compare_enable(false)
         .compare_op(vk::CompareOp::ALWAYS)
         .mipmap_mode(vk::SamplerMipmapMode::LINEAR)
-        .mip_lod_bias(0.0)
+        .mip_lod_bias(data.mip_lod_bias)
         .min_lod(0.0)
-        .max_lod(data.mip_levels as f32);
-
-    data.texture_sampler = device.create_sampler(&info, None)?;
-
-    Ok(())
+        .max_lod(max_mip_levels as f32)
+        .build()
 }
 
 pub unsafe fn create_image(
@@ -344,6 +534,8 @@ pub unsafe fn create_image_view(
     Ok(device.create_image_view(&create_info, None)?)
 }
 
+/// Moves the `mip_levels` first levels of `image` from `old_layout` to
+/// `new_layout` with a pipeline barrier, waiting for it to complete.
 pub unsafe fn transition_image_layout(
     device: &Device,
     data: &AppData,
@@ -354,28 +546,7 @@ pub unsafe fn transition_image_layout(
     mip_levels: u32,
 ) -> Result<()> {
     let (src_access_mask, dst_access_mask, src_stage_mask, dst_stage_mask) =
-        match (old_layout, new_layout) {
-            (vk::ImageLayout::UNDEFINED, vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL) => (
-                vk::AccessFlags::empty(),
-                vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_READ
-                    | vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE,
-                vk::PipelineStageFlags::TOP_OF_PIPE,
-                vk::PipelineStageFlags::EARLY_FRAGMENT_TESTS,
-            ),
-            (vk::ImageLayout::UNDEFINED, vk::ImageLayout::TRANSFER_DST_OPTIMAL) => (
-                vk::AccessFlags::empty(),
-                vk::AccessFlags::TRANSFER_WRITE,
-                vk::PipelineStageFlags::TOP_OF_PIPE,
-                vk::PipelineStageFlags::TRANSFER,
-            ),
-            (vk::ImageLayout::TRANSFER_DST_OPTIMAL, vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL) => (
-                vk::AccessFlags::TRANSFER_WRITE,
-                vk::AccessFlags::SHADER_READ,
-                vk::PipelineStageFlags::TRANSFER,
-                vk::PipelineStageFlags::FRAGMENT_SHADER,
-            ),
-            _ => return Err(anyhow!("unsupported image layout transition")),
-        };
+        transition_masks(old_layout, new_layout)?;
 
     let command_buffer = begin_single_time_commands(device, data)?;
 
@@ -422,6 +593,55 @@ pub unsafe fn transition_image_layout(
     Ok(())
 }
 
+/// Access masks and pipeline stages, source then destination, of the
+/// barrier between two image layouts.
+fn transition_masks(
+    old_layout: vk::ImageLayout,
+    new_layout: vk::ImageLayout,
+) -> Result<(
+    vk::AccessFlags,
+    vk::AccessFlags,
+    vk::PipelineStageFlags,
+    vk::PipelineStageFlags,
+)> {
+    let masks = match (old_layout, new_layout) {
+        (vk::ImageLayout::UNDEFINED, vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL) => (
+            vk::AccessFlags::empty(),
+            vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_READ
+                | vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE,
+            vk::PipelineStageFlags::TOP_OF_PIPE,
+            vk::PipelineStageFlags::EARLY_FRAGMENT_TESTS,
+        ),
+        (vk::ImageLayout::UNDEFINED, vk::ImageLayout::TRANSFER_DST_OPTIMAL) => (
+            vk::AccessFlags::empty(),
+            vk::AccessFlags::TRANSFER_WRITE,
+            vk::PipelineStageFlags::TOP_OF_PIPE,
+            vk::PipelineStageFlags::TRANSFER,
+        ),
+        (vk::ImageLayout::TRANSFER_DST_OPTIMAL, vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL) => (
+            vk::AccessFlags::TRANSFER_WRITE,
+            vk::AccessFlags::SHADER_READ,
+            vk::PipelineStageFlags::TRANSFER,
+            vk::PipelineStageFlags::FRAGMENT_SHADER,
+        ),
+        _ => {
+            return Err(anyhow!(
+                "unsupported image layout transition from {:?} to {:?}",
+                old_layout,
+                new_layout
+            ))
+        }
+    };
+    Ok(masks)
+}
+
+/// Copies the pixels of `buffer` to the first mip level of `image`, moving
+/// its `mip_levels` levels from the `UNDEFINED` layout to
+/// `TRANSFER_DST_OPTIMAL`.
+///
+/// The copy goes through the dedicated transfer queue when there is one. Its
+/// family then releases the image to the graphics one, which generates the
+/// mipmaps.
 pub unsafe fn copy_buffer_to_image(
     device: &Device,
     data: &AppData,
@@ -429,16 +649,122 @@ pub unsafe fn copy_buffer_to_image(
     image: vk::Image,
     width: u32,
     height: u32,
+    mip_levels: u32,
 ) -> Result<()> {
+    let command_buffer = buffers::begin_transfer_commands(device, data)?;
+
+    let (src_access_mask, dst_access_mask, src_stage_mask, dst_stage_mask) = transition_masks(
+        vk::ImageLayout::UNDEFINED,
+        vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+    )?;
+    let barrier = vk::ImageMemoryBarrier {
+        old_layout: vk::ImageLayout::UNDEFINED,
+        src_access_mask,
+        dst_access_mask,
+        ..upload_barrier(image, mip_levels)
+    };
+    device.cmd_pipeline_barrier(
+        command_buffer,
+        src_stage_mask,
+        dst_stage_mask,
+        vk::DependencyFlags::empty(),
+        &[] as &[vk::MemoryBarrier],
+        &[] as &[vk::BufferMemoryBarrier],
+        &[barrier],
+    );
+
+    device.cmd_copy_buffer_to_image(
+        command_buffer,
+        buffer,
+        image,
+        vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+        &[buffer_image_copy(width, height)],
+    );
+
+    let Some(transfer_family) = data.transfer_family else {
+        return buffers::end_transfer_commands(device, data, command_buffer);
+    };
+
+    let (release, acquire) =
+        ownership_transfer(image, mip_levels, transfer_family, data.graphics_family);
+    device.cmd_pipeline_barrier(
+        command_buffer,
+        vk::PipelineStageFlags::TRANSFER,
+        vk::PipelineStageFlags::BOTTOM_OF_PIPE,
+        vk::DependencyFlags::empty(),
+        &[] as &[vk::MemoryBarrier],
+        &[] as &[vk::BufferMemoryBarrier],
+        &[release],
+    );
+    buffers::end_transfer_commands(device, data, command_buffer)?;
+
     let command_buffer = begin_single_time_commands(device, data)?;
+    device.cmd_pipeline_barrier(
+        command_buffer,
+        vk::PipelineStageFlags::TOP_OF_PIPE,
+        vk::PipelineStageFlags::TRANSFER,
+        vk::DependencyFlags::empty(),
+        &[] as &[vk::MemoryBarrier],
+        &[] as &[vk::BufferMemoryBarrier],
+        &[acquire],
+    );
+    end_single_time_commands(device, data, command_buffer)
+}
+
+/// Barrier keeping the `mip_levels` levels of a texture being uploaded in
+/// the `TRANSFER_DST_OPTIMAL` layout, without any access nor queue family
+/// ownership transfer.
+fn upload_barrier(image: vk::Image, mip_levels: u32) -> vk::ImageMemoryBarrier {
+    let subresource = vk::ImageSubresourceRange::builder()
+        .aspect_mask(vk::ImageAspectFlags::COLOR)
+        .base_mip_level(0)
+        .level_count(mip_levels)
+        .base_array_layer(0)
+        .layer_count(1);
+
+    vk::ImageMemoryBarrier::builder()
+        .old_layout(vk::ImageLayout::TRANSFER_DST_OPTIMAL)
+        .new_layout(vk::ImageLayout::TRANSFER_DST_OPTIMAL)
+        .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+        .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+        .image(image)
+        .subresource_range(subresource)
+        .build()
+}
+
+/// Release and acquire barriers handing a texture uploaded by the `from`
+/// queue family over to the `to` one, which then writes its mip levels.
+fn ownership_transfer(
+    image: vk::Image,
+    mip_levels: u32,
+    from: u32,
+    to: u32,
+) -> (vk::ImageMemoryBarrier, vk::ImageMemoryBarrier) {
+    let barrier = vk::ImageMemoryBarrier {
+        src_queue_family_index: from,
+        dst_queue_family_index: to,
+        ..upload_barrier(image, mip_levels)
+    };
+    let release = vk::ImageMemoryBarrier {
+        src_access_mask: vk::AccessFlags::TRANSFER_WRITE,
+        ..barrier
+    };
+    let acquire = vk::ImageMemoryBarrier {
+        dst_access_mask: vk::AccessFlags::TRANSFER_READ | vk::AccessFlags::TRANSFER_WRITE,
+        ..barrier
+    };
+    (release, acquire)
+}
 
+/// Copy of tightly packed pixels to the whole first mip level of an image.
+fn buffer_image_copy(width: u32, height: u32) -> vk::BufferImageCopy {
     let subresource = vk::ImageSubresourceLayers::builder()
         .aspect_mask(vk::ImageAspectFlags::COLOR)
         .mip_level(0)
         .base_array_layer(0)
         .layer_count(1);
 
-    let region = vk::BufferImageCopy::builder()
+    vk::BufferImageCopy::builder()
         .buffer_offset(0)
         .buffer_row_length(0)
         .buffer_image_height(0)
@@ -448,17 +774,173 @@ pub unsafe fn copy_buffer_to_image(
             width,
             height,
             depth: 1,
-        });
+        })
+        .build()
+}
 
-    device.cmd_copy_buffer_to_image(
-        command_buffer,
-        buffer,
-        image,
-        vk::ImageLayout::TRANSFER_DST_OPTIMAL,
-        &[region],
-    );
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A 1 x 1 RGBA PNG, tagged with the given `gAMA` and `sRGB` chunks.
+    fn encode_png(gamma: Option<f32>, srgb: bool) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        let mut encoder = png::Encoder::new(&mut bytes, 1, 1);
+        encoder.set_color(png::ColorType::Rgba);
+        if let Some(gamma) = gamma {
+            encoder.set_source_gamma(png::ScaledFloat::new(gamma));
+        }
+        if srgb {
+            encoder.set_source_srgb(png::SrgbRenderingIntent::Perceptual);
+        }
+        let mut writer = encoder.write_header().unwrap();
+        writer.write_image_data(&[255, 128, 0, 255]).unwrap();
+        writer.finish().unwrap();
+        bytes
+    }
 
-    end_single_time_commands(device, data, command_buffer)?;
+    fn decoded_format(gamma: Option<f32>, srgb: bool) -> vk::Format {
+        decode_png_from(encode_png(gamma, srgb).as_slice())
+            .unwrap()
+            .format
+    }
 
-    Ok(())
+    #[test]
+    fn png_gamma_picks_the_texture_format() {
+        assert_eq!(decoded_format(None, false), vk::Format::R8G8B8A8_SRGB);
+        assert_eq!(decoded_format(None, true), vk::Format::R8G8B8A8_SRGB);
+        assert_eq!(
+            decoded_format(Some(1.0 / 2.2), false),
+            vk::Format::R8G8B8A8_SRGB
+        );
+        assert_eq!(decoded_format(Some(1.0), false), vk::Format::R8G8B8A8_UNORM);
+    }
+
+    /// Color of the pixel at `x`, `y` of `image`.
+    fn pixel(image: &DecodedImage, x: u32, y: u32) -> [u8; 4] {
+        let offset = ((y * image.width + x) * 4) as usize;
+        image.pixels[offset..offset + 4].try_into().unwrap()
+    }
+
+    #[test]
+    fn checkerboard_cells_alternate() {
+        let white = [255, 255, 255, 255];
+        let black = [0, 0, 0, 255];
+        // 8 x 8 cells of 8 pixels.
+        let image = generate_checkerboard(64);
+        assert_eq!(image.pixels.len(), 64 * 64 * 4);
+        assert_eq!(pixel(&image, 0, 0), white);
+        assert_eq!(pixel(&image, 7, 7), white);
+        assert_eq!(pixel(&image, 8, 0), black);
+        assert_eq!(pixel(&image, 0, 8), black);
+        assert_eq!(pixel(&image, 8, 8), white);
+        assert_eq!(pixel(&image, 63, 0), black);
+    }
+
+    #[test]
+    fn sampler_uses_the_clamped_lod_bias() {
+        let mut data = AppData {
+            max_sampler_lod_bias: 2.0,
+            ..Default::default()
+        };
+        data.mip_lod_bias = clamp_lod_bias(1.5, data.max_sampler_lod_bias);
+        assert_eq!(sampler_info(&data).mip_lod_bias, 1.5);
+        data.mip_lod_bias = clamp_lod_bias(4.0, data.max_sampler_lod_bias);
+        assert_eq!(sampler_info(&data).mip_lod_bias, 2.0);
+        data.mip_lod_bias = clamp_lod_bias(-4.0, data.max_sampler_lod_bias);
+        assert_eq!(sampler_info(&data).mip_lod_bias, -2.0);
+    }
+
+    #[test]
+    fn barriers_follow_the_layout_transition() {
+        use vk::{AccessFlags as Access, ImageLayout as Layout, PipelineStageFlags as Stage};
+
+        let upload = transition_masks(Layout::UNDEFINED, Layout::TRANSFER_DST_OPTIMAL).unwrap();
+        assert_eq!(
+            upload,
+            (
+                Access::empty(),
+                Access::TRANSFER_WRITE,
+                Stage::TOP_OF_PIPE,
+                Stage::TRANSFER
+            )
+        );
+
+        let sample = transition_masks(
+            Layout::TRANSFER_DST_OPTIMAL,
+            Layout::SHADER_READ_ONLY_OPTIMAL,
+        )
+        .unwrap();
+        assert_eq!(
+            sample,
+            (
+                Access::TRANSFER_WRITE,
+                Access::SHADER_READ,
+                Stage::TRANSFER,
+                Stage::FRAGMENT_SHADER
+            )
+        );
+
+        let (_, depth_access, _, depth_stage) =
+            transition_masks(Layout::UNDEFINED, Layout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL).unwrap();
+        assert!(depth_access.contains(Access::DEPTH_STENCIL_ATTACHMENT_WRITE));
+        assert_eq!(depth_stage, Stage::EARLY_FRAGMENT_TESTS);
+
+        assert!(transition_masks(Layout::GENERAL, Layout::PRESENT_SRC_KHR).is_err());
+    }
+
+    #[test]
+    fn copy_covers_the_whole_image() {
+        let region = buffer_image_copy(640, 480);
+        let extent = region.image_extent;
+        assert_eq!((extent.width, extent.height, extent.depth), (640, 480, 1));
+        assert_eq!((region.image_offset.x, region.image_offset.y), (0, 0));
+        // Rows are tightly packed.
+        assert_eq!(region.buffer_row_length, 0);
+        assert_eq!(region.image_subresource.mip_level, 0);
+    }
+
+    #[test]
+    fn sampler_uses_the_address_mode_on_every_axis() {
+        let mut data = AppData::default();
+        for _ in 0..3 {
+            data.address_mode = next_address_mode(data.address_mode);
+            let info = sampler_info(&data);
+            assert_eq!(info.address_mode_u, data.address_mode);
+            assert_eq!(info.address_mode_v, data.address_mode);
+            assert_eq!(info.address_mode_w, data.address_mode);
+        }
+        // The modes cycle back to the first one.
+        assert_eq!(data.address_mode, vk::SamplerAddressMode::REPEAT);
+    }
+
+    #[test]
+    fn generated_textures_are_srgb() {
+        let image = GeneratedTexture::Solid.generate(1);
+        assert_eq!(image.format, vk::Format::R8G8B8A8_SRGB);
+        let [r, g, b, a] = pixel(&image, 0, 0);
+        assert!(r == g && g == b && a == 255);
+        let linear = crate::math::srgb_to_linear(r as f32 / 255.0);
+        assert!((linear - 0.58).abs() < 0.005, "{}", linear);
+    }
+
+    #[test]
+    fn uploads_are_handed_to_the_graphics_family() {
+        let image = vk::Image::null();
+        let (release, acquire) = ownership_transfer(image, 9, 2, 0);
+        for barrier in [release, acquire] {
+            assert_eq!(barrier.src_queue_family_index, 2);
+            assert_eq!(barrier.dst_queue_family_index, 0);
+            assert_eq!(barrier.old_layout, vk::ImageLayout::TRANSFER_DST_OPTIMAL);
+            assert_eq!(barrier.new_layout, vk::ImageLayout::TRANSFER_DST_OPTIMAL);
+            assert_eq!(barrier.subresource_range.level_count, 9);
+        }
+        // The release only makes the copy available, the acquire visible.
+        assert_eq!(release.src_access_mask, vk::AccessFlags::TRANSFER_WRITE);
+        assert!(release.dst_access_mask.is_empty());
+        assert!(acquire.src_access_mask.is_empty());
+        assert!(acquire
+            .dst_access_mask
+            .contains(vk::AccessFlags::TRANSFER_READ | vk::AccessFlags::TRANSFER_WRITE));
+    }
 }
@@ -0,0 +1,138 @@
+use std::collections::VecDeque;
+
+use anyhow::{Ok, Result};
+use log::*;
+use vulkanalia::prelude::v1_2::*;
+
+use crate::buffers::{begin_single_time_commands, end_single_time_commands};
+use crate::AppData;
+
+/// Number of frames the GPU render pass duration is averaged over.
+pub const TIMING_WINDOW: usize = 60;
+
+/// Whether the render pass is timed on the GPU, from `SCOP_GPU_TIMINGS`.
+pub fn gpu_timings_from_env() -> bool {
+    std::env::var("SCOP_GPU_TIMINGS").is_ok_and(|value| value == "1" || value == "true")
+}
+
+/// Converts a number of timestamp ticks, each `timestamp_period`
+/// nanoseconds long, to milliseconds.
+pub fn ticks_to_ms(ticks: u64, timestamp_period: f32) -> f32 {
+    (ticks as f64 * timestamp_period as f64 / 1_000_000.0) as f32
+}
+
+/// Rolling average of the GPU render pass duration.
+#[derive(Clone, Debug, Default)]
+pub struct GpuTimings {
+    durations: VecDeque<f32>,
+    frames: usize,
+}
+
+impl GpuTimings {
+    /// Records the duration of a frame, in milliseconds, and logs the average
+    /// once every `TIMING_WINDOW` frames.
+    pub fn push(&mut self, duration: f32) {
+        if self.durations.len() == TIMING_WINDOW {
+            self.durations.pop_front();
+        }
+        self.durations.push_back(duration);
+
+        self.frames += 1;
+        if self.frames.is_multiple_of(TIMING_WINDOW) {
+            debug!("GPU render pass: {:.3} ms", self.average());
+        }
+    }
+
+    pub fn average(&self) -> f32 {
+        self.durations.iter().sum::<f32>() / self.durations.len().max(1) as f32
+    }
+}
+
+/// Creates a query pool with two timestamps per swapchain image, written
+/// before and after its render pass.
+///
+/// Nothing is created when the timings are off or the graphics queue has no
+/// timestamp support.
+pub unsafe fn create_query_pool(device: &Device, data: &mut AppData) -> Result<()> {
+    data.query_pool = vk::QueryPool::null();
+    if !data.gpu_timings_enabled || data.timestamp_valid_bits == 0 {
+        return Ok(());
+    }
+
+    let query_count = 2 * data.swapchain_images.len() as u32;
+    let info = vk::QueryPoolCreateInfo::builder()
+        .query_type(vk::QueryType::TIMESTAMP)
+        .query_count(query_count);
+    data.query_pool = device.create_query_pool(&info, None)?;
+
+    // Queries must be reset before their results are read, even when they
+    // were never written.
+    let command_buffer = begin_single_time_commands(device, data)?;
+    device.cmd_reset_query_pool(command_buffer, data.query_pool, 0, query_count);
+    end_single_time_commands(device, data, command_buffer)?;
+
+    Ok(())
+}
+
+/// Reads the render pass duration of the last frame drawn to `image_index`,
+/// if the GPU finished it.
+pub unsafe fn read_timestamps(
+    device: &Device,
+    data: &mut AppData,
+    image_index: usize,
+) -> Result<()> {
+    if data.query_pool.is_null() {
+        return Ok(());
+    }
+
+    let mut timestamps = [0u64; 2];
+    let bytes = std::slice::from_raw_parts_mut(
+        timestamps.as_mut_ptr().cast::<u8>(),
+        std::mem::size_of_val(&timestamps),
+    );
+    let result = device.get_query_pool_results(
+        data.query_pool,
+        2 * image_index as u32,
+        2,
+        bytes,
+        std::mem::size_of::<u64>() as u64,
+        vk::QueryResultFlags::_64,
+    )?;
+    if result != vk::SuccessCode::SUCCESS {
+        return Ok(());
+    }
+
+    // Only the low `timestamp_valid_bits` bits of the timestamps are meaningful.
+    let mask = u64::MAX >> (64 - data.timestamp_valid_bits.min(64));
+    let ticks = timestamps[1].wrapping_sub(timestamps[0]) & mask;
+    data.gpu_timings
+        .push(ticks_to_ms(ticks, data.timestamp_period));
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ticks_follow_the_timestamp_period() {
+        assert_eq!(ticks_to_ms(1_000_000, 1.0), 1.0);
+        assert_eq!(ticks_to_ms(0, 52.08), 0.0);
+        // A 12 MHz timer ticks every 83.33 ns.
+        assert!((ticks_to_ms(12_000, 83.333) - 1.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn average_covers_the_last_window() {
+        let mut timings = GpuTimings::default();
+        assert_eq!(timings.average(), 0.0);
+        for _ in 0..TIMING_WINDOW {
+            timings.push(1.0);
+        }
+        for _ in 0..TIMING_WINDOW / 2 {
+            timings.push(3.0);
+        }
+        assert_eq!(timings.average(), 2.0);
+    }
+}
@@ -1,12 +1,13 @@
 use crate::math::{Vec2, Vec3};
-use anyhow::{Ok, Result};
+use anyhow::{anyhow, Result};
 use vulkanalia::prelude::v1_2::*;
 
 use std::hash::{Hash, Hasher};
-use std::mem::size_of;
+use std::mem::{size_of, size_of_val};
 use std::ptr::copy_nonoverlapping as memcpy;
 
 use crate::buffers::{copy_buffer, create_buffer};
+use crate::device::unified_memory_type;
 use crate::AppData;
 
 #[repr(C)]
@@ -15,11 +16,22 @@ pub struct Vertex {
     pub pos: Vec3,
     pub color: Vec3,
     pub tex_coord: Vec2,
+    pub emissive: Vec3,
+    pub specular: Vec3,
+    pub shininess: f32,
+    /// Zero when the model has no normals.
+    pub normal: Vec3,
 }
 
 impl PartialEq for Vertex {
     fn eq(&self, other: &Self) -> bool {
-        self.pos == other.pos && self.color == other.color && self.tex_coord == other.tex_coord
+        self.pos == other.pos
+            && self.color == other.color
+            && self.tex_coord == other.tex_coord
+            && self.emissive == other.emissive
+            && self.specular == other.specular
+            && self.shininess == other.shininess
+            && self.normal == other.normal
     }
 }
 
@@ -35,15 +47,37 @@ impl Hash for Vertex {
         self.color[2].to_bits().hash(state);
         self.tex_coord[0].to_bits().hash(state);
         self.tex_coord[1].to_bits().hash(state);
+        self.emissive[0].to_bits().hash(state);
+        self.emissive[1].to_bits().hash(state);
+        self.emissive[2].to_bits().hash(state);
+        self.specular[0].to_bits().hash(state);
+        self.specular[1].to_bits().hash(state);
+        self.specular[2].to_bits().hash(state);
+        self.shininess.to_bits().hash(state);
+        self.normal[0].to_bits().hash(state);
+        self.normal[1].to_bits().hash(state);
+        self.normal[2].to_bits().hash(state);
     }
 }
 
 impl Vertex {
-    const fn new(pos: Vec3, color: Vec3, tex_coord: Vec2) -> Self {
+    pub const fn new(
+        pos: Vec3,
+        color: Vec3,
+        tex_coord: Vec2,
+        emissive: Vec3,
+        specular: Vec3,
+        shininess: f32,
+        normal: Vec3,
+    ) -> Self {
         Self {
             pos,
             color,
             tex_coord,
+            emissive,
+            specular,
+            shininess,
+            normal,
         }
     }
 
@@ -55,7 +89,26 @@ impl Vertex {
             .build()
     }
 
-    pub fn attribute_descriptions() -> [vk::VertexInputAttributeDescription; 3] {
+    /// Descriptions of the bindings, with the lightmap texture coordinates
+    /// in a second buffer at binding 1 only when `lightmap` is set. Models
+    /// without them keep the vertices as small.
+    pub fn binding_descriptions(lightmap: bool) -> Vec<vk::VertexInputBindingDescription> {
+        let mut descriptions = vec![Self::binding_description()];
+        if lightmap {
+            descriptions.push(
+                vk::VertexInputBindingDescription::builder()
+                    .binding(1)
+                    .stride(size_of::<Vec2>() as u32)
+                    .input_rate(vk::VertexInputRate::VERTEX)
+                    .build(),
+            );
+        }
+        descriptions
+    }
+
+    /// Descriptions of the attributes, with the lightmap texture coordinates
+    /// of binding 1 at location 7 only when `lightmap` is set.
+    pub fn attribute_descriptions(lightmap: bool) -> Vec<vk::VertexInputAttributeDescription> {
         let pos = vk::VertexInputAttributeDescription::builder()
             .binding(0)
             .location(0)
@@ -74,7 +127,42 @@ impl Vertex {
             .format(vk::Format::R32G32_SFLOAT)
             .offset((size_of::<Vec3>() + size_of::<Vec3>()) as u32)
             .build();
-        [pos, color, tex_coord]
+        let emissive = vk::VertexInputAttributeDescription::builder()
+            .binding(0)
+            .location(3)
+            .format(vk::Format::R32G32B32_SFLOAT)
+            .offset((size_of::<Vec3>() + size_of::<Vec3>() + size_of::<Vec2>()) as u32)
+            .build();
+        let specular = vk::VertexInputAttributeDescription::builder()
+            .binding(0)
+            .location(4)
+            .format(vk::Format::R32G32B32_SFLOAT)
+            .offset((size_of::<Vec3>() * 3 + size_of::<Vec2>()) as u32)
+            .build();
+        let shininess = vk::VertexInputAttributeDescription::builder()
+            .binding(0)
+            .location(5)
+            .format(vk::Format::R32_SFLOAT)
+            .offset((size_of::<Vec3>() * 4 + size_of::<Vec2>()) as u32)
+            .build();
+        let normal = vk::VertexInputAttributeDescription::builder()
+            .binding(0)
+            .location(6)
+            .format(vk::Format::R32G32B32_SFLOAT)
+            .offset((size_of::<Vec3>() * 4 + size_of::<Vec2>() + size_of::<f32>()) as u32)
+            .build();
+        let mut descriptions = vec![pos, color, tex_coord, emissive, specular, shininess, normal];
+        if lightmap {
+            descriptions.push(
+                vk::VertexInputAttributeDescription::builder()
+                    .binding(1)
+                    .location(7)
+                    .format(vk::Format::R32G32_SFLOAT)
+                    .offset(0)
+                    .build(),
+            );
+        }
+        descriptions
     }
 }
 
@@ -83,48 +171,150 @@ pub unsafe fn create_vertex_buffer(
     device: &Device,
     data: &mut AppData,
 ) -> Result<()> {
-    let size = (size_of::<Vertex>() * data.vertices.len()) as u64;
-
-    let (staging_buffer, staging_memory) = create_buffer(
+    let (vertex_buffer, vertex_memory) = upload_buffer(
         instance,
         device,
         data,
-        size,
-        vk::BufferUsageFlags::TRANSFER_SRC,
-        vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+        &data.vertices,
+        vk::BufferUsageFlags::VERTEX_BUFFER,
     )?;
+    data.vertex_buffer = vertex_buffer;
+    data.vertex_buffer_memory = vertex_memory;
 
-    let memory = device.map_memory(staging_memory, 0, size, vk::MemoryMapFlags::empty())?;
-
-    memcpy(data.vertices.as_ptr(), memory.cast(), data.vertices.len());
-
-    device.unmap_memory(staging_memory);
+    Ok(())
+}
 
-    let (vertex_buffer, vertex_memory) = create_buffer(
+/// Creates the buffer of `data.lightmap_tex_coords`, bound next to the
+/// vertex buffer. There is none when the model has no lightmap coordinates.
+pub unsafe fn create_lightmap_buffer(
+    instance: &Instance,
+    device: &Device,
+    data: &mut AppData,
+) -> Result<()> {
+    if !data.has_lightmap_tex_coords {
+        data.lightmap_buffer = vk::Buffer::null();
+        data.lightmap_buffer_memory = vk::DeviceMemory::null();
+        return Ok(());
+    }
+    let (lightmap_buffer, lightmap_memory) = upload_buffer(
         instance,
         device,
         data,
-        size,
-        vk::BufferUsageFlags::VERTEX_BUFFER | vk::BufferUsageFlags::TRANSFER_DST,
-        vk::MemoryPropertyFlags::DEVICE_LOCAL,
+        &data.lightmap_tex_coords,
+        vk::BufferUsageFlags::VERTEX_BUFFER,
     )?;
-    data.vertex_buffer = vertex_buffer;
-    data.vertex_buffer_memory = vertex_memory;
+    data.lightmap_buffer = lightmap_buffer;
+    data.lightmap_buffer_memory = lightmap_memory;
 
-    copy_buffer(device, data, staging_buffer, data.vertex_buffer, size)?;
+    Ok(())
+}
 
-    device.destroy_buffer(staging_buffer, None);
-    device.free_memory(staging_memory, None);
+pub unsafe fn create_index_buffer(
+    instance: &Instance,
+    device: &Device,
+    data: &mut AppData,
+) -> Result<()> {
+    data.index_type = index_type_for(data.vertices.len());
+
+    // Point clouds have no triangles, and Vulkan rejects empty buffers.
+    if data.indices.is_empty() {
+        data.index_buffer = vk::Buffer::null();
+        data.index_buffer_memory = vk::DeviceMemory::null();
+        return Ok(());
+    }
+
+    let (index_buffer, index_memory) = upload_indices(instance, device, data, &data.indices)?;
+    data.index_buffer = index_buffer;
+    data.index_buffer_memory = index_memory;
 
     Ok(())
 }
 
-pub unsafe fn create_index_buffer(
+pub unsafe fn create_point_index_buffer(
     instance: &Instance,
     device: &Device,
     data: &mut AppData,
 ) -> Result<()> {
-    let size = (size_of::<u32>() * data.indices.len()) as u64;
+    if data.point_indices.is_empty() {
+        data.point_index_buffer = vk::Buffer::null();
+        data.point_index_buffer_memory = vk::DeviceMemory::null();
+        return Ok(());
+    }
+
+    let (index_buffer, index_memory) = upload_indices(instance, device, data, &data.point_indices)?;
+    data.point_index_buffer = index_buffer;
+    data.point_index_buffer_memory = index_memory;
+
+    Ok(())
+}
+
+/// Smallest index type able to address `vertex_count` vertices.
+pub fn index_type_for(vertex_count: usize) -> vk::IndexType {
+    if vertex_count <= u16::MAX as usize {
+        vk::IndexType::UINT16
+    } else {
+        vk::IndexType::UINT32
+    }
+}
+
+/// Uploads `indices` to a device local index buffer through a staging buffer,
+/// narrowing them to `data.index_type`.
+unsafe fn upload_indices(
+    instance: &Instance,
+    device: &Device,
+    data: &AppData,
+    indices: &[u32],
+) -> Result<(vk::Buffer, vk::DeviceMemory)> {
+    let usage = vk::BufferUsageFlags::INDEX_BUFFER;
+    if data.index_type == vk::IndexType::UINT16 {
+        upload_buffer(instance, device, data, &narrow_indices(indices), usage)
+    } else {
+        upload_buffer(instance, device, data, indices, usage)
+    }
+}
+
+/// Converts `indices` to 16 bits, they must all fit.
+fn narrow_indices(indices: &[u32]) -> Vec<u16> {
+    indices.iter().map(|&i| i as u16).collect()
+}
+
+/// Whether buffers always go through a staging buffer, from
+/// `SCOP_FORCE_STAGING`.
+pub fn force_staging_from_env() -> bool {
+    force_staging(std::env::var("SCOP_FORCE_STAGING").ok().as_deref())
+}
+
+/// Whether buffers always go through a staging buffer, given the value of
+/// `SCOP_FORCE_STAGING`, if set.
+fn force_staging(value: Option<&str>) -> bool {
+    matches!(value, Some("1") | Some("true"))
+}
+
+/// Copies `elements` to a new device local buffer with the given `usage`,
+/// through a staging buffer unless the device memory is host visible.
+pub unsafe fn upload_buffer<T: Copy>(
+    instance: &Instance,
+    device: &Device,
+    data: &AppData,
+    elements: &[T],
+    usage: vk::BufferUsageFlags,
+) -> Result<(vk::Buffer, vk::DeviceMemory)> {
+    if elements.is_empty() {
+        return Err(anyhow!("Can't upload an empty buffer"));
+    }
+    let size = size_of_val(elements) as u64;
+
+    let unified = if data.force_staging {
+        None
+    } else {
+        create_unified_buffer(instance, device, data, size, usage)?
+    };
+    if let Some((buffer, memory)) = unified {
+        let mapped = device.map_memory(memory, 0, size, vk::MemoryMapFlags::empty())?;
+        memcpy(elements.as_ptr(), mapped.cast(), elements.len());
+        device.unmap_memory(memory);
+        return Ok((buffer, memory));
+    }
 
     let (staging_buffer, staging_memory) = create_buffer(
         instance,
@@ -137,25 +327,133 @@ pub unsafe fn create_index_buffer(
 
     let memory = device.map_memory(staging_memory, 0, size, vk::MemoryMapFlags::empty())?;
 
-    memcpy(data.indices.as_ptr(), memory.cast(), data.indices.len());
+    memcpy(elements.as_ptr(), memory.cast(), elements.len());
 
     device.unmap_memory(staging_memory);
 
-    let (index_buffer, index_memory) = create_buffer(
+    let (buffer, buffer_memory) = create_buffer(
         instance,
         device,
         data,
         size,
-        vk::BufferUsageFlags::INDEX_BUFFER | vk::BufferUsageFlags::TRANSFER_DST,
+        usage | vk::BufferUsageFlags::TRANSFER_DST,
         vk::MemoryPropertyFlags::DEVICE_LOCAL,
     )?;
-    data.index_buffer = index_buffer;
-    data.index_buffer_memory = index_memory;
 
-    copy_buffer(device, data, staging_buffer, data.index_buffer, size)?;
+    copy_buffer(device, data, staging_buffer, buffer, size)?;
 
     device.destroy_buffer(staging_buffer, None);
     device.free_memory(staging_memory, None);
 
-    Ok(())
+    Ok((buffer, buffer_memory))
+}
+
+/// Creates a buffer in memory both device local and host visible, if the
+/// device has a `unified_memory_type`.
+unsafe fn create_unified_buffer(
+    instance: &Instance,
+    device: &Device,
+    data: &AppData,
+    size: vk::DeviceSize,
+    usage: vk::BufferUsageFlags,
+) -> Result<Option<(vk::Buffer, vk::DeviceMemory)>> {
+    let buffer_info = vk::BufferCreateInfo::builder()
+        .size(size)
+        .usage(usage)
+        .sharing_mode(vk::SharingMode::EXCLUSIVE);
+    let buffer = device.create_buffer(&buffer_info, None)?;
+
+    let requirements = device.get_buffer_memory_requirements(buffer);
+    let memory_properties = instance.get_physical_device_memory_properties(data.physical_device);
+    let Some(memory_type_index) =
+        unified_memory_type(&memory_properties, requirements.memory_type_bits)
+    else {
+        device.destroy_buffer(buffer, None);
+        return Ok(None);
+    };
+
+    let allocate_info = vk::MemoryAllocateInfo::builder()
+        .allocation_size(requirements.size)
+        .memory_type_index(memory_type_index);
+    let memory = match device.allocate_memory(&allocate_info, None) {
+        Ok(memory) => memory,
+        Err(error) => {
+            device.destroy_buffer(buffer, None);
+            return Err(error.into());
+        }
+    };
+    if let Err(error) = device.bind_buffer_memory(buffer, memory, 0) {
+        device.destroy_buffer(buffer, None);
+        device.free_memory(memory, None);
+        return Err(error.into());
+    }
+
+    Ok(Some((buffer, memory)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn force_staging_reads_the_environment() {
+        assert!(!force_staging(None));
+        for (value, expected) in [("1", true), ("true", true), ("0", false), ("yes", false)] {
+            assert_eq!(force_staging(Some(value)), expected, "{}", value);
+        }
+    }
+
+    #[test]
+    fn index_type_follows_the_vertex_count() {
+        assert_eq!(index_type_for(3), vk::IndexType::UINT16);
+        assert_eq!(index_type_for(65535), vk::IndexType::UINT16);
+        assert_eq!(index_type_for(65536), vk::IndexType::UINT32);
+    }
+
+    #[test]
+    fn narrowed_indices_take_two_bytes() {
+        let indices = [0, 1, 2, 65534];
+        let narrowed = narrow_indices(&indices);
+        assert_eq!(narrowed, [0, 1, 2, 65534]);
+        assert_eq!(size_of_val(narrowed.as_slice()), indices.len() * 2);
+    }
+
+    #[test]
+    fn normal_reaches_the_shader() {
+        let descriptions = Vertex::attribute_descriptions(false);
+        let normal = descriptions.iter().find(|d| d.location == 6).unwrap();
+        assert_eq!(normal.offset as usize, std::mem::offset_of!(Vertex, normal));
+        assert_eq!(normal.format, vk::Format::R32G32B32_SFLOAT);
+    }
+
+    /// Only compiles with the prelude `Ok`, `anyhow::Ok` being an `anyhow::Result`.
+    fn parse_count(text: &str) -> std::result::Result<u32, String> {
+        match text.parse() {
+            Ok(count) => Ok(count),
+            Err(_) => Err(format!("invalid count {}", text)),
+        }
+    }
+
+    #[test]
+    fn ok_is_the_prelude_one() {
+        assert_eq!(parse_count("3"), Ok(3));
+        assert!(parse_count("three").is_err());
+    }
+
+    #[test]
+    fn lightmap_attribute_only_with_lightmap_tex_coords() {
+        assert_eq!(Vertex::attribute_descriptions(false).len(), 7);
+        assert_eq!(Vertex::binding_descriptions(false).len(), 1);
+
+        // The lightmap coordinates come from their own buffer.
+        let descriptions = Vertex::attribute_descriptions(true);
+        assert_eq!(descriptions.len(), 8);
+        let lightmap = descriptions.iter().find(|d| d.location == 7).unwrap();
+        assert_eq!((lightmap.binding, lightmap.offset), (1, 0));
+        assert_eq!(lightmap.format, vk::Format::R32G32_SFLOAT);
+        let bindings = Vertex::binding_descriptions(true);
+        assert_eq!(bindings[1].binding, 1);
+        assert_eq!(bindings[1].stride as usize, size_of::<Vec2>());
+        assert!(descriptions[..7].iter().all(|d| d.binding == 0));
+    }
 }
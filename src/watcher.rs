@@ -0,0 +1,127 @@
+use std::time::{Duration, Instant, SystemTime};
+
+/// Time a file must stay untouched before a change is reported.
+pub const DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// Minimum time between two checks of the files on disk.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Polls the modification time of a set of files and reports changes once
+/// they have settled, so editors writing a file in several steps only trigger
+/// a single reload.
+#[derive(Clone, Debug)]
+pub struct FileWatcher {
+    paths: Vec<String>,
+    modified: Vec<Option<SystemTime>>,
+    pending_since: Option<Instant>,
+    last_poll: Option<Instant>,
+    debounce: Duration,
+}
+
+impl FileWatcher {
+    pub fn new(debounce: Duration) -> Self {
+        Self {
+            paths: Vec::new(),
+            modified: Vec::new(),
+            pending_since: None,
+            last_poll: None,
+            debounce,
+        }
+    }
+
+    /// Checks the given files and returns `true` when a debounced change is
+    /// detected. Watching a different set of files resets the watcher.
+    pub fn poll(&mut self, paths: &[&str]) -> bool {
+        let now = Instant::now();
+
+        if self
+            .paths
+            .iter()
+            .map(String::as_str)
+            .ne(paths.iter().copied())
+        {
+            self.paths = paths.iter().map(|path| path.to_string()).collect();
+            self.modified = self.read_modified();
+            self.pending_since = None;
+            self.last_poll = Some(now);
+            return false;
+        }
+
+        if self
+            .last_poll
+            .is_some_and(|last| now.duration_since(last) < POLL_INTERVAL)
+        {
+            return false;
+        }
+        self.last_poll = Some(now);
+
+        let modified = self.read_modified();
+        self.update(modified, now)
+    }
+
+    fn read_modified(&self) -> Vec<Option<SystemTime>> {
+        self.paths
+            .iter()
+            .map(|path| std::fs::metadata(path).and_then(|m| m.modified()).ok())
+            .collect()
+    }
+
+    /// Debounce logic, independent of how modification times are obtained.
+    fn update(&mut self, modified: Vec<Option<SystemTime>>, now: Instant) -> bool {
+        if modified != self.modified {
+            // Restart the debounce window on every new write.
+            self.modified = modified;
+            self.pending_since = Some(now);
+            return false;
+        }
+
+        match self.pending_since {
+            Some(since) if now.duration_since(since) >= self.debounce => {
+                self.pending_since = None;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn watcher() -> (FileWatcher, Instant, Vec<Option<SystemTime>>) {
+        let mut watcher = FileWatcher::new(DEBOUNCE);
+        let initial = vec![Some(SystemTime::UNIX_EPOCH)];
+        watcher.modified = initial.clone();
+        (watcher, Instant::now(), initial)
+    }
+
+    #[test]
+    fn change_is_reported_once_settled() {
+        let (mut watcher, start, _) = watcher();
+        let changed = vec![Some(SystemTime::UNIX_EPOCH + Duration::from_secs(1))];
+        assert!(!watcher.update(changed.clone(), start));
+        assert!(!watcher.update(changed.clone(), start + DEBOUNCE / 2));
+        assert!(watcher.update(changed.clone(), start + DEBOUNCE));
+        // Reported a single time.
+        assert!(!watcher.update(changed, start + DEBOUNCE * 2));
+    }
+
+    #[test]
+    fn new_writes_restart_the_debounce() {
+        let (mut watcher, start, _) = watcher();
+        let first = vec![Some(SystemTime::UNIX_EPOCH + Duration::from_secs(1))];
+        let second = vec![Some(SystemTime::UNIX_EPOCH + Duration::from_secs(2))];
+        assert!(!watcher.update(first, start));
+        assert!(!watcher.update(second.clone(), start + DEBOUNCE / 2));
+        assert!(!watcher.update(second.clone(), start + DEBOUNCE));
+        assert!(watcher.update(second, start + DEBOUNCE / 2 + DEBOUNCE));
+    }
+
+    #[test]
+    fn unchanged_files_are_not_reported() {
+        let (mut watcher, start, initial) = watcher();
+        assert!(!watcher.update(initial.clone(), start));
+        assert!(!watcher.update(initial, start + DEBOUNCE * 2));
+    }
+}